@@ -243,9 +243,43 @@ fn bench_then(c: &mut Criterion) {
     });
 }
 
+fn bench_spanned(c: &mut Criterion) {
+    // A token stream of nested lists, such as `[[a, b], [c]]`, in which every token carries its own span
+    let source = "[[a, b], [c, [d, e, f]], []], ".repeat(1000);
+    let tokens = source
+        .char_indices()
+        .filter(|(_, c)| !c.is_whitespace())
+        .map(|(i, c)| (c, SimpleSpan::new(i, i + 1)))
+        .collect::<Vec<_>>();
+    let eoi = SimpleSpan::new(source.len(), source.len());
+
+    let list = recursive::<_, _, extra::Default, _, _>(|list| {
+        let item = any()
+            .filter(char::is_ascii_alphabetic)
+            .map_with_span(|_, span: SimpleSpan| span)
+            .or(list);
+        item.separated_by(just(','))
+            .collect::<Vec<_>>()
+            .delimited_by(just('['), just(']'))
+            .map_with_span(|_, span: SimpleSpan| span)
+    });
+    let lists = list
+        .separated_by(just(','))
+        .allow_trailing()
+        .collect::<Vec<_>>();
+
+    c.bench_function("spanned_slice", |b| {
+        b.iter(|| {
+            black_box(lists.parse(black_box(tokens.as_slice().spanned(eoi))))
+                .into_result()
+                .unwrap();
+        })
+    });
+}
+
 criterion_group!(
     name = benches;
     config = utils::make_criterion();
-    targets = bench_choice, bench_or, bench_group, bench_then,
+    targets = bench_choice, bench_or, bench_group, bench_then, bench_spanned,
 );
 criterion_main!(benches);
@@ -236,7 +236,8 @@ where
         self.parser.go::<Check>(inp)?;
         let after = inp.offset().offset;
 
-        Ok(M::bind(|| (self.mapper)(inp.slice_inner(before..after))))
+        let slice = inp.slice_or_fail(before..after)?;
+        Ok(M::bind(|| (self.mapper)(slice)))
     }
 
     go_extra!(U);
@@ -274,7 +275,8 @@ where
         self.parser.go::<Check>(inp)?;
         let after = inp.offset().offset;
 
-        Ok(M::bind(|| inp.slice_inner(before..after)))
+        let slice = inp.slice_or_fail(before..after)?;
+        Ok(M::bind(|| slice))
     }
 
     go_extra!(I::Slice);
@@ -467,6 +469,83 @@ where
     go_extra!(O);
 }
 
+/// See [`Parser::map_with`].
+pub struct MapWith<A, OA, F> {
+    pub(crate) parser: A,
+    pub(crate) mapper: F,
+    #[allow(dead_code)]
+    pub(crate) phantom: EmptyPhantom<OA>,
+}
+
+impl<A: Copy, OA, F: Copy> Copy for MapWith<A, OA, F> {}
+impl<A: Clone, OA, F: Clone> Clone for MapWith<A, OA, F> {
+    fn clone(&self) -> Self {
+        Self {
+            parser: self.parser.clone(),
+            mapper: self.mapper.clone(),
+            phantom: EmptyPhantom::new(),
+        }
+    }
+}
+
+impl<'a, I, O, E, A, OA, F> ParserSealed<'a, I, O, E> for MapWith<A, OA, F>
+where
+    I: Input<'a>,
+    E: ParserExtra<'a, I>,
+    A: Parser<'a, I, OA, E>,
+    F: Fn(OA, &mut MapExtra<'a, '_, '_, I, E>) -> O,
+{
+    #[inline(always)]
+    fn go<M: Mode>(&self, inp: &mut InputRef<'a, '_, I, E>) -> PResult<M, O> {
+        let before = inp.offset();
+        let out = self.parser.go::<Emit>(inp)?;
+        Ok(M::bind(|| {
+            (self.mapper)(out, &mut MapExtra::new(before, inp))
+        }))
+    }
+
+    go_extra!(O);
+}
+
+/// See [`Parser::map_slice_with_state`].
+pub struct MapSliceWithState<A, OA, F> {
+    pub(crate) parser: A,
+    pub(crate) mapper: F,
+    #[allow(dead_code)]
+    pub(crate) phantom: EmptyPhantom<OA>,
+}
+
+impl<A: Copy, OA, F: Copy> Copy for MapSliceWithState<A, OA, F> {}
+impl<A: Clone, OA, F: Clone> Clone for MapSliceWithState<A, OA, F> {
+    fn clone(&self) -> Self {
+        Self {
+            parser: self.parser.clone(),
+            mapper: self.mapper.clone(),
+            phantom: EmptyPhantom::new(),
+        }
+    }
+}
+
+impl<'a, I, O, E, A, OA, F> ParserSealed<'a, I, O, E> for MapSliceWithState<A, OA, F>
+where
+    I: SliceInput<'a>,
+    E: ParserExtra<'a, I>,
+    A: Parser<'a, I, OA, E>,
+    F: Fn(I::Slice, &mut E::State) -> O,
+{
+    #[inline(always)]
+    fn go<M: Mode>(&self, inp: &mut InputRef<'a, '_, I, E>) -> PResult<M, O> {
+        let before = inp.offset().offset;
+        self.parser.go::<Check>(inp)?;
+        let after = inp.offset().offset;
+
+        let slice = inp.slice_or_fail(before..after)?;
+        Ok(M::bind(|| (self.mapper)(slice, inp.state())))
+    }
+
+    go_extra!(O);
+}
+
 /// See [`Parser::try_map`].
 pub struct TryMap<A, OA, F> {
     pub(crate) parser: A,
@@ -706,7 +785,7 @@ where
             &self.parser as *const _ as *const () as usize,
         );
 
-        match inp.memos.entry(key) {
+        match inp.memos.parsers.entry(key) {
             hashbrown::hash_map::Entry::Occupied(o) => {
                 if let Some(err) = o.get() {
                     let err = err.clone();
@@ -725,12 +804,12 @@ where
         let res = self.parser.go::<M>(inp);
 
         if res.is_err() {
-            inp.memos.insert(
+            inp.memos.parsers.insert(
                 key,
                 Some(inp.errors.alt.clone().expect("failure but no alt?!")),
             );
         } else {
-            inp.memos.remove(&key);
+            inp.memos.parsers.remove(&key);
         }
 
         res
@@ -880,7 +959,7 @@ where
         let alt = inp.errors.alt.take();
 
         #[cfg(feature = "memoization")]
-        let mut memos = HashMap::default();
+        let mut memos = input::Memos::default();
         let res = inp.with_input(
             &inp2,
             |inp| (&self.parser_a).then_ignore(end()).go::<M>(inp),
@@ -921,12 +1000,12 @@ impl<A: Clone, B: Clone, OA, I, E> Clone for ThenWithCtx<A, B, OA, I, E> {
 }
 
 impl<'a, I, E, A, B, OA, OB> ParserSealed<'a, I, OB, E>
-    for ThenWithCtx<A, B, OA, I, extra::Full<E::Error, E::State, OA>>
+    for ThenWithCtx<A, B, OA, I, E>
 where
     I: Input<'a>,
     E: ParserExtra<'a, I>,
     A: Parser<'a, I, OA, E>,
-    B: Parser<'a, I, OB, extra::Full<E::Error, E::State, OA>>,
+    B: Parser<'a, I, OB, E::WithContext<OA>>,
     OA: 'a,
 {
     #[inline(always)]
@@ -939,12 +1018,12 @@ where
 }
 
 impl<'a, I, E, A, B, OA, OB> IterParserSealed<'a, I, OB, E>
-    for ThenWithCtx<A, B, OA, I, extra::Full<E::Error, E::State, OA>>
+    for ThenWithCtx<A, B, OA, I, E>
 where
     I: Input<'a>,
     E: ParserExtra<'a, I>,
     A: Parser<'a, I, OA, E>,
-    B: IterParser<'a, I, OB, extra::Full<E::Error, E::State, OA>>,
+    B: IterParser<'a, I, OB, E::WithContext<OA>>,
     OA: 'a,
 {
     type IterState<M: Mode> = (OA, B::IterState<M>)
@@ -993,7 +1072,7 @@ impl<'a, I, O, E, A, Ctx> ParserSealed<'a, I, O, E> for WithCtx<A, Ctx>
 where
     I: Input<'a>,
     E: ParserExtra<'a, I>,
-    A: Parser<'a, I, O, extra::Full<E::Error, E::State, Ctx>>,
+    A: Parser<'a, I, O, E::WithContext<Ctx>>,
     Ctx: 'a,
 {
     #[inline(always)]
@@ -1072,9 +1151,13 @@ where
 {
     #[inline(always)]
     fn go<M: Mode>(&self, inp: &mut InputRef<'a, '_, I, E>) -> PResult<M, OA> {
+        let before = inp.offset();
         self.padding.go::<Check>(inp)?;
+        inp.record_trivia(before);
         let a = self.parser.go::<M>(inp)?;
+        let before = inp.offset();
         self.padding.go::<Check>(inp)?;
+        inp.record_trivia(before);
         Ok(a)
     }
 
@@ -1234,16 +1317,18 @@ where
         if self.at_most == !0 && self.at_least == 0 {
             loop {
                 let before = inp.save();
-                match self.parser.go::<Check>(inp) {
-                    Ok(()) => {}
-                    Err(()) => {
+                match inp.go_alt(|inp| self.parser.go::<Check>(inp)) {
+                    Some(Ok(())) => {}
+                    // A committed item failed, so the repetition fails with it
+                    Some(Err(())) => break Err(()),
+                    None => {
                         inp.rewind(before);
                         break Ok(M::bind(|| ()));
                     }
                 }
                 #[cfg(debug_assertions)]
                 debug_assert!(
-                    before.offset() != inp.offset(),
+                    inp.advanced_since(before),
                     "found Repeated combinator making no progress at {}",
                     self.location,
                 );
@@ -1301,12 +1386,13 @@ where
         }
 
         let before = inp.save();
-        match self.parser.go::<M>(inp) {
-            Ok(item) => {
+        match inp.go_alt(|inp| self.parser.go::<M>(inp)) {
+            Some(Ok(item)) => {
                 *count += 1;
                 Ok(Some(item))
             }
-            Err(()) => {
+            Some(Err(())) => Err(()),
+            None => {
                 inp.rewind(before);
                 if *count >= self.at_least {
                     Ok(None)
@@ -1341,12 +1427,13 @@ where
         }
 
         let before = inp.save();
-        match self.parser.go::<M>(inp) {
-            Ok(item) => {
+        match inp.go_alt(|inp| self.parser.go::<M>(inp)) {
+            Some(Ok(item)) => {
                 *count += 1;
                 Ok(Some(item))
             }
-            Err(()) => {
+            Some(Err(())) => Err(()),
+            None => {
                 inp.rewind(before);
                 if *count >= at_least {
                     Ok(None)
@@ -1558,19 +1645,22 @@ where
 
         let before_separator = inp.save();
         if *state == 0 && self.allow_leading {
-            if self.separator.go::<Check>(inp).is_err() {
-                inp.rewind(before_separator);
+            match inp.go_alt(|inp| self.separator.go::<Check>(inp)) {
+                Some(Ok(())) => {}
+                Some(Err(())) => return Err(()),
+                None => inp.rewind(before_separator),
             }
         } else if *state > 0 {
-            match self.separator.go::<Check>(inp) {
-                Ok(()) => {
+            match inp.go_alt(|inp| self.separator.go::<Check>(inp)) {
+                Some(Ok(())) => {
                     // Do nothing
                 }
-                Err(()) if *state < self.at_least => {
+                Some(Err(())) => return Err(()),
+                None if *state < self.at_least => {
                     inp.rewind(before_separator);
                     return Err(());
                 }
-                Err(()) => {
+                None => {
                     inp.rewind(before_separator);
                     return Ok(None);
                 }
@@ -1578,19 +1668,21 @@ where
         }
 
         let before_item = inp.save();
-        match self.parser.go::<M>(inp) {
-            Ok(item) => {
+        match inp.go_alt(|inp| self.parser.go::<M>(inp)) {
+            Some(Ok(item)) => {
                 *state += 1;
                 Ok(Some(item))
             }
-            Err(()) if *state < self.at_least => {
+            // The item committed before failing, so we may not backtrack past it
+            Some(Err(())) => Err(()),
+            None if *state < self.at_least => {
                 // We have errored before we have reached the count,
                 // and therefore should return this error, as we are
                 // still expecting items
                 inp.rewind(before_separator);
                 Err(())
             }
-            Err(()) => {
+            None => {
                 // We are not expecting any more items, so it is okay
                 // for it to fail.
 
@@ -1817,9 +1909,10 @@ where
     #[inline(always)]
     fn go<M: Mode>(&self, inp: &mut InputRef<'a, '_, I, E>) -> PResult<M, Option<O>> {
         let before = inp.save();
-        Ok(match self.parser.go::<M>(inp) {
-            Ok(out) => M::map::<O, _, _>(out, Some),
-            Err(()) => {
+        Ok(match inp.go_alt(|inp| self.parser.go::<M>(inp)) {
+            Some(Ok(out)) => M::map::<O, _, _>(out, Some),
+            Some(Err(())) => return Err(()),
+            None => {
                 inp.rewind(before);
                 M::bind::<Option<O>, _>(|| None)
             }
@@ -2228,6 +2321,32 @@ where
     go_extra!(O);
 }
 
+/// See [`Parser::recursion_limit`].
+#[must_use]
+#[derive(Copy, Clone)]
+pub struct RecursionLimit<A> {
+    pub(crate) parser: A,
+    pub(crate) limit: usize,
+}
+
+impl<'a, I, O, E, A> ParserSealed<'a, I, O, E> for RecursionLimit<A>
+where
+    I: Input<'a>,
+    E: ParserExtra<'a, I>,
+    A: Parser<'a, I, O, E>,
+{
+    #[inline(always)]
+    fn go<M: Mode>(&self, inp: &mut InputRef<'a, '_, I, E>) -> PResult<M, O> {
+        let old_limit = inp.depth_limit;
+        inp.depth_limit = old_limit.min(inp.depth.saturating_add(self.limit));
+        let res = self.parser.go::<M>(inp);
+        inp.depth_limit = old_limit;
+        res
+    }
+
+    go_extra!(O);
+}
+
 /// See [`Parser::map_err`].
 #[derive(Copy, Clone)]
 pub struct MapErr<A, F> {
@@ -2355,7 +2474,8 @@ where
     I: Input<'a>,
     E: ParserExtra<'a, I>,
     A: Parser<'a, I, OA, E>,
-    F: Fn(OA, I::Span, &mut Emitter<E::Error>) -> U,
+    I::Span: Clone,
+    F: Fn(OA, I::Span, &mut Emitter<E::Error, I::Span>) -> U,
 {
     #[inline(always)]
     fn go<M: Mode>(&self, inp: &mut InputRef<'a, '_, I, E>) -> PResult<M, U>
@@ -2366,7 +2486,7 @@ where
         let out = self.parser.go::<Emit>(inp)?;
 
         let span = inp.span_since(before);
-        let mut emitter = Emitter::new();
+        let mut emitter = Emitter::new(span.clone());
         let out = (self.validator)(out, span, &mut emitter);
         for err in emitter.errors() {
             inp.emit(inp.offset, err);
@@ -108,6 +108,69 @@ pub trait Error<'a, I: Input<'a>>: Sized {
     }
 }
 
+/// A trait for error types that can carry secondary labels alongside their primary span and message.
+///
+/// Many diagnostics want to point at more than one location (for example, an unclosed delimiter error might label
+/// both the place the delimiter was expected and the opening delimiter: "note: opening brace here"). Implementing this
+/// trait allows such errors to be emitted with [`Emitter::emit_labeled`]. [`Rich`] implements this trait, and its
+/// labels can be read back with [`Rich::labels`].
+pub trait LabelledError<S>: Sized {
+    /// Create an error with the given primary span and message, without any labels.
+    fn labelled<M: ToString>(span: S, msg: M) -> Self;
+
+    /// Attach a secondary label, with a span and message, to this error.
+    fn add_label(&mut self, span: S, msg: String);
+}
+
+/// A trait for tokens that have a compact name for use in diagnostics.
+///
+/// Tokens of non-text grammars are often large enums whose contents are not useful in an error message: an error
+/// should say that it found an `identifier`, not the whole token struct. By default, tokens are written using their
+/// [`Debug`](fmt::Debug) implementation, so only tokens that need a different name must override
+/// [`TokenDisplay::fmt_token`]. See [`Rich::display_tokens`].
+pub trait TokenDisplay: fmt::Debug {
+    /// Write the name of this token, as it should appear in diagnostics.
+    fn fmt_token(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// An error paired with the position in the input at which it was produced.
+///
+/// For the errors of a [`ParseResult`], the position is the raw input offset of the error, as returned by
+/// [`ParseResult::errors_located`]. See [`ParseResult::into_errors_located`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Located<T, E> {
+    pub(crate) pos: T,
+    pub(crate) err: E,
+}
+
+impl<T, E> Located<T, E> {
+    /// Create a new located error at the given position.
+    #[inline]
+    pub fn at(pos: T, err: E) -> Self {
+        Self { pos, err }
+    }
+
+    /// Get the position at which the error was produced.
+    #[inline]
+    pub fn pos(&self) -> &T {
+        &self.pos
+    }
+
+    /// Get a reference to the error.
+    #[inline]
+    pub fn err(&self) -> &E {
+        &self.err
+    }
+
+    /// Convert this located error into its position and error.
+    #[inline]
+    pub fn into_parts(self) -> (T, E) {
+        (self.pos, self.err)
+    }
+}
+
 /// A ZST error type that tracks only whether a parse error occurred at all. This type is for when
 /// you want maximum parse speed, at the cost of all error reporting.
 #[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Copy, Clone, Default)]
@@ -516,6 +579,7 @@ pub struct Rich<'a, T, S = SimpleSpan<usize>, L = &'static str> {
     reason: Box<RichReason<'a, T, L>>,
     #[cfg(feature = "label")]
     context: Vec<(L, S)>,
+    labels: Vec<(S, String)>,
 }
 
 impl<'a, T, S, L> Rich<'a, T, S, L> {
@@ -546,6 +610,7 @@ impl<'a, T, S, L> Rich<'a, T, S, L> {
             reason: Box::new(RichReason::Custom(msg.to_string())),
             #[cfg(feature = "label")]
             context: Vec::new(),
+            labels: Vec::new(),
         }
     }
 
@@ -578,6 +643,61 @@ impl<'a, T, S, L> Rich<'a, T, S, L> {
         self.context.iter().map(|(l, s)| (l, s))
     }
 
+    /// Return an iterator over the secondary labels attached to this error, in the order they were added.
+    ///
+    /// Each label is a span paired with a message describing its relevance to the error (such as "opening delimiter
+    /// here"). Labels can be attached with [`Emitter::emit_labeled`] or [`LabelledError::add_label`].
+    pub fn labels(&self) -> impl ExactSizeIterator<Item = (&S, &str)> {
+        self.labels.iter().map(|(s, m)| (s, m.as_str()))
+    }
+
+    /// Get a value that displays this error in the same way as its [`Display`](fmt::Display) implementation, but with
+    /// tokens written using their [`TokenDisplay`] implementation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::{prelude::*, error::TokenDisplay};
+    /// #[derive(Clone, Debug, PartialEq)]
+    /// enum Token {
+    ///     Ident(String),
+    ///     Semicolon,
+    /// }
+    ///
+    /// impl TokenDisplay for Token {
+    ///     fn fmt_token(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    ///         match self {
+    ///             Token::Ident(_) => write!(f, "identifier"),
+    ///             Token::Semicolon => write!(f, ";"),
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let tokens = [Token::Ident("x".to_string()), Token::Ident("y".to_string())];
+    /// let errs = any::<_, extra::Err<Rich<_>>>()
+    ///     .then(just(Token::Semicolon))
+    ///     .parse(&tokens[..])
+    ///     .into_errors();
+    ///
+    /// assert_eq!(errs[0].display_tokens().to_string(), "found 'identifier' expected ';'");
+    /// ```
+    pub fn display_tokens(&self) -> impl fmt::Display + '_
+    where
+        T: TokenDisplay,
+        L: fmt::Display,
+    {
+        struct DisplayTokens<'b, 'a, T, S, L>(&'b Rich<'a, T, S, L>);
+
+        impl<'b, 'a, T: TokenDisplay, S, L: fmt::Display> fmt::Display for DisplayTokens<'b, 'a, T, S, L> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0
+                    .inner_fmt(f, T::fmt_token, |_: &S, _| Ok(()), L::fmt, false)
+            }
+        }
+
+        DisplayTokens(self)
+    }
+
     /// Convert this error into an owned version of itself by cloning any borrowed internal tokens, if necessary.
     pub fn into_owned<'b>(self) -> Rich<'b, T, S, L>
     where
@@ -619,6 +739,7 @@ impl<'a, T, S, L> Rich<'a, T, S, L> {
             reason: Box::new(self.reason.map_token(f)),
             #[cfg(feature = "label")]
             context: self.context,
+            labels: self.labels,
         }
     }
 }
@@ -648,17 +769,20 @@ where
             }),
             #[cfg(feature = "label")]
             context: Vec::new(),
+            labels: Vec::new(),
         }
     }
 
     #[inline]
-    fn merge(self, other: Self) -> Self {
+    fn merge(mut self, other: Self) -> Self {
         let new_reason = self.reason.flat_merge(*other.reason);
+        self.labels.extend(other.labels);
         Self {
             span: self.span,
             reason: Box::new(new_reason),
             #[cfg(feature = "label")]
             context: self.context, // TOOD: Merge contexts
+            labels: self.labels,
         }
     }
 
@@ -778,6 +902,18 @@ where
     }
 }
 
+impl<'a, T, S, L> LabelledError<S> for Rich<'a, T, S, L> {
+    #[inline]
+    fn labelled<M: ToString>(span: S, msg: M) -> Self {
+        Self::custom(span, msg)
+    }
+
+    #[inline]
+    fn add_label(&mut self, span: S, msg: String) {
+        self.labels.push((span, msg));
+    }
+}
+
 impl<'a, T, S, L> fmt::Debug for Rich<'a, T, S, L>
 where
     T: fmt::Debug,
@@ -17,6 +17,33 @@ where
     type State: 'a;
     /// Context used for parser configuration.
     type Context: 'a;
+    /// This extra type with its context replaced by `C`, used by parsers that provide a new context to their inner
+    /// parser, such as [`Parser::then_with_ctx`]. Every other setting is kept.
+    type WithContext<C: 'a>: ParserExtra<
+        'a,
+        I,
+        Error = Self::Error,
+        State = Self::State,
+        Context = C,
+        ErrorStorage = Self::ErrorStorage,
+    >;
+
+    /// Whether the expected inputs of alternatives that fail at the end of input should always be merged with those of
+    /// earlier alternatives that failed at the same position. See [`MergeEof`].
+    #[doc(hidden)]
+    const MERGE_EOF: bool = false;
+
+    /// Whether trivia skipped by parsers such as [`Parser::padded`] should be recorded. See [`RecordTrivia`].
+    #[doc(hidden)]
+    const RECORD_TRIVIA: bool = false;
+
+    /// The maximum number of secondary errors that are kept during a parse. See [`MaxErrors`].
+    #[doc(hidden)]
+    const MAX_ERRORS: usize = usize::MAX;
+
+    /// The storage used for the secondary errors of a parse. See [`StoreErrors`].
+    #[doc(hidden)]
+    type ErrorStorage: ErrorStorage<Located<I::Offset, Self::Error>>;
 }
 
 /// Use all default extra types
@@ -45,4 +72,205 @@ where
     type Error = E;
     type State = S;
     type Context = C;
+    type WithContext<C2: 'a> = Full<E, S, C2>;
+    type ErrorStorage = Vec<Located<I::Offset, E>>;
+}
+
+/// Wraps another extra type (such as [`Err`]), such that the expected inputs of every alternative that fails at the end
+/// of input are merged into the error.
+///
+/// By default, when an alternative fails because the end of input was reached, a new error is created for it and any
+/// error already produced by earlier alternatives at the same position is merged *into* the new one with
+/// [`Error::merge`]. For error types that do not implement [`Error::merge`], this means that the expected inputs of
+/// earlier alternatives are lost. With this wrapper, the earlier error is instead extended with the new expected inputs
+/// using [`Error::merge_expected_found`], just as happens for failures before the end of input.
+///
+/// # Examples
+///
+/// ```
+/// # use chumsky::{prelude::*, error::Error, extra::MergeEof, input::Input, util::MaybeRef};
+/// // An error type that tracks expected characters, but does not implement `Error::merge`
+/// #[derive(Debug, PartialEq)]
+/// struct Expected(Vec<char>);
+///
+/// impl<'a, I: Input<'a, Token = char>> Error<'a, I> for Expected {
+///     fn expected_found<E: IntoIterator<Item = Option<MaybeRef<'a, char>>>>(
+///         expected: E,
+///         _found: Option<MaybeRef<'a, char>>,
+///         _span: I::Span,
+///     ) -> Self {
+///         Self(expected.into_iter().flatten().map(|c| *c).collect())
+///     }
+///
+///     fn merge_expected_found<E: IntoIterator<Item = Option<MaybeRef<'a, char>>>>(
+///         mut self,
+///         expected: E,
+///         _found: Option<MaybeRef<'a, char>>,
+///         _span: I::Span,
+///     ) -> Self {
+///         self.0.extend(expected.into_iter().flatten().map(|c| *c));
+///         self
+///     }
+/// }
+///
+/// fn block<'a, E: extra::ParserExtra<'a, &'a str, Error = Expected>>() -> impl Parser<'a, &'a str, (), E> {
+///     just('{').ignore_then(choice((just('}'), just(';'), just('x')))).ignored()
+/// }
+///
+/// assert_eq!(block::<extra::Err<Expected>>().parse("{").into_errors(), vec![Expected(vec!['x'])]);
+/// assert_eq!(
+///     block::<MergeEof<extra::Err<Expected>>>().parse("{").into_errors(),
+///     vec![Expected(vec!['}', ';', 'x'])],
+/// );
+/// ```
+pub struct MergeEof<X>(PhantomData<X>);
+
+impl<X> Sealed for MergeEof<X> {}
+impl<'a, I, X> ParserExtra<'a, I> for MergeEof<X>
+where
+    I: Input<'a>,
+    X: ParserExtra<'a, I>,
+{
+    type Error = X::Error;
+    type State = X::State;
+    type Context = X::Context;
+    type WithContext<C: 'a> = MergeEof<X::WithContext<C>>;
+    type ErrorStorage = X::ErrorStorage;
+
+    const MERGE_EOF: bool = true;
+    const RECORD_TRIVIA: bool = X::RECORD_TRIVIA;
+    const MAX_ERRORS: usize = X::MAX_ERRORS;
+}
+
+/// Wraps another extra type (such as [`Err`]), such that the trivia skipped by [`Parser::padded`] and
+/// [`Parser::padded_by`] is recorded rather than discarded.
+///
+/// The recorded trivia can be retrieved with [`InputRef::trivia_since`] or, within [`Parser::map_with`], with
+/// [`MapExtra::trivia`], allowing it to be attached to the nodes of a lossless concrete syntax tree (for use by
+/// formatters and refactoring tools, for example). Trivia recorded by an alternative that is later backtracked out of
+/// is discarded along with it. Parsers that skip trivia by other means can record it themselves with
+/// [`InputRef::record_trivia`].
+///
+/// # Examples
+///
+/// ```
+/// # use chumsky::{prelude::*, extra::RecordTrivia};
+/// let word = text::ident::<_, _, RecordTrivia<extra::Err<Simple<char>>>>().padded();
+/// let node = custom(move |inp| {
+///     let before = inp.offset();
+///     let word = inp.parse(word)?;
+///     Ok((word, inp.trivia_since(before)))
+/// });
+///
+/// assert_eq!(
+///     node.repeated().collect::<Vec<_>>().parse(" foo  bar").into_result(),
+///     Ok(vec![
+///         ("foo", vec![SimpleSpan::new(0, 1), SimpleSpan::new(4, 6)]),
+///         ("bar", vec![]),
+///     ]),
+/// );
+/// ```
+pub struct RecordTrivia<X>(PhantomData<X>);
+
+impl<X> Sealed for RecordTrivia<X> {}
+impl<'a, I, X> ParserExtra<'a, I> for RecordTrivia<X>
+where
+    I: Input<'a>,
+    X: ParserExtra<'a, I>,
+{
+    type Error = X::Error;
+    type State = X::State;
+    type Context = X::Context;
+    type WithContext<C: 'a> = RecordTrivia<X::WithContext<C>>;
+    type ErrorStorage = X::ErrorStorage;
+
+    const MERGE_EOF: bool = X::MERGE_EOF;
+    const RECORD_TRIVIA: bool = true;
+    const MAX_ERRORS: usize = X::MAX_ERRORS;
+}
+
+/// Wraps another extra type (such as [`Err`]), such that at most `N` secondary errors are kept during a parse.
+///
+/// Secondary errors are those emitted while parsing continues, such as by error recovery or [`Parser::validate`].
+/// Once `N` of them have been kept, any further errors are dropped, so the storage needed for errors is bounded no
+/// matter how malformed the input is. The number of errors that were dropped is reported by
+/// [`ParseResult::dropped_errors`]. The error that causes a parse to fail is always kept.
+///
+/// # Examples
+///
+/// ```
+/// # use chumsky::{prelude::*, extra::MaxErrors};
+/// let xs = just::<_, _, MaxErrors<extra::Err<Rich<char>>, 2>>('x')
+///     .recover_with(via_parser(any().to('x')))
+///     .repeated()
+///     .collect::<String>();
+///
+/// let res = xs.parse("xabcx");
+/// assert_eq!(res.output().map(String::as_str), Some("xxxxx"));
+/// assert_eq!(res.errors().map(|e| e.span().start).collect::<Vec<_>>(), [1, 2]);
+/// assert_eq!(res.dropped_errors(), 1);
+/// ```
+pub struct MaxErrors<X, const N: usize>(PhantomData<X>);
+
+impl<X, const N: usize> Sealed for MaxErrors<X, N> {}
+impl<'a, I, X, const N: usize> ParserExtra<'a, I> for MaxErrors<X, N>
+where
+    I: Input<'a>,
+    X: ParserExtra<'a, I>,
+{
+    type Error = X::Error;
+    type State = X::State;
+    type Context = X::Context;
+    type WithContext<C: 'a> = MaxErrors<X::WithContext<C>, N>;
+    type ErrorStorage = X::ErrorStorage;
+
+    const MERGE_EOF: bool = X::MERGE_EOF;
+    const RECORD_TRIVIA: bool = X::RECORD_TRIVIA;
+    const MAX_ERRORS: usize = N;
+}
+
+/// Wraps another extra type (such as [`Err`]), such that the secondary errors of a parse are kept in `S` rather than in
+/// a [`Vec`].
+///
+/// Any [`ErrorStorage`] may be used. With a fixed-capacity storage such as [`ArrayStorage`], no allocation is needed to
+/// hold errors while parsing: once the storage is full, further errors are dropped and counted by
+/// [`ParseResult::dropped_errors`], as with [`MaxErrors`]. The error that causes a parse to fail is always kept.
+///
+/// [`ErrorStorage`]: crate::container::ErrorStorage
+/// [`ArrayStorage`]: crate::container::ArrayStorage
+///
+/// # Examples
+///
+/// ```
+/// # use chumsky::{prelude::*, container::ArrayStorage, error::Located, extra::StoreErrors};
+/// type Storage<'a> = ArrayStorage<Located<usize, Rich<'a, char>>, 2>;
+///
+/// let xs = just::<_, _, StoreErrors<extra::Err<Rich<char>>, Storage>>('x')
+///     .recover_with(via_parser(any().to('x')))
+///     .repeated()
+///     .collect::<String>();
+///
+/// let res = xs.parse("xabcx");
+/// assert_eq!(res.output().map(String::as_str), Some("xxxxx"));
+/// assert_eq!(res.errors().map(|e| e.span().start).collect::<Vec<_>>(), [1, 2]);
+/// assert_eq!(res.dropped_errors(), 1);
+/// ```
+pub struct StoreErrors<X, S>(PhantomData<(X, S)>);
+
+impl<X, S> Sealed for StoreErrors<X, S> {}
+impl<'a, I, X, S> ParserExtra<'a, I> for StoreErrors<X, S>
+where
+    I: Input<'a>,
+    X: ParserExtra<'a, I>,
+    S: ErrorStorage<Located<I::Offset, X::Error>> + 'a,
+{
+    type Error = X::Error;
+    type State = X::State;
+    type Context = X::Context;
+    type WithContext<C: 'a> = StoreErrors<X::WithContext<C>, S>;
+    type ErrorStorage = S;
+
+    const MERGE_EOF: bool = X::MERGE_EOF;
+    const RECORD_TRIVIA: bool = X::RECORD_TRIVIA;
+    const MAX_ERRORS: usize = X::MAX_ERRORS;
 }
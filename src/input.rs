@@ -5,9 +5,15 @@
 //! [`Input`] is the primary trait used to feed input data into a chumsky parser. You can create them in a number of
 //! ways: from strings, slices, arrays, etc.
 
-pub use crate::stream::{BoxedExactSizeStream, BoxedStream, Stream};
+pub use crate::stream::{
+    BoxedExactSizeStream, BoxedStream, GrowableBuffer, GrowableInput, OffsetSpans, SpannedStream,
+    SparseTokens, Stream, ZipLengthError, ZipSpanned,
+};
+#[cfg(feature = "serde")]
+pub use crate::stream::{JsonRecordError, JsonRecords};
 
 use super::*;
+use core::ops::{Bound, RangeBounds};
 #[cfg(feature = "memoization")]
 use hashbrown::HashMap;
 
@@ -48,6 +54,21 @@ pub trait Input<'a>: Sealed + 'a {
     #[doc(hidden)]
     unsafe fn next_maybe(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::TokenMaybe>);
 
+    /// Get the token at the provided offset, if it exists, without finding the offset that follows it.
+    ///
+    /// This is used when only the token itself is needed (such as when [`SpannedInput`] reads the spans of the tokens
+    /// at either end of a range) and may be overridden by inputs that can look tokens up more cheaply than
+    /// [`Input::next_maybe`], such as slices.
+    ///
+    /// # Safety
+    ///
+    /// As with [`Input::next_maybe`], `offset` must be generated by either [`Input::start`] or [`Input::next_maybe`].
+    #[doc(hidden)]
+    #[inline(always)]
+    unsafe fn token_at(&self, offset: Self::Offset) -> Option<Self::TokenMaybe> {
+        self.next_maybe(offset).1
+    }
+
     /// Create a span from a start and end offset.
     ///
     /// # Safety
@@ -61,6 +82,55 @@ pub trait Input<'a>: Sealed + 'a {
     #[doc(hidden)]
     fn prev(offs: Self::Offset) -> Self::Offset;
 
+    // Whether the given offset lies on a token boundary within the input. Inputs that are produced lazily may need to
+    // pull tokens up to the offset to determine this.
+    #[doc(hidden)]
+    fn is_boundary(&self, offset: Self::Offset) -> bool;
+
+    // Count the number of tokens that `from` must be advanced by to reach `to`, or zero if `to` is not after `from`.
+    // By default, this walks the input. Inputs that use token indices as offsets may simply subtract.
+    //
+    // # Safety
+    //
+    // As with [`Input::next_maybe`], the offsets passed to this function must be generated by either [`Input::start`]
+    // or [`Input::next_maybe`].
+    #[doc(hidden)]
+    unsafe fn token_distance(&self, from: Self::Offset, to: Self::Offset) -> usize {
+        let mut offset = from;
+        let mut distance = 0;
+        while offset < to {
+            match self.next_maybe(offset) {
+                (next, Some(_)) => offset = next,
+                (_, None) => break,
+            }
+            distance += 1;
+        }
+        distance
+    }
+
+    // The first offset (if any) at which the parser asked for a token that this input had already discarded because
+    // it lay too far behind the furthest token read. See `Stream::with_backtrack_limit`.
+    #[doc(hidden)]
+    fn backtrack_exceeded_at(&self) -> Option<Self::Offset> {
+        None
+    }
+
+    // The first offset (if any) at which the parser reached the end of the tokens currently available from this input
+    // before the input was known to be complete. See `GrowableBuffer`.
+    #[doc(hidden)]
+    fn starved_at(&self) -> Option<Self::Offset> {
+        None
+    }
+
+    // Called once parsing can no longer backtrack to before the given offset, such as when the parser commits to an
+    // alternative. See `Input::tee`.
+    //
+    // # Safety
+    //
+    // The offset must have been generated by the input.
+    #[doc(hidden)]
+    unsafe fn committed_to(&self, _offset: Self::Offset) {}
+
     /// Split an input that produces tokens of type `(T, S)` into one that produces tokens of type `T` and spans of
     /// type `S`.
     ///
@@ -72,7 +142,14 @@ pub trait Input<'a>: Sealed + 'a {
     /// sensible spans that extend to the end of the input or are zero-width. Most implementations simply use some
     /// equivalent of `len..len` (i.e: a span where both the start and end offsets are set to the end of the input).
     /// However, what you choose for this span is up to you: but consider that the context, start, and end of the span
-    /// will be recombined to create new spans as required by the parser.
+    /// will be recombined to create new spans as required by the parser. If the input contains no tokens at all, the
+    /// EoI span is used verbatim: this allows errors in an empty (or entirely trivia) file to point at something
+    /// sensible, such as the whole file.
+    ///
+    /// Spans produced by the parser (such as those given to [`Parser::map_with_span`], or by [`InputRef::span`]) are
+    /// created with [`Span::new`], taking the context of the EoI span, the start offset of the first token's span, and
+    /// the end offset of the last token's span. Any other information stored in the token spans is not preserved: see
+    /// [`Span`] for more information.
     ///
     /// Although `Spanned` does implement [`BorrowInput`], please be aware that, as you might anticipate, the slices
     /// will be those of the original input (usually `&[(T, S)]`) and not `&[T]` so as to avoid the need to copy
@@ -86,6 +163,32 @@ pub trait Input<'a>: Sealed + 'a {
         SpannedInput {
             input: self,
             eoi,
+            #[cfg(debug_assertions)]
+            check: None,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Like [`Input::spanned`], but additionally checks that token spans are ordered.
+    ///
+    /// In debug builds, every token pulled from the input has its span compared against that of the previous token:
+    /// if a span ends before it starts, or starts before the previous token's span ends, the parser will panic with a
+    /// message describing both spans. This is useful for catching bugs in lexers that would otherwise manifest as
+    /// nonsensical spans in diagnostics far downstream.
+    ///
+    /// In release builds, this is identical to [`Input::spanned`].
+    fn spanned_checked<T, S>(self, eoi: S) -> SpannedInput<T, S, Self>
+    where
+        Self: Input<'a, Token = (T, S)> + Sized,
+        T: 'a,
+        S: Span + Clone + 'a,
+        S::Offset: PartialOrd + fmt::Debug,
+    {
+        SpannedInput {
+            input: self,
+            eoi,
+            #[cfg(debug_assertions)]
+            check: Some(check_span_order::<S>),
             phantom: PhantomData,
         }
     }
@@ -105,221 +208,1067 @@ pub trait Input<'a>: Sealed + 'a {
             context,
         }
     }
-}
-
-/// Implement by inputs that have a known size (including spans)
-pub trait ExactSizeInput<'a>: Input<'a> {
-    /// Get a span from a start offset to the end of the input.
-    #[doc(hidden)]
-    unsafe fn span_from(&self, range: RangeFrom<Self::Offset>) -> Self::Span;
-}
-
-/// Implemented by inputs that represent slice-like streams of input tokens.
-pub trait SliceInput<'a>: ExactSizeInput<'a> {
-    /// The unsized slice type of this input. For [`&str`] it's `&str`, and for [`&[T]`] it will be `&[T]`.
-    type Slice;
-
-    /// Get a slice from a start and end offset
-    // TODO: Make unsafe
-    #[doc(hidden)]
-    fn slice(&self, range: Range<Self::Offset>) -> Self::Slice;
-
-    /// Get a slice from a start offset till the end of the input
-    // TODO: Make unsafe
-    #[doc(hidden)]
-    fn slice_from(&self, from: RangeFrom<Self::Offset>) -> Self::Slice;
-}
-
-// Implemented by inputs that reference a string slice and use byte indices as their offset.
-/// A trait for types that represent string-like streams of input tokens
-pub trait StrInput<'a, C: Char>:
-    ValueInput<'a, Offset = usize, Token = C> + SliceInput<'a, Slice = &'a C::Str>
-{
-}
 
-/// Implemented by inputs that can have tokens borrowed from them.
-pub trait ValueInput<'a>: Input<'a> {
-    /// Get the next offset from the provided one, and the next token if it exists
+    /// Transform every span generated by this input with a function, potentially producing a different span type.
     ///
-    /// # Safety
+    /// This is a more general form of [`Input::with_context`] and [`Input::offset_by`], useful for translating spans
+    /// from the coordinates of a transformed source back into those of the original: undoing a preprocessor's line
+    /// mapping, or adjusting columns for tab expansion, for example. Only spans are affected: slices, tokens, and
+    /// other internal offsets are unchanged.
     ///
-    /// `offset` must be generated by either `Input::start` or a previous call to this function.
-    #[doc(hidden)]
-    unsafe fn next(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::Token>);
-}
-
-/// Implemented by inputs that can have tokens borrowed from them.
-pub trait BorrowInput<'a>: Input<'a> {
-    /// Borrowed version of [`ValueInput::next`] with the same safety requirements.
+    /// The function is called once for every span that gets generated, so it should be deterministic and cheap.
     ///
-    /// # Safety
+    /// # Examples
     ///
-    /// Same as [`ValueInput::next`]
-    #[doc(hidden)]
-    unsafe fn next_ref(&self, offset: Self::Offset) -> (Self::Offset, Option<&'a Self::Token>);
-}
-
-impl<'a> Sealed for &'a str {}
-impl<'a> Input<'a> for &'a str {
-    type Offset = usize;
-    type Token = char;
-    type Span = SimpleSpan<usize>;
-
-    #[inline]
-    fn start(&self) -> Self::Offset {
-        0
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// // The original source was `"foo\\\n bar"`, but a preprocessor removed the line continuation after `foo`
+    /// let physical = |offs: usize| if offs > 3 { offs + 2 } else { offs };
+    ///
+    /// let ident = text::ident::<_, _, extra::Err<Simple<char>>>()
+    ///     .map_with_span(|ident, span| (ident, span))
+    ///     .padded();
+    ///
+    /// let input = "foo bar".map_span(|span: SimpleSpan| SimpleSpan::new(physical(span.start), physical(span.end)));
+    ///
+    /// assert_eq!(
+    ///     ident.repeated().collect::<Vec<_>>().parse(input).into_result(),
+    ///     Ok(vec![("foo", SimpleSpan::new(0, 3)), ("bar", SimpleSpan::new(6, 9))]),
+    /// );
+    /// ```
+    fn map_span<S, F>(self, f: F) -> MapSpan<Self, F, S>
+    where
+        Self: Sized,
+        S: Span + 'a,
+        F: Fn(Self::Span) -> S + 'a,
+    {
+        MapSpan {
+            input: self,
+            f,
+            phantom: PhantomData,
+        }
     }
 
-    type TokenMaybe = char;
+    /// Make spans generated by this input carry the line and column of their start and end, as well as their byte
+    /// offsets, producing spans with [`LineCol`](span::LineCol) offsets.
+    ///
+    /// Tabs are expanded to the next multiple of `width` when counting columns, such that reported columns match those
+    /// shown by an editor configured with the same tab width. Only spans are affected: offsets, slices, and tokens are
+    /// unchanged. Lines and columns are counted from the start of this input.
+    ///
+    /// The positions of the start of each line, and of regularly spaced points within each line, are found with a single
+    /// pass over the input when this function is called. After that, finding the position of a span takes constant
+    /// time, however long its line is.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::{prelude::*, span::LineCol};
+    /// let ident = text::ident::<_, _, extra::Err<Simple<char, SimpleSpan<LineCol>>>>()
+    ///     .map_with_span(|_, span: SimpleSpan<LineCol>| (span.start.line, span.start.column))
+    ///     .padded();
+    ///
+    /// assert_eq!(
+    ///     ident.repeated().collect::<Vec<_>>().parse("foo\n\tbar\n  \tbaz".with_tab_width(4)).into_result(),
+    ///     Ok(vec![(0, 0), (1, 4), (2, 4)]),
+    /// );
+    /// ```
+    fn with_tab_width(self, width: usize) -> WithTabWidth<Self>
+    where
+        Self: StrInput<'a, char> + Sized,
+    {
+        assert!(width > 0, "tab width must be greater than zero");
+        let start = self.start();
+        let rest = self.slice_from(start..);
+        let mut pos = span::LineCol {
+            offset: start,
+            line: 0,
+            column: 0,
+        };
+        let mut anchors = vec![pos];
+        let mut last_anchor = start;
+        for (i, c) in rest.char_indices() {
+            pos.offset = start + i;
+            if pos.offset - last_anchor >= WithTabWidth::<Self>::ANCHOR_STRIDE {
+                anchors.push(pos);
+                last_anchor = pos.offset;
+            }
+            match c {
+                '\n' => {
+                    pos.line += 1;
+                    pos.column = 0;
+                    last_anchor = pos.offset + 1;
+                    anchors.push(span::LineCol {
+                        offset: last_anchor,
+                        ..pos
+                    });
+                }
+                '\t' => pos.column += width - pos.column % width,
+                _ => pos.column += 1,
+            }
+        }
+        WithTabWidth {
+            input: self,
+            width,
+            end: start + rest.len(),
+            anchors,
+        }
+    }
 
-    #[inline(always)]
-    unsafe fn next_maybe(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::TokenMaybe>) {
-        self.next(offset)
+    /// Annotate every span generated by this input with the direction of the text that it covers, producing
+    /// [`DirectionalSpan`](span::DirectionalSpan)s.
+    ///
+    /// `direction` is used to find the strong direction of each token, if it has one: the direction of a span is that
+    /// of the first token within it that has a strong direction, or [`Direction::Ltr`](span::Direction::Ltr) if there
+    /// is no such token. Chumsky does not implement the Unicode bidirectional algorithm itself, but this provides a
+    /// hook through which directionality can be attached to spans as they get created, rather than post-processing
+    /// them.
+    ///
+    /// Note that finding the direction of a span requires walking the tokens within it, so spans covering large
+    /// regions of input are more expensive to generate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::{prelude::*, span::{Direction, DirectionalSpan}};
+    /// let direction = |c: &char| match c {
+    ///     '\u{0590}'..='\u{08FF}' => Some(Direction::Rtl),
+    ///     c if c.is_alphabetic() => Some(Direction::Ltr),
+    ///     _ => None,
+    /// };
+    ///
+    /// let word = any::<_, extra::Err<Simple<char, DirectionalSpan<SimpleSpan>>>>()
+    ///     .filter(|c: &char| c.is_alphabetic())
+    ///     .repeated()
+    ///     .at_least(1)
+    ///     .map_with_span(|_, span: DirectionalSpan<SimpleSpan>| span.direction)
+    ///     .padded();
+    ///
+    /// assert_eq!(
+    ///     word.repeated().collect::<Vec<_>>().parse("hello שלום".with_bidi(direction)).into_result(),
+    ///     Ok(vec![Direction::Ltr, Direction::Rtl]),
+    /// );
+    /// ```
+    fn with_bidi<F>(self, direction: F) -> WithBidi<Self, F>
+    where
+        Self: Sized,
+        F: Fn(&Self::Token) -> Option<span::Direction>,
+    {
+        WithBidi {
+            input: self,
+            direction,
+        }
     }
 
-    #[inline(always)]
-    unsafe fn span(&self, range: Range<Self::Offset>) -> Self::Span {
-        range.into()
+    /// Report each token of this input to a callback, along with its span, once parsing can no longer backtrack to it.
+    ///
+    /// This is useful for building a lossless concrete syntax tree (including trivia such as whitespace and comments)
+    /// alongside the abstract one, without needing to reconstruct it from slices after the fact.
+    ///
+    /// Each token is reported exactly once, in input order. Tokens are reported when the parser commits to an
+    /// alternative that has consumed them (see [`InputRef::commit`]), and the remaining tokens once parsing has
+    /// succeeded. Tokens consumed by an alternative that is later abandoned are therefore not reported early, and a
+    /// parse that fails reports only the tokens that it committed to.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// # use std::cell::RefCell;
+    /// let seen = RefCell::new(Vec::new());
+    ///
+    /// let word = text::ident::<_, _, extra::Err<Simple<char>>>().padded();
+    /// let input = "let x".tee(|c: &char, span: SimpleSpan| seen.borrow_mut().push((*c, span.start)));
+    /// assert_eq!(word.repeated().collect::<Vec<_>>().parse(input).into_result(), Ok(vec!["let", "x"]));
+    ///
+    /// assert_eq!(seen.into_inner(), vec![('l', 0), ('e', 1), ('t', 2), (' ', 3), ('x', 4)]);
+    /// ```
+    fn tee<F>(self, f: F) -> Tee<'a, Self, F>
+    where
+        Self: Sized,
+        F: Fn(&Self::Token, Self::Span),
+    {
+        Tee {
+            reported: Cell::new(self.start()),
+            input: self,
+            f,
+        }
     }
 
-    #[inline(always)]
-    fn prev(offs: Self::Offset) -> Self::Offset {
-        offs.saturating_sub(1)
+    /// Classify the tokens of this input into small integer 'kinds' with the given function, such that the kind of the
+    /// next token can be cheaply inspected with [`InputRef::peek_kind`].
+    ///
+    /// This is useful for table-driven parsers with many alternatives that are each keyed on the kind of their first
+    /// token: rather than trying each alternative in turn with [`Parser::or`] or [`choice`](crate::primitive::choice),
+    /// a [`custom`] parser can peek at the kind of the next token and `match` on it, jumping straight to the only
+    /// alternative that could succeed. The tokens, spans and slices of the input are unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// #[derive(Clone, Debug, PartialEq)]
+    /// enum Item<'a> {
+    ///     Ident(&'a str),
+    ///     Num(&'a str),
+    ///     Punct(char),
+    /// }
+    ///
+    /// const IDENT: u8 = 0;
+    /// const NUM: u8 = 1;
+    /// let kind = |c: &char| match c {
+    ///     'a'..='z' | 'A'..='Z' | '_' => IDENT,
+    ///     '0'..='9' => NUM,
+    ///     _ => 2,
+    /// };
+    ///
+    /// let ident = text::ident().map(Item::Ident);
+    /// let num = text::int(10).map(Item::Num);
+    /// let punct = any().map(Item::Punct);
+    /// let item = custom::<_, _, _, extra::Err<Simple<char>>>(move |inp| match inp.peek_kind() {
+    ///     Some(IDENT) => inp.parse(ident),
+    ///     Some(NUM) => inp.parse(num),
+    ///     _ => inp.parse(punct),
+    /// });
+    ///
+    /// assert_eq!(
+    ///     item.repeated().collect::<Vec<_>>().parse("x=42;".with_kinds(kind)).into_result(),
+    ///     Ok(vec![Item::Ident("x"), Item::Punct('='), Item::Num("42"), Item::Punct(';')]),
+    /// );
+    /// ```
+    fn with_kinds<F>(self, classify: F) -> WithKinds<Self, F>
+    where
+        Self: Sized,
+        F: Fn(&Self::Token) -> u8,
+    {
+        WithKinds {
+            input: self,
+            classify,
+        }
     }
-}
 
-impl<'a> ExactSizeInput<'a> for &'a str {
-    #[inline(always)]
-    unsafe fn span_from(&self, range: RangeFrom<Self::Offset>) -> Self::Span {
-        (range.start..self.len()).into()
+    /// Shift all spans generated by this input forward by a fixed number of offsets.
+    ///
+    /// This is useful when parsing a region that was extracted from a larger input, such as an included section of a
+    /// file: spans generated by the parser will be expressed in terms of the original input rather than the region.
+    /// Only spans are affected: slices, tokens, and other internal offsets are unchanged.
+    ///
+    /// The base offset has the same type as the offsets of the input's spans, so this works equally well for spans
+    /// with narrower offset types, such as `SimpleSpan<u32>`.
+    ///
+    /// This composes with [`Input::with_context`], allowing both a base offset and a file identifier to be attached.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let source = "let x = foo + bar;";
+    ///
+    /// let ident = text::ident::<_, _, extra::Err<Simple<char>>>()
+    ///     .map_with_span(|ident, span| (ident, span))
+    ///     .padded();
+    /// let expr = ident.then_ignore(just('+')).then(ident);
+    ///
+    /// assert_eq!(
+    ///     expr.parse(source[8..17].offset_by(8)).into_result(),
+    ///     Ok((("foo", SimpleSpan::new(8, 11)), ("bar", SimpleSpan::new(14, 17)))),
+    /// );
+    /// ```
+    fn offset_by<O>(self, base: O) -> OffsetBy<Self, O>
+    where
+        Self: Sized,
+        Self::Span: Span<Offset = O>,
+        O: Copy + core::ops::Add<Output = O>,
+    {
+        OffsetBy { input: self, base }
     }
-}
 
-impl<'a> ValueInput<'a> for &'a str {
-    #[inline(always)]
-    unsafe fn next(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::Token>) {
-        if offset < self.len() {
-            // SAFETY: `offset < self.len()` above guarantees offset is in-bounds
-            //         We only ever return offsets that are at a character boundary
-            let c = unsafe {
-                self.get_unchecked(offset..)
-                    .chars()
-                    .next()
-                    .unwrap_unchecked()
-            };
-            (offset + c.len_utf8(), Some(c))
-        } else {
-            (offset, None)
+    /// Restrict this input to the offsets `lo..hi`, such that parsing starts at `lo` and the end of input is reached at
+    /// `hi`.
+    ///
+    /// Unlike slicing the input, offsets are not renumbered: spans and slices produced by the parser continue to
+    /// address the original input. This is useful for re-parsing a fragment of a larger buffer (such as a single
+    /// function body after an edit) while producing spans that can be used directly alongside those of earlier
+    /// parses. A token that would extend beyond `hi` is treated as being past the end of the input.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `lo` is greater than `hi`, or if either does not lie on a token boundary within the input (see
+    /// [`InputRef::is_boundary`]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let source = "fn a() { foo } fn b() { bar }";
+    ///
+    /// let body = text::ident::<_, _, extra::Err<Rich<char>>>()
+    ///     .map_with_span(|ident, span| (ident, span))
+    ///     .padded()
+    ///     .delimited_by(just('{'), just('}'));
+    ///
+    /// assert_eq!(body.parse(source.window(7, 14)).into_result(), Ok(("foo", SimpleSpan::new(9, 12))));
+    ///
+    /// // The end of input is reported at the end of the window
+    /// let errs = body.parse(source.window(22, 27)).into_errors();
+    /// assert_eq!(errs[0].span(), &SimpleSpan::new(27, 27));
+    /// ```
+    fn window(self, lo: usize, hi: usize) -> Window<Self>
+    where
+        Self: Input<'a, Offset = usize> + Sized,
+    {
+        assert!(lo <= hi, "window start {lo} is after window end {hi}");
+        assert!(
+            self.is_boundary(lo) && self.is_boundary(hi),
+            "window {lo}..{hi} does not lie on token boundaries within the input",
+        );
+        Window {
+            input: self,
+            lo,
+            hi,
         }
     }
-}
-
-impl<'a> StrInput<'a, char> for &'a str {}
 
-impl<'a> SliceInput<'a> for &'a str {
-    type Slice = &'a str;
-
-    #[inline(always)]
-    fn slice(&self, range: Range<Self::Offset>) -> Self::Slice {
-        &self[range]
+    /// Skip a leading UTF-8 byte order mark (`U+FEFF`), if there is one, such that the parser never sees it.
+    ///
+    /// Offsets are not renumbered (see [`Input::window`]), so spans continue to refer to byte positions within the
+    /// original source, including the byte order mark.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let ident = text::ident::<_, _, extra::Err<Simple<char>>>().map_with_span(|ident, span| (ident, span));
+    ///
+    /// assert_eq!(ident.parse("\u{FEFF}foo".strip_bom()).into_result(), Ok(("foo", SimpleSpan::new(3, 6))));
+    /// assert_eq!(ident.parse("foo".strip_bom()).into_result(), Ok(("foo", SimpleSpan::new(0, 3))));
+    /// ```
+    fn strip_bom(self) -> Window<Self>
+    where
+        Self: StrInput<'a, char> + Sized,
+    {
+        let start = self.start();
+        let rest = self.slice_from(start..);
+        let bom = rest
+            .strip_prefix('\u{FEFF}')
+            .map_or(0, |stripped| rest.len() - stripped.len());
+        self.window(start + bom, start + rest.len())
     }
 
-    #[inline(always)]
-    fn slice_from(&self, from: RangeFrom<Self::Offset>) -> Self::Slice {
-        &self[from]
+    /// Skip a leading shebang line (such as `#!/usr/bin/env foo`), if there is one, such that the parser never sees
+    /// it. The newline that ends the shebang line is not skipped.
+    ///
+    /// As in Rust, a leading `#!` that is followed by `[` (possibly after whitespace) begins an inner attribute rather
+    /// than a shebang, and is not skipped.
+    ///
+    /// Offsets are not renumbered (see [`Input::window`]), so spans continue to refer to byte positions within the
+    /// original source, including the shebang line. This may be combined with [`Input::strip_bom`], which should be
+    /// applied first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let ident = text::ident::<_, _, extra::Err<Simple<char>>>()
+    ///     .map_with_span(|ident, span| (ident, span))
+    ///     .padded();
+    ///
+    /// let source = "\u{FEFF}#!/bin/foo\nbar";
+    /// assert_eq!(ident.parse(source.strip_bom().strip_shebang()).into_result(), Ok(("bar", SimpleSpan::new(14, 17))));
+    ///
+    /// // An inner attribute is left for the parser
+    /// let attr = just::<_, _, extra::Err<Simple<char>>>("#![bar]").map_with_span(|_, span| span);
+    /// assert_eq!(attr.parse("#![bar]".strip_shebang()).into_result(), Ok(SimpleSpan::new(0, 7)));
+    /// ```
+    fn strip_shebang(self) -> Window<Self>
+    where
+        Self: StrInput<'a, char> + Sized,
+    {
+        let start = self.start();
+        let rest = self.slice_from(start..);
+        let shebang = if rest.starts_with("#!") && !rest[2..].trim_start().starts_with('[') {
+            rest.find('\n').unwrap_or(rest.len())
+        } else {
+            0
+        };
+        self.window(start + shebang, start + rest.len())
     }
-}
-
-impl<'a, T> Sealed for &'a [T] {}
-impl<'a, T> Input<'a> for &'a [T] {
-    type Offset = usize;
-    type Token = T;
-    type Span = SimpleSpan<usize>;
 
-    #[inline(always)]
-    fn start(&self) -> Self::Offset {
-        0
+    /// End this input at the first occurrence of the given sentinel token, such that the parser never sees the
+    /// sentinel or anything after it. If the sentinel does not occur, the input is unchanged.
+    ///
+    /// This is useful for fixed-size buffers in which the meaningful content is terminated by a designated token
+    /// and followed by padding. Offsets are not renumbered (see [`Input::window`]), so spans and slices continue to
+    /// address the whole buffer, and the end of input is reported at the position of the sentinel.
+    ///
+    /// The sentinel is searched for when this method is called, so the input is read up to the sentinel (or to its end,
+    /// if there is no sentinel) before parsing begins. For a lazy input such as a [`Stream`], this means that all of
+    /// those tokens are pulled from the underlying iterator up front.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let record = [3, 1, 4, 0, 0, 0];
+    /// let digits = any::<_, extra::Err<Rich<u8>>>().filter(|&x| x > 0).repeated().collect::<Vec<_>>();
+    ///
+    /// assert_eq!(digits.parse(record.end_at_sentinel(0)).into_result(), Ok(vec![3, 1, 4]));
+    ///
+    /// // The end of input is reported at the sentinel
+    /// let errs = digits.then(any()).parse(record.end_at_sentinel(0)).into_errors();
+    /// assert_eq!(errs[0].span(), &SimpleSpan::new(3, 3));
+    /// ```
+    fn end_at_sentinel(self, sentinel: Self::Token) -> Window<Self>
+    where
+        Self: Input<'a, Offset = usize> + Sized,
+        Self::Token: PartialEq,
+    {
+        let start = self.start();
+        let mut end = start;
+        loop {
+            // SAFETY: `end` is either the start offset or was generated by a previous call to `Input::next_maybe`
+            match unsafe { self.next_maybe(end) } {
+                (next, Some(tok)) if *tok.borrow() != sentinel => end = next,
+                _ => break,
+            }
+        }
+        self.window(start, end)
     }
 
-    type TokenMaybe = &'a T;
-
-    #[inline(always)]
-    unsafe fn next_maybe(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::TokenMaybe>) {
-        self.next_ref(offset)
+    /// Check that this input begins with the given tokens, and skip them such that the parser never sees them.
+    ///
+    /// This is useful for binary formats that begin with a magic number that must be verified before the real grammar
+    /// starts. Offsets are not renumbered (see [`Input::window`]), so spans and slices continue to address the whole
+    /// input, including the prefix. If the input does not begin with the prefix, a [`PrefixError`] describing the
+    /// first mismatch is returned instead.
+    ///
+    /// Only the tokens of the prefix are read when this method is called, so a lazy input such as a [`Stream`] is not
+    /// read any further until parsing begins.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::{prelude::*, input::PrefixError};
+    /// let bytes = any::<_, extra::Err<Rich<u8>>>().map_with_span(|b, span| (b, span)).repeated().collect::<Vec<_>>();
+    ///
+    /// let file = [0x7F, b'E', b'L', b'F', 1, 2];
+    /// let body = file.require_prefix(&[0x7F, b'E', b'L', b'F']).unwrap();
+    /// assert_eq!(bytes.parse(body).into_result(), Ok(vec![(1, (4..5).into()), (2, (5..6).into())]));
+    ///
+    /// let file = [0x7F, b'E', b'L', b'X', 1, 2];
+    /// let err = file.require_prefix(&[0x7F, b'E', b'L', b'F']).map(|_| ()).unwrap_err();
+    /// assert_eq!(err, PrefixError { offset: 3, index: 3 });
+    /// ```
+    fn require_prefix(self, prefix: &[Self::Token]) -> Result<Window<Self>, PrefixError>
+    where
+        Self: Input<'a, Offset = usize> + Sized,
+        Self::Token: PartialEq,
+    {
+        let start = self.start();
+        let mut offset = start;
+        for (index, expected) in prefix.iter().enumerate() {
+            // SAFETY: `offset` is either the start offset or was generated by a previous call to `Input::next_maybe`
+            match unsafe { self.next_maybe(offset) } {
+                (next, Some(tok)) if tok.borrow() == expected => offset = next,
+                _ => return Err(PrefixError { offset, index }),
+            }
+        }
+        // Leave the end of the window open, so that lazy inputs (such as a `Stream`) are not read any further
+        Ok(Window {
+            input: self,
+            lo: offset,
+            hi: OPEN_WINDOW,
+        })
     }
 
-    #[inline(always)]
-    unsafe fn span(&self, range: Range<Self::Offset>) -> Self::Span {
-        range.into()
+    /// Case-fold the tokens of this input as they are parsed, while leaving slices of the input untouched.
+    ///
+    /// Parsers that match tokens (such as [`just`](crate::primitive::just) or [`one_of`](crate::primitive::one_of))
+    /// will see each character in lowercase, making them case-insensitive, but slices of the input (such as those
+    /// produced by [`Parser::slice`] or [`text::ident`](crate::text::ident)) will retain the original casing.
+    /// Offsets and spans are unaffected. This is useful for languages like SQL in which keywords are case-insensitive
+    /// but identifiers must be preserved as written.
+    ///
+    /// Characters whose lowercase form is not a single character are left as they are. Note that parsers which
+    /// compare slices, such as [`text::keyword`](crate::text::keyword), see the original casing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let ident = text::ident::<_, _, extra::Err<Simple<char>>>().padded();
+    /// let select = just("select").padded().ignore_then(ident);
+    ///
+    /// assert_eq!(select.parse("SELECT Name".case_fold()).into_result(), Ok("Name"));
+    /// assert_eq!(select.parse("select Name".case_fold()).into_result(), Ok("Name"));
+    /// ```
+    fn case_fold(self) -> CaseFold<Self>
+    where
+        Self: StrInput<'a, char> + Sized,
+    {
+        CaseFold { input: self }
     }
 
-    #[inline(always)]
-    fn prev(offs: Self::Offset) -> Self::Offset {
-        offs.saturating_sub(1)
+    /// Merge runs of consecutive tokens into single tokens as they are parsed.
+    ///
+    /// Whenever a token is read, `f` is given it along with the token that follows. If `f` returns `Some`, the two are
+    /// replaced by the merged token, which is then given to `f` along with the next token, and so on until `f` returns
+    /// `None`. The span of the merged token covers the whole run, and parsing continues after the last token of the
+    /// run. Slices of the input are unaffected. This is useful for normalising noisy lexer output (such as runs of
+    /// whitespace tokens) without a separate pass.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// #[derive(Clone, Debug, PartialEq)]
+    /// enum Token {
+    ///     Word(&'static str),
+    ///     Space(usize),
+    /// }
+    ///
+    /// let tokens = [Token::Word("a"), Token::Space(1), Token::Space(2), Token::Word("b")];
+    /// let input = tokens.coalesce(|a, b| match (a, b) {
+    ///     (Token::Space(a), Token::Space(b)) => Some(Token::Space(a + b)),
+    ///     _ => None,
+    /// });
+    ///
+    /// let token = any::<_, extra::Err<Simple<Token>>>().map_with_span(|tok, span: SimpleSpan| (tok, span));
+    /// assert_eq!(
+    ///     token.repeated().collect::<Vec<_>>().parse(input).into_result(),
+    ///     Ok(vec![
+    ///         (Token::Word("a"), SimpleSpan::new(0, 1)),
+    ///         (Token::Space(3), SimpleSpan::new(1, 3)),
+    ///         (Token::Word("b"), SimpleSpan::new(3, 4)),
+    ///     ]),
+    /// );
+    /// ```
+    fn coalesce<F>(self, f: F) -> Coalesce<Self, F>
+    where
+        Self: ValueInput<'a> + Sized,
+        F: Fn(&Self::Token, &Self::Token) -> Option<Self::Token>,
+    {
+        Coalesce { input: self, f }
+    }
+
+    /// Yield the tokens of this input in reverse order, from the end of the input to its start.
+    ///
+    /// This is useful for grammars that are more easily parsed from right to left, such as grouping the digits of a
+    /// number into thousands, or parsing a sequence of trailing suffixes: parse the reversed input, then reverse the
+    /// result. Spans continue to refer to the original input, so diagnostics point to the right place. Slices are also
+    /// taken from the original input, so their contents are in the original order.
+    ///
+    /// Constructing the reversed input requires walking this input to find the boundaries between its tokens, so
+    /// takes time and memory proportional to its length.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// // Group digits into thousands, starting from the right
+    /// let group = any::<_, extra::Err<Simple<char>>>()
+    ///     .repeated()
+    ///     .at_least(1)
+    ///     .at_most(3)
+    ///     .map_slice(|digits: &str| digits.to_string())
+    ///     .map_with_span(|digits, span: SimpleSpan| (digits, span));
+    ///
+    /// let groups = group.repeated().collect::<Vec<_>>().parse("1234567".reversed()).into_result();
+    /// assert_eq!(
+    ///     groups,
+    ///     Ok(vec![
+    ///         ("567".to_string(), SimpleSpan::new(4, 7)),
+    ///         ("234".to_string(), SimpleSpan::new(1, 4)),
+    ///         ("1".to_string(), SimpleSpan::new(0, 1)),
+    ///     ]),
+    /// );
+    /// ```
+    fn reversed(self) -> Reversed<'a, Self>
+    where
+        Self: Sized,
+    {
+        let mut boundaries = vec![self.start()];
+        loop {
+            let offset = boundaries[boundaries.len() - 1];
+            // SAFETY: `offset` is either the start offset or was generated by a previous call to `Input::next_maybe`
+            match unsafe { self.next_maybe(offset) } {
+                (next, Some(_)) => boundaries.push(next),
+                (_, None) => break,
+            }
+        }
+        Reversed {
+            input: self,
+            boundaries,
+        }
+    }
+
+    /// Concatenate this input with another input that produces the same tokens, such that they may be parsed as if
+    /// they were one continuous input.
+    ///
+    /// This is useful for parsing a prelude of built-in definitions followed by a user's source, for example. The spans
+    /// produced by the resulting input are paired with a [`ChainPart`] indicating which of the two inputs they came
+    /// from, and their offsets are relative to the start of that input. A span that would cross the boundary between
+    /// the inputs is truncated to the end of the first input.
+    ///
+    /// Slicing is supported if both inputs support it, although slices may not cross the boundary between the inputs:
+    /// doing so will cause a panic.
+    ///
+    /// Constructing the chained input requires walking the first input to find its end, so may take time proportional
+    /// to its length.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::{prelude::*, input::ChainPart};
+    /// let ident = text::ident::<_, _, extra::Err<Simple<char, (ChainPart, SimpleSpan)>>>()
+    ///     .map_with_span(|ident, span| (ident, span))
+    ///     .padded();
+    ///
+    /// assert_eq!(
+    ///     ident.repeated().collect::<Vec<_>>().parse("print len\n".chain("foo")).into_result(),
+    ///     Ok(vec![
+    ///         ("print", (ChainPart::First, SimpleSpan::new(0, 5))),
+    ///         ("len", (ChainPart::First, SimpleSpan::new(6, 9))),
+    ///         ("foo", (ChainPart::Second, SimpleSpan::new(0, 3))),
+    ///     ]),
+    /// );
+    /// ```
+    fn chain<B>(self, other: B) -> ChainedInput<Self, B>
+    where
+        Self: Input<'a, Offset = usize> + Sized,
+        Self::Span: Span<Context = ()>,
+        B: Input<'a, Offset = usize, Token = Self::Token, Span = Self::Span>,
+    {
+        let mut split = self.start();
+        // SAFETY: `split` is always either the start of the input or an offset produced by `next_maybe`
+        while let (next, Some(_)) = unsafe { self.next_maybe(split) } {
+            split = next;
+        }
+        ChainedInput {
+            first: self,
+            second: other,
+            split,
+        }
+    }
+
+    /// Append a synthetic end of input token to this input, such that it can be matched explicitly by parsers (with
+    /// [`just`], for example).
+    ///
+    /// This is useful for grammars that are most naturally written with an explicit end of input production, like
+    /// `statement* EOF`. The synthetic token has a zero-width span at the end of the input, making it a good anchor for
+    /// 'expected end of input' errors. There are no more tokens after it. Slices never include the synthetic token.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// #[derive(Clone, Debug, PartialEq)]
+    /// enum Token {
+    ///     Num(u64),
+    ///     Eof,
+    /// }
+    ///
+    /// let nums = select! { Token::Num(x) => x }
+    ///     .repeated()
+    ///     .collect::<Vec<_>>()
+    ///     .then(just::<_, _, extra::Err<Simple<_>>>(Token::Eof).map_with_span(|_, span| span));
+    ///
+    /// let tokens = [Token::Num(1), Token::Num(2)];
+    /// assert_eq!(
+    ///     nums.parse(tokens.as_slice().with_eof_token(Token::Eof)).into_result(),
+    ///     Ok((vec![1, 2], SimpleSpan::new(2, 2))),
+    /// );
+    /// ```
+    fn with_eof_token(self, eof: Self::Token) -> WithEofToken<Self, Self::Token>
+    where
+        Self: Sized,
+        Self::Token: Clone,
+    {
+        WithEofToken { input: self, eof }
+    }
+
+    /// Append a fixed sequence of tokens to the end of this input, such that they are produced once the input itself
+    /// is exhausted.
+    ///
+    /// This is useful for grammars that expect a terminator (such as an implicit `end_module` token) that the source
+    /// may not contain, without needing to copy the input into a new buffer. The spans of the input's own tokens are
+    /// unchanged, and each appended token has a zero-width span positioned at the end of the input.
+    ///
+    /// Slicing is supported if this input produces slices of its tokens: slices that lie entirely within the appended
+    /// tokens are taken from `extra`. Slices may not cross the boundary between the input and the appended tokens:
+    /// doing so will cause a panic. Slices from an offset within the input extend only to the end of the input.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// #[derive(Clone, Debug, PartialEq)]
+    /// enum Token {
+    ///     Item(u64),
+    ///     EndModule,
+    /// }
+    ///
+    /// let module = select! { Token::Item(x) => x }
+    ///     .repeated()
+    ///     .collect::<Vec<_>>()
+    ///     .then_ignore(just::<_, _, extra::Err<Simple<_>>>(Token::EndModule));
+    ///
+    /// let tokens = [Token::Item(1), Token::Item(2)];
+    /// let trailer = [Token::EndModule];
+    /// assert_eq!(
+    ///     module.parse(tokens.as_slice().append_tokens(&trailer)).into_result(),
+    ///     Ok(vec![1, 2]),
+    /// );
+    /// ```
+    fn append_tokens(self, extra: &'a [Self::Token]) -> AppendTokens<'a, Self>
+    where
+        Self: Sized,
+    {
+        AppendTokens { input: self, extra }
+    }
+
+    /// Insert a synthetic `line_break` token after every `\n` in this string input.
+    ///
+    /// This is useful for languages whose error recovery is line-based, such as configuration files, where the parser
+    /// should be able to resynchronise at the start of the next line (with [`skip_until`](crate::recovery::skip_until),
+    /// for example) without having to split the input into lines or tokens beforehand. The `\n` characters themselves
+    /// are still produced, so parsers that treat them as whitespace continue to work; the synthetic tokens simply sit
+    /// between each `\n` and the character that follows it. `line_break` should therefore be a character that cannot
+    /// otherwise appear in the input, such as a control character.
+    ///
+    /// The offsets of the characters in the input are unchanged, and each synthetic token has a zero-width span
+    /// positioned immediately after the `\n` that precedes it. Slices of the input never include synthetic tokens.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// const LINE_BREAK: char = '\u{1E}';
+    ///
+    /// let key = any::<_, extra::Err<Simple<char>>>()
+    ///     .filter(char::is_ascii_alphabetic)
+    ///     .repeated()
+    ///     .at_least(1)
+    ///     .slice();
+    /// let value = text::digits(10).at_least(1).map_slice(|s: &str| s.parse::<u32>().unwrap());
+    /// let entry = key
+    ///     .then_ignore(just('='))
+    ///     .then(value)
+    ///     .then_ignore(just('\n'))
+    ///     .map(Some)
+    ///     .recover_with(via_parser(none_of(LINE_BREAK).repeated().at_least(1).to(None)));
+    /// let config = entry
+    ///     .separated_by(just(LINE_BREAK))
+    ///     .allow_trailing()
+    ///     .collect::<Vec<_>>();
+    ///
+    /// let (entries, errs) = config
+    ///     .parse("a=1\nb=oops\nc=3\n".with_line_breaks(LINE_BREAK))
+    ///     .into_output_errors();
+    /// assert_eq!(entries, Some(vec![Some(("a", 1)), None, Some(("c", 3))]));
+    /// assert_eq!(errs.len(), 1);
+    /// ```
+    fn with_line_breaks(self, line_break: char) -> WithLineBreaks<Self>
+    where
+        Self: Sized + StrInput<'a, char>,
+    {
+        WithLineBreaks {
+            input: self,
+            line_break,
+        }
+    }
+
+    /// Present every line ending of this string input as a single `\n` character, such that `\r\n` and lone `\r`
+    /// line endings can be handled by parsers that only expect `\n`.
+    ///
+    /// Unlike stripping `\r` characters in a separate pass, the offsets of the input are unchanged: a `\r\n` is
+    /// produced as a single `\n` token whose span covers both of its bytes, so spans continue to refer to the
+    /// original source. Slices of the input also return the original text, line endings included.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let line = none_of::<_, _, extra::Err<Simple<char>>>('\n').repeated().slice();
+    /// let lines = line
+    ///     .then_ignore(just('\n'))
+    ///     .repeated()
+    ///     .collect::<Vec<_>>();
+    ///
+    /// assert_eq!(
+    ///     lines.parse("a\r\nb\rc\n".normalize_newlines()).into_result(),
+    ///     Ok(vec!["a", "b", "c"]),
+    /// );
+    ///
+    /// // The span of a `\r\n` line ending covers both bytes
+    /// let ending = just::<_, _, extra::Err<Simple<char>>>('a')
+    ///     .ignore_then(just('\n').map_with_span(|_, span: SimpleSpan| span));
+    /// assert_eq!(
+    ///     ending.parse("a\r\n".normalize_newlines()).into_result(),
+    ///     Ok(SimpleSpan::new(1, 3)),
+    /// );
+    /// ```
+    fn normalize_newlines(self) -> NormalizeNewlines<Self>
+    where
+        Self: Sized + StrInput<'a, char>,
+    {
+        NormalizeNewlines { input: self }
+    }
+
+    /// Rewrite the tokens of this input with a stateful function, such that each token may be replaced by zero or
+    /// more new tokens.
+    ///
+    /// `f` is called with the state (initially `init`) and each token of this input in turn, and returns the tokens
+    /// that should replace it. This is useful for the context-sensitive 'lexer hacks' that many languages require
+    /// between lexing and parsing, such as splitting `>>` into two `>` tokens when closing nested generic parameters.
+    /// Every token produced by `f` is given the span of the token it replaced.
+    ///
+    /// Tokens are rewritten lazily, as the parser requests them, and the results are buffered so that backtracking
+    /// does not cause `f` to be called again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// #[derive(Clone, Debug, PartialEq)]
+    /// enum Token {
+    ///     Ident(&'static str),
+    ///     Lt,
+    ///     Gt,
+    ///     Shr,
+    /// }
+    ///
+    /// // `Vec<Vec<u8>>`
+    /// let tokens = [Token::Ident("Vec"), Token::Lt, Token::Ident("Vec"), Token::Lt, Token::Ident("u8"), Token::Shr];
+    ///
+    /// // Split `>>` into `>` `>` when it closes two levels of generic parameters
+    /// let split = tokens.as_slice().scan(0, |depth: &mut usize, tok| match tok {
+    ///     Token::Lt => {
+    ///         *depth += 1;
+    ///         vec![Token::Lt]
+    ///     }
+    ///     Token::Shr if *depth >= 2 => {
+    ///         *depth -= 2;
+    ///         vec![Token::Gt, Token::Gt]
+    ///     }
+    ///     tok => vec![tok],
+    /// });
+    ///
+    /// let spanned = any::<_, extra::Err<Simple<_>>>()
+    ///     .map_with_span(|tok, span| (tok, span))
+    ///     .repeated()
+    ///     .collect::<Vec<_>>();
+    ///
+    /// assert_eq!(
+    ///     spanned.parse(split).into_result().unwrap()[5..],
+    ///     [(Token::Gt, SimpleSpan::new(5, 6)), (Token::Gt, SimpleSpan::new(5, 6))],
+    /// );
+    /// ```
+    #[allow(clippy::type_complexity)]
+    fn scan<St, F, R>(
+        self,
+        init: St,
+        f: F,
+    ) -> SpannedInput<R::Item, Self::Span, Stream<Scan<'a, Self, St, F, R>>>
+    where
+        Self: ExactSizeInput<'a> + ValueInput<'a> + Sized + 'a,
+        Self::Span: Clone + 'a,
+        St: 'a,
+        F: FnMut(&mut St, Self::Token) -> R + 'a,
+        R: IntoIterator + 'a,
+        R::IntoIter: 'a,
+        R::Item: Clone + 'a,
+    {
+        let full = self.full_span();
+        let eoi = Self::Span::new(full.context(), full.end()..full.end());
+        Stream::from_iter(Scan {
+            offset: self.start(),
+            input: self,
+            state: init,
+            f,
+            pending: None,
+            phantom: PhantomData,
+        })
+        .spanned(eoi)
     }
 }
 
-impl<'a, T> ExactSizeInput<'a> for &'a [T] {
-    #[inline(always)]
-    unsafe fn span_from(&self, range: RangeFrom<Self::Offset>) -> Self::Span {
-        (range.start..self.len()).into()
+/// Implement by inputs that have a known size (including spans)
+pub trait ExactSizeInput<'a>: Input<'a> {
+    /// Get a span from a start offset to the end of the input.
+    #[doc(hidden)]
+    unsafe fn span_from(&self, range: RangeFrom<Self::Offset>) -> Self::Span;
+
+    /// Get a span covering the whole of the input.
+    ///
+    /// This is useful for attaching a span to synthetic nodes, such as the root of an AST, without needing access to
+    /// an [`InputRef`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::{prelude::*, input::ExactSizeInput};
+    /// assert_eq!("hello".full_span(), SimpleSpan::new(0, 5));
+    /// assert_eq!("hello".with_context(42).full_span(), (42, SimpleSpan::new(0, 5)));
+    /// ```
+    fn full_span(&self) -> Self::Span {
+        // SAFETY: `Input::start` always produces a valid offset
+        unsafe { self.span_from(self.start()..) }
     }
 }
 
-impl<'a> StrInput<'a, u8> for &'a [u8] {}
+/// Implemented by inputs that represent slice-like streams of input tokens.
+pub trait SliceInput<'a>: ExactSizeInput<'a> {
+    /// The unsized slice type of this input. For [`&str`] it's `&str`, and for [`&[T]`] it will be `&[T]`.
+    type Slice;
 
-impl<'a, T> SliceInput<'a> for &'a [T] {
-    type Slice = &'a [T];
+    /// Get a slice from a start and end offset
+    // TODO: Make unsafe
+    #[doc(hidden)]
+    fn slice(&self, range: Range<Self::Offset>) -> Self::Slice;
 
-    #[inline(always)]
-    fn slice(&self, range: Range<Self::Offset>) -> Self::Slice {
-        &self[range]
+    /// Get a slice from a start offset till the end of the input.
+    ///
+    /// Inputs that are not contiguous, such as [`ChainedInput`] and [`AppendTokens`], return the longest contiguous
+    /// slice that begins at the offset, which may end before the end of the input.
+    // TODO: Make unsafe
+    #[doc(hidden)]
+    fn slice_from(&self, from: RangeFrom<Self::Offset>) -> Self::Slice;
+
+    /// Get slices for each of several start and end offsets, in order.
+    ///
+    /// By default, this just calls [`SliceInput::slice`] for each range, but inputs for which random access is
+    /// expensive may override it to do something more efficient.
+    fn slices(&self, ranges: &[Range<Self::Offset>]) -> Vec<Self::Slice> {
+        ranges
+            .iter()
+            .map(|range| self.slice(range.start..range.end))
+            .collect()
     }
 
-    #[inline(always)]
-    fn slice_from(&self, from: RangeFrom<Self::Offset>) -> Self::Slice {
-        &self[from]
+    /// Get a slice from a start and end offset, or an error if the input cannot produce a single slice that covers it.
+    ///
+    /// By default, this just calls [`SliceInput::slice`], which never fails. Inputs that cannot slice every range
+    /// override it to report that a range cannot be sliced rather than panicking. This includes inputs that are not
+    /// contiguous (such as [`ChainedInput`] and [`AppendTokens`], for ranges that cross the boundary between their
+    /// parts) and streaming inputs that discard tokens once they have been parsed.
+    fn try_slice(&self, range: Range<Self::Offset>) -> Result<Self::Slice, SliceError> {
+        Ok(self.slice(range))
     }
 }
 
-impl<'a, T: Clone> ValueInput<'a> for &'a [T] {
-    #[inline(always)]
-    unsafe fn next(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::Token>) {
-        if let Some(tok) = self.get(offset) {
-            (offset + 1, Some(tok.clone()))
-        } else {
-            (offset, None)
-        }
+/// An error produced when a slice of an input is requested that the input cannot produce, such as because the range
+/// crosses the boundary between two chained inputs. See [`SliceInput::try_slice`] and [`InputRef::try_slice`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SliceError(());
+
+impl fmt::Display for SliceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the requested region of the input cannot be sliced")
     }
 }
 
-impl<'a, T> BorrowInput<'a> for &'a [T] {
-    #[inline(always)]
-    unsafe fn next_ref(&self, offset: Self::Offset) -> (Self::Offset, Option<&'a Self::Token>) {
-        if let Some(tok) = self.get(offset) {
-            (offset + 1, Some(tok))
-        } else {
-            (offset, None)
+/// An error produced when an input does not begin with an expected prefix. See [`Input::require_prefix`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PrefixError {
+    /// The offset within the input at which the first mismatching token (or the end of the input) was found.
+    pub offset: usize,
+    /// The index within the prefix of the token that was expected at that offset.
+    pub index: usize,
+}
+
+impl fmt::Display for PrefixError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "input does not begin with the expected prefix: token {} of the prefix was not found at offset {}",
+            self.index, self.offset,
+        )
+    }
+}
+
+// Implemented by inputs that reference a string slice and use byte indices as their offset.
+/// A trait for types that represent string-like streams of input tokens
+pub trait StrInput<'a, C: Char>:
+    ValueInput<'a, Offset = usize, Token = C> + SliceInput<'a, Slice = &'a C::Str>
+{
+}
+
+/// Implemented by inputs that can have tokens borrowed from them.
+pub trait ValueInput<'a>: Input<'a> {
+    /// Get the next offset from the provided one, and the next token if it exists
+    ///
+    /// # Safety
+    ///
+    /// `offset` must be generated by either `Input::start` or a previous call to this function.
+    #[doc(hidden)]
+    unsafe fn next(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::Token>);
+
+    // Find the offset of the first token at or after `offset` that is equal to `token`, or the offset of the end of
+    // the input if there is no such token. By default, this walks the input. String inputs override it with a fast
+    // search of their bytes.
+    //
+    // # Safety
+    //
+    // As with [`ValueInput::next`], `offset` must be generated by either [`Input::start`] or [`Input::next_maybe`].
+    #[doc(hidden)]
+    unsafe fn find_token(&self, mut offset: Self::Offset, token: &Self::Token) -> Self::Offset
+    where
+        Self::Token: PartialEq,
+    {
+        loop {
+            match self.next(offset) {
+                (next, Some(tok)) if tok != *token => offset = next,
+                _ => return offset,
+            }
         }
     }
 }
 
-impl<'a, T: 'a, const N: usize> Sealed for &'a [T; N] {}
-impl<'a, T: 'a, const N: usize> Input<'a> for &'a [T; N] {
+/// Implemented by inputs that can have tokens borrowed from them.
+///
+/// Inputs that produce tokens by value, such as [`Stream`], cannot implement this trait because there is nothing for
+/// the borrowed token to point into. If your grammar makes use of [`select_ref!`] or other borrowing combinators,
+/// either switch to their by-value equivalents (such as [`select!`]), or collect the tokens into a [`Vec`] and parse a
+/// slice of it instead.
+#[cfg_attr(
+    feature = "nightly",
+    rustc_on_unimplemented(
+        message = "`{Self}` cannot have tokens borrowed from it",
+        label = "This input only produces tokens by value",
+        note = "Consider using `select!` instead of `select_ref!`, or collecting the tokens into a `Vec` and parsing a slice of it",
+    )
+)]
+pub trait BorrowInput<'a>: Input<'a> {
+    /// Borrowed version of [`ValueInput::next`] with the same safety requirements.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`ValueInput::next`]
+    #[doc(hidden)]
+    unsafe fn next_ref(&self, offset: Self::Offset) -> (Self::Offset, Option<&'a Self::Token>);
+}
+
+impl<'a> Sealed for &'a str {}
+impl<'a> Input<'a> for &'a str {
     type Offset = usize;
-    type Token = T;
+    type Token = char;
     type Span = SimpleSpan<usize>;
 
-    #[inline(always)]
+    #[inline]
     fn start(&self) -> Self::Offset {
         0
     }
 
-    type TokenMaybe = &'a T;
+    type TokenMaybe = char;
 
     #[inline(always)]
     unsafe fn next_maybe(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::TokenMaybe>) {
-        self.next_ref(offset)
+        self.next(offset)
     }
 
     #[inline(always)]
@@ -331,36 +1280,223 @@ impl<'a, T: 'a, const N: usize> Input<'a> for &'a [T; N] {
     fn prev(offs: Self::Offset) -> Self::Offset {
         offs.saturating_sub(1)
     }
-}
 
-impl<'a, T: 'a, const N: usize> ExactSizeInput<'a> for &'a [T; N] {
     #[inline(always)]
-    unsafe fn span_from(&self, range: RangeFrom<Self::Offset>) -> Self::Span {
-        (range.start..N).into()
+    fn is_boundary(&self, offset: Self::Offset) -> bool {
+        self.is_char_boundary(offset)
     }
 }
 
-impl<'a, const N: usize> StrInput<'a, u8> for &'a [u8; N] {}
-
-impl<'a, T: 'a, const N: usize> SliceInput<'a> for &'a [T; N] {
-    type Slice = &'a [T];
-
-    #[inline(always)]
-    fn slice(&self, range: Range<Self::Offset>) -> Self::Slice {
-        &self[range]
-    }
-
+impl<'a> ExactSizeInput<'a> for &'a str {
     #[inline(always)]
-    fn slice_from(&self, from: RangeFrom<Self::Offset>) -> Self::Slice {
-        &self[from]
+    unsafe fn span_from(&self, range: RangeFrom<Self::Offset>) -> Self::Span {
+        (range.start..self.len()).into()
     }
 }
 
-impl<'a, T: Clone + 'a, const N: usize> ValueInput<'a> for &'a [T; N] {
+impl<'a> ValueInput<'a> for &'a str {
     #[inline(always)]
     unsafe fn next(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::Token>) {
-        if let Some(tok) = self.get(offset) {
-            (offset + 1, Some(tok.clone()))
+        if offset < self.len() {
+            // SAFETY: `offset < self.len()` above guarantees offset is in-bounds
+            //         We only ever return offsets that are at a character boundary
+            let c = unsafe {
+                self.get_unchecked(offset..)
+                    .chars()
+                    .next()
+                    .unwrap_unchecked()
+            };
+            (offset + c.len_utf8(), Some(c))
+        } else {
+            (offset, None)
+        }
+    }
+
+    #[inline]
+    unsafe fn find_token(&self, offset: Self::Offset, token: &Self::Token) -> Self::Offset {
+        // SAFETY: offsets are only ever generated at character boundaries within the string
+        let rest = unsafe { self.get_unchecked(offset..) };
+        offset + rest.find(*token).unwrap_or(rest.len())
+    }
+}
+
+impl<'a> StrInput<'a, char> for &'a str {}
+
+impl<'a> SliceInput<'a> for &'a str {
+    type Slice = &'a str;
+
+    #[inline(always)]
+    fn slice(&self, range: Range<Self::Offset>) -> Self::Slice {
+        &self[range]
+    }
+
+    #[inline(always)]
+    fn slice_from(&self, from: RangeFrom<Self::Offset>) -> Self::Slice {
+        &self[from]
+    }
+}
+
+impl<'a, T> Sealed for &'a [T] {}
+impl<'a, T> Input<'a> for &'a [T] {
+    type Offset = usize;
+    type Token = T;
+    type Span = SimpleSpan<usize>;
+
+    #[inline(always)]
+    fn start(&self) -> Self::Offset {
+        0
+    }
+
+    type TokenMaybe = &'a T;
+
+    #[inline(always)]
+    unsafe fn next_maybe(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::TokenMaybe>) {
+        self.next_ref(offset)
+    }
+
+    #[inline(always)]
+    unsafe fn token_at(&self, offset: Self::Offset) -> Option<Self::TokenMaybe> {
+        self.get(offset)
+    }
+
+    #[inline(always)]
+    unsafe fn span(&self, range: Range<Self::Offset>) -> Self::Span {
+        range.into()
+    }
+
+    #[inline(always)]
+    fn prev(offs: Self::Offset) -> Self::Offset {
+        offs.saturating_sub(1)
+    }
+
+    #[inline(always)]
+    fn is_boundary(&self, offset: Self::Offset) -> bool {
+        offset <= self.len()
+    }
+
+    #[inline(always)]
+    unsafe fn token_distance(&self, from: Self::Offset, to: Self::Offset) -> usize {
+        to.saturating_sub(from)
+    }
+}
+
+impl<'a, T> ExactSizeInput<'a> for &'a [T] {
+    #[inline(always)]
+    unsafe fn span_from(&self, range: RangeFrom<Self::Offset>) -> Self::Span {
+        (range.start..self.len()).into()
+    }
+}
+
+impl<'a> StrInput<'a, u8> for &'a [u8] {}
+
+impl<'a, T> SliceInput<'a> for &'a [T] {
+    type Slice = &'a [T];
+
+    #[inline(always)]
+    fn slice(&self, range: Range<Self::Offset>) -> Self::Slice {
+        &self[range]
+    }
+
+    #[inline(always)]
+    fn slice_from(&self, from: RangeFrom<Self::Offset>) -> Self::Slice {
+        &self[from]
+    }
+}
+
+impl<'a, T: Clone> ValueInput<'a> for &'a [T] {
+    #[inline(always)]
+    unsafe fn next(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::Token>) {
+        if let Some(tok) = self.get(offset) {
+            (offset + 1, Some(tok.clone()))
+        } else {
+            (offset, None)
+        }
+    }
+}
+
+impl<'a, T> BorrowInput<'a> for &'a [T] {
+    #[inline(always)]
+    unsafe fn next_ref(&self, offset: Self::Offset) -> (Self::Offset, Option<&'a Self::Token>) {
+        if let Some(tok) = self.get(offset) {
+            (offset + 1, Some(tok))
+        } else {
+            (offset, None)
+        }
+    }
+}
+
+impl<'a, T: 'a, const N: usize> Sealed for &'a [T; N] {}
+impl<'a, T: 'a, const N: usize> Input<'a> for &'a [T; N] {
+    type Offset = usize;
+    type Token = T;
+    type Span = SimpleSpan<usize>;
+
+    #[inline(always)]
+    fn start(&self) -> Self::Offset {
+        0
+    }
+
+    type TokenMaybe = &'a T;
+
+    #[inline(always)]
+    unsafe fn next_maybe(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::TokenMaybe>) {
+        self.next_ref(offset)
+    }
+
+    #[inline(always)]
+    unsafe fn token_at(&self, offset: Self::Offset) -> Option<Self::TokenMaybe> {
+        self.get(offset)
+    }
+
+    #[inline(always)]
+    unsafe fn span(&self, range: Range<Self::Offset>) -> Self::Span {
+        range.into()
+    }
+
+    #[inline(always)]
+    fn prev(offs: Self::Offset) -> Self::Offset {
+        offs.saturating_sub(1)
+    }
+
+    #[inline(always)]
+    fn is_boundary(&self, offset: Self::Offset) -> bool {
+        offset <= self.len()
+    }
+
+    #[inline(always)]
+    unsafe fn token_distance(&self, from: Self::Offset, to: Self::Offset) -> usize {
+        to.saturating_sub(from)
+    }
+}
+
+impl<'a, T: 'a, const N: usize> ExactSizeInput<'a> for &'a [T; N] {
+    #[inline(always)]
+    unsafe fn span_from(&self, range: RangeFrom<Self::Offset>) -> Self::Span {
+        (range.start..N).into()
+    }
+}
+
+impl<'a, const N: usize> StrInput<'a, u8> for &'a [u8; N] {}
+
+impl<'a, T: 'a, const N: usize> SliceInput<'a> for &'a [T; N] {
+    type Slice = &'a [T];
+
+    #[inline(always)]
+    fn slice(&self, range: Range<Self::Offset>) -> Self::Slice {
+        &self[range]
+    }
+
+    #[inline(always)]
+    fn slice_from(&self, from: RangeFrom<Self::Offset>) -> Self::Slice {
+        &self[from]
+    }
+}
+
+impl<'a, T: Clone + 'a, const N: usize> ValueInput<'a> for &'a [T; N] {
+    #[inline(always)]
+    unsafe fn next(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::Token>) {
+        if let Some(tok) = self.get(offset) {
+            (offset + 1, Some(tok.clone()))
         } else {
             (offset, None)
         }
@@ -379,13 +1515,40 @@ impl<'a, T: 'a, const N: usize> BorrowInput<'a> for &'a [T; N] {
 }
 
 /// A wrapper around an input that splits an input into spans and tokens. See [`Input::spanned`].
+///
+/// This input only implements [`BorrowInput`] if the wrapped input does. To use borrowing combinators like
+/// [`select_ref!`] with a by-value input such as [`Stream`], collect its tokens into a [`Vec`] and parse a slice of it.
 #[derive(Copy, Clone)]
 pub struct SpannedInput<T, S, I> {
     input: I,
     eoi: S,
+    #[cfg(debug_assertions)]
+    check: Option<fn(&S, &S)>,
     phantom: PhantomData<T>,
 }
 
+/// Panics if `span` is malformed or out of order with respect to `prev`. See [`Input::spanned_checked`].
+#[cfg(debug_assertions)]
+fn check_span_order<S: Span>(prev: &S, span: &S)
+where
+    S::Offset: PartialOrd + fmt::Debug,
+{
+    assert!(
+        span.start() <= span.end(),
+        "token span {:?}..{:?} ends before it starts",
+        span.start(),
+        span.end(),
+    );
+    assert!(
+        prev.end() <= span.start(),
+        "token span {:?}..{:?} starts before the end of the previous token span {:?}..{:?}",
+        span.start(),
+        span.end(),
+        prev.start(),
+        prev.end(),
+    );
+}
+
 /// Utility type required to allow [`SpannedInput`] to implement [`Input`].
 #[doc(hidden)]
 pub struct SpannedTokenMaybe<'a, I: Input<'a>, T, S>(I::TokenMaybe, PhantomData<(T, S)>);
@@ -409,6 +1572,31 @@ impl<'a, I: Input<'a, Token = (T, S)>, T, S: 'a> From<SpannedTokenMaybe<'a, I, T
     }
 }
 
+impl<'a, T, S, I> SpannedInput<T, S, I>
+where
+    I: Input<'a, Token = (T, S)>,
+    T: 'a,
+    S: Span + Clone + 'a,
+{
+    /// If this input was created with [`Input::spanned_checked`], check the span of the token at `offset` against
+    /// that of the token before it.
+    ///
+    /// # Safety
+    ///
+    /// `offset` must have been generated by the inner input.
+    #[cfg(debug_assertions)]
+    #[inline(always)]
+    unsafe fn check_order(&self, offset: I::Offset, span: &S) {
+        if let Some(check) = self.check {
+            if offset != self.input.start() {
+                if let Some(prev) = self.input.next_maybe(I::prev(offset)).1 {
+                    check(&prev.borrow().1, span);
+                }
+            }
+        }
+    }
+}
+
 impl<'a, T, S, I: Input<'a>> Sealed for SpannedInput<T, S, I> {}
 impl<'a, T, S, I> Input<'a> for SpannedInput<T, S, I>
 where
@@ -429,21 +1617,34 @@ where
 
     #[inline(always)]
     unsafe fn next_maybe(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::TokenMaybe>) {
-        let (offset, tok) = self.input.next_maybe(offset);
-        (offset, tok.map(|tok| SpannedTokenMaybe(tok, PhantomData)))
+        let (next, tok) = self.input.next_maybe(offset);
+        #[cfg(debug_assertions)]
+        if let Some(tok) = &tok {
+            self.check_order(offset, &tok.borrow().1);
+        }
+        (next, tok.map(|tok| SpannedTokenMaybe(tok, PhantomData)))
+    }
+
+    #[inline(always)]
+    unsafe fn token_at(&self, offset: Self::Offset) -> Option<Self::TokenMaybe> {
+        self.input
+            .token_at(offset)
+            .map(|tok| SpannedTokenMaybe(tok, PhantomData))
     }
 
     #[inline(always)]
     unsafe fn span(&self, range: Range<Self::Offset>) -> Self::Span {
-        let start = self
-            .input
-            .next_maybe(range.start)
-            .1
-            .map_or(self.eoi.start(), |tok| tok.borrow().1.start());
+        // Only the tokens at either end of the range are looked up. For slice-backed inputs, `token_at` indexes the
+        // slice directly, so this never goes through `next_maybe`.
+        let start = match self.input.token_at(range.start) {
+            Some(tok) => tok.borrow().1.start(),
+            // The input is empty, so the EoI span is the only sensible span
+            None if range.start == self.input.start() => return self.eoi.clone(),
+            None => self.eoi.start(),
+        };
         let end = self
             .input
-            .next_maybe(I::prev(range.end))
-            .1
+            .token_at(I::prev(range.end))
             .map_or(self.eoi.start(), |tok| tok.borrow().1.end());
         S::new(self.eoi.context(), start..end)
     }
@@ -452,6 +1653,31 @@ where
     fn prev(offs: Self::Offset) -> Self::Offset {
         I::prev(offs)
     }
+
+    #[inline(always)]
+    fn is_boundary(&self, offset: Self::Offset) -> bool {
+        self.input.is_boundary(offset)
+    }
+
+    #[inline(always)]
+    unsafe fn token_distance(&self, from: Self::Offset, to: Self::Offset) -> usize {
+        self.input.token_distance(from, to)
+    }
+
+    #[inline(always)]
+    fn backtrack_exceeded_at(&self) -> Option<Self::Offset> {
+        self.input.backtrack_exceeded_at()
+    }
+
+    #[inline(always)]
+    fn starved_at(&self) -> Option<Self::Offset> {
+        self.input.starved_at()
+    }
+
+    #[inline(always)]
+    unsafe fn committed_to(&self, offset: Self::Offset) {
+        self.input.committed_to(offset)
+    }
 }
 
 impl<'a, T, S, I> ExactSizeInput<'a> for SpannedInput<T, S, I>
@@ -462,11 +1688,12 @@ where
 {
     #[inline(always)]
     unsafe fn span_from(&self, range: RangeFrom<Self::Offset>) -> Self::Span {
-        let start = self
-            .input
-            .next_maybe(range.start)
-            .1
-            .map_or(self.eoi.start(), |tok| tok.borrow().1.start());
+        let start = match self.input.token_at(range.start) {
+            Some(tok) => tok.borrow().1.start(),
+            // The input is empty, so the EoI span is the only sensible span
+            None if range.start == self.input.start() => return self.eoi.clone(),
+            None => self.eoi.start(),
+        };
         S::new(self.eoi.context(), start..self.eoi.start())
     }
 }
@@ -480,6 +1707,10 @@ where
     #[inline(always)]
     unsafe fn next(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::Token>) {
         let (offs, tok) = self.input.next(offset);
+        #[cfg(debug_assertions)]
+        if let Some((_, span)) = &tok {
+            self.check_order(offset, span);
+        }
         (offs, tok.map(|(tok, _)| tok))
     }
 }
@@ -493,6 +1724,10 @@ where
     #[inline(always)]
     unsafe fn next_ref(&self, offset: Self::Offset) -> (Self::Offset, Option<&'a Self::Token>) {
         let (offs, tok) = self.input.next_ref(offset);
+        #[cfg(debug_assertions)]
+        if let Some((_, span)) = tok {
+            self.check_order(offset, span);
+        }
         (offs, tok.map(|(tok, _)| tok))
     }
 }
@@ -510,10 +1745,20 @@ where
         <I as SliceInput>::slice(&self.input, range)
     }
 
+    #[inline(always)]
+    fn try_slice(&self, range: Range<Self::Offset>) -> Result<Self::Slice, SliceError> {
+        <I as SliceInput>::try_slice(&self.input, range)
+    }
+
     #[inline(always)]
     fn slice_from(&self, from: RangeFrom<Self::Offset>) -> Self::Slice {
         <I as SliceInput>::slice_from(&self.input, from)
     }
+
+    #[inline(always)]
+    fn slices(&self, ranges: &[Range<Self::Offset>]) -> Vec<Self::Slice> {
+        <I as SliceInput>::slices(&self.input, ranges)
+    }
 }
 
 /// An input wrapper contains a user-defined context in its span, in addition to the span of the wrapped input. See
@@ -545,6 +1790,11 @@ where
         self.input.next_maybe(offset)
     }
 
+    #[inline(always)]
+    unsafe fn token_at(&self, offset: Self::Offset) -> Option<Self::TokenMaybe> {
+        self.input.token_at(offset)
+    }
+
     #[inline(always)]
     unsafe fn span(&self, range: Range<Self::Offset>) -> Self::Span {
         (self.context.clone(), self.input.span(range))
@@ -554,6 +1804,31 @@ where
     fn prev(offs: Self::Offset) -> Self::Offset {
         I::prev(offs)
     }
+
+    #[inline(always)]
+    fn is_boundary(&self, offset: Self::Offset) -> bool {
+        self.input.is_boundary(offset)
+    }
+
+    #[inline(always)]
+    unsafe fn token_distance(&self, from: Self::Offset, to: Self::Offset) -> usize {
+        self.input.token_distance(from, to)
+    }
+
+    #[inline(always)]
+    fn backtrack_exceeded_at(&self) -> Option<Self::Offset> {
+        self.input.backtrack_exceeded_at()
+    }
+
+    #[inline(always)]
+    fn starved_at(&self) -> Option<Self::Offset> {
+        self.input.starved_at()
+    }
+
+    #[inline(always)]
+    unsafe fn committed_to(&self, offset: Self::Offset) {
+        self.input.committed_to(offset)
+    }
 }
 
 impl<'a, Ctx: Clone + 'a, I: Input<'a>> ExactSizeInput<'a> for WithContext<Ctx, I>
@@ -575,6 +1850,14 @@ where
     unsafe fn next(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::Token>) {
         self.input.next(offset)
     }
+
+    #[inline(always)]
+    unsafe fn find_token(&self, offset: Self::Offset, token: &Self::Token) -> Self::Offset
+    where
+        Self::Token: PartialEq,
+    {
+        self.input.find_token(offset, token)
+    }
 }
 
 impl<'a, Ctx: Clone + 'a, I: BorrowInput<'a>> BorrowInput<'a> for WithContext<Ctx, I>
@@ -598,10 +1881,20 @@ where
         <I as SliceInput>::slice(&self.input, range)
     }
 
+    #[inline(always)]
+    fn try_slice(&self, range: Range<Self::Offset>) -> Result<Self::Slice, SliceError> {
+        <I as SliceInput>::try_slice(&self.input, range)
+    }
+
     #[inline(always)]
     fn slice_from(&self, from: RangeFrom<Self::Offset>) -> Self::Slice {
         <I as SliceInput>::slice_from(&self.input, from)
     }
+
+    #[inline(always)]
+    fn slices(&self, ranges: &[Range<Self::Offset>]) -> Vec<Self::Slice> {
+        <I as SliceInput>::slices(&self.input, ranges)
+    }
 }
 
 impl<'a, Ctx, C, I> StrInput<'a, C> for WithContext<Ctx, I>
@@ -613,438 +1906,4848 @@ where
 {
 }
 
-/// Represents a location in an input that can be rewound to.
-///
-/// Markers can be created with [`InputRef::save`] and rewound to with [`InputRef::rewind`].
-pub struct Marker<'a, 'parse, I: Input<'a>> {
-    pub(crate) offset: I::Offset,
-    pub(crate) err_count: usize,
-    phantom: PhantomData<fn(&'parse ()) -> &'parse ()>, // Invariance
+/// An input wrapper that transforms the spans of the wrapped input with a function. See [`Input::map_span`].
+#[derive(Copy, Clone)]
+pub struct MapSpan<I, F, S> {
+    input: I,
+    f: F,
+    phantom: PhantomData<S>,
 }
 
-impl<'a, 'parse, I: Input<'a>> Marker<'a, 'parse, I> {
-    /// Get the [`Offset`] that this marker corresponds to.
-    pub fn offset(self) -> Offset<'a, 'parse, I> {
-        Offset {
-            offset: self.offset,
-            phantom: PhantomData,
-        }
+impl<I, F, S> Sealed for MapSpan<I, F, S> {}
+impl<'a, I, F, S> Input<'a> for MapSpan<I, F, S>
+where
+    I: Input<'a>,
+    S: Span + 'a,
+    F: Fn(I::Span) -> S + 'a,
+{
+    type Offset = I::Offset;
+    type Token = I::Token;
+    type Span = S;
+
+    #[inline(always)]
+    fn start(&self) -> Self::Offset {
+        self.input.start()
+    }
+
+    type TokenMaybe = I::TokenMaybe;
+
+    #[inline(always)]
+    unsafe fn next_maybe(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::TokenMaybe>) {
+        self.input.next_maybe(offset)
+    }
+
+    #[inline(always)]
+    unsafe fn token_at(&self, offset: Self::Offset) -> Option<Self::TokenMaybe> {
+        self.input.token_at(offset)
+    }
+
+    #[inline(always)]
+    unsafe fn span(&self, range: Range<Self::Offset>) -> Self::Span {
+        (self.f)(self.input.span(range))
+    }
+
+    #[inline(always)]
+    fn prev(offs: Self::Offset) -> Self::Offset {
+        I::prev(offs)
+    }
+
+    #[inline(always)]
+    fn is_boundary(&self, offset: Self::Offset) -> bool {
+        self.input.is_boundary(offset)
+    }
+
+    #[inline(always)]
+    unsafe fn token_distance(&self, from: Self::Offset, to: Self::Offset) -> usize {
+        self.input.token_distance(from, to)
+    }
+
+    #[inline(always)]
+    fn backtrack_exceeded_at(&self) -> Option<Self::Offset> {
+        self.input.backtrack_exceeded_at()
+    }
+
+    #[inline(always)]
+    fn starved_at(&self) -> Option<Self::Offset> {
+        self.input.starved_at()
+    }
+
+    #[inline(always)]
+    unsafe fn committed_to(&self, offset: Self::Offset) {
+        self.input.committed_to(offset)
     }
 }
 
-impl<'a, 'parse, I: Input<'a>> Copy for Marker<'a, 'parse, I> {}
-impl<'a, 'parse, I: Input<'a>> Clone for Marker<'a, 'parse, I> {
+impl<'a, I, F, S> ExactSizeInput<'a> for MapSpan<I, F, S>
+where
+    I: ExactSizeInput<'a>,
+    S: Span + 'a,
+    F: Fn(I::Span) -> S + 'a,
+{
     #[inline(always)]
-    fn clone(&self) -> Self {
-        *self
+    unsafe fn span_from(&self, range: RangeFrom<Self::Offset>) -> Self::Span {
+        (self.f)(self.input.span_from(range))
     }
 }
 
-/// Represents a location in an input.
-///
-/// If you to rewind to an old input location, see [`Marker`].
-pub struct Offset<'a, 'parse, I: Input<'a>> {
-    pub(crate) offset: I::Offset,
-    phantom: PhantomData<fn(&'parse ()) -> &'parse ()>, // Invariance
+impl<'a, I, F, S> ValueInput<'a> for MapSpan<I, F, S>
+where
+    I: ValueInput<'a>,
+    S: Span + 'a,
+    F: Fn(I::Span) -> S + 'a,
+{
+    #[inline(always)]
+    unsafe fn next(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::Token>) {
+        self.input.next(offset)
+    }
+
+    #[inline(always)]
+    unsafe fn find_token(&self, offset: Self::Offset, token: &Self::Token) -> Self::Offset
+    where
+        Self::Token: PartialEq,
+    {
+        self.input.find_token(offset, token)
+    }
 }
 
-impl<'a, 'parse, I: Input<'a>> Copy for Offset<'a, 'parse, I> {}
-impl<'a, 'parse, I: Input<'a>> Clone for Offset<'a, 'parse, I> {
+impl<'a, I, F, S> BorrowInput<'a> for MapSpan<I, F, S>
+where
+    I: BorrowInput<'a>,
+    S: Span + 'a,
+    F: Fn(I::Span) -> S + 'a,
+{
     #[inline(always)]
-    fn clone(&self) -> Self {
-        *self
+    unsafe fn next_ref(&self, offset: Self::Offset) -> (Self::Offset, Option<&'a Self::Token>) {
+        self.input.next_ref(offset)
     }
 }
 
-impl<'a, 'parse, I: Input<'a>> PartialEq for Offset<'a, 'parse, I> {
-    fn eq(&self, other: &Self) -> bool {
-        self.offset == other.offset
+impl<'a, I, F, S> SliceInput<'a> for MapSpan<I, F, S>
+where
+    I: SliceInput<'a>,
+    S: Span + 'a,
+    F: Fn(I::Span) -> S + 'a,
+{
+    type Slice = I::Slice;
+
+    #[inline(always)]
+    fn slice(&self, range: Range<Self::Offset>) -> Self::Slice {
+        <I as SliceInput>::slice(&self.input, range)
+    }
+
+    #[inline(always)]
+    fn try_slice(&self, range: Range<Self::Offset>) -> Result<Self::Slice, SliceError> {
+        <I as SliceInput>::try_slice(&self.input, range)
+    }
+
+    #[inline(always)]
+    fn slice_from(&self, from: RangeFrom<Self::Offset>) -> Self::Slice {
+        <I as SliceInput>::slice_from(&self.input, from)
+    }
+
+    #[inline(always)]
+    fn slices(&self, ranges: &[Range<Self::Offset>]) -> Vec<Self::Slice> {
+        <I as SliceInput>::slices(&self.input, ranges)
     }
 }
 
-pub(crate) struct Errors<T, E> {
-    pub(crate) alt: Option<Located<T, E>>,
-    pub(crate) secondary: Vec<Located<T, E>>,
+impl<'a, C, I, F, S> StrInput<'a, C> for MapSpan<I, F, S>
+where
+    I: StrInput<'a, C>,
+    S: Span + 'a,
+    F: Fn(I::Span) -> S + 'a,
+    C: Char,
+{
 }
 
-impl<T, E> Errors<T, E> {
-    /// Returns a slice of the secondary errors (if any) have been emitted since the given marker was created.
+/// An input wrapper that gives the spans of the wrapped input line and column information, with tabs expanded. See
+/// [`Input::with_tab_width`].
+#[derive(Clone)]
+pub struct WithTabWidth<I> {
+    input: I,
+    width: usize,
+    end: usize,
+    // Known positions in the input, in order: the start of every line, and points roughly `ANCHOR_STRIDE` bytes apart
+    // within each line. Other positions are found by walking forward from the nearest preceding one.
+    anchors: Vec<span::LineCol>,
+}
+
+impl<I> WithTabWidth<I> {
+    const ANCHOR_STRIDE: usize = 64;
+}
+
+impl<'a, I: StrInput<'a, char>> WithTabWidth<I> {
+    fn line_col(&self, offset: usize) -> span::LineCol {
+        let anchor = self.anchors[self.anchors.partition_point(|a| a.offset <= offset) - 1];
+        let column = self.input.slice(anchor.offset..offset).chars().fold(
+            anchor.column,
+            |column, c| match c {
+                '\t' => column + self.width - column % self.width,
+                _ => column + 1,
+            },
+        );
+        span::LineCol {
+            offset,
+            column,
+            ..anchor
+        }
+    }
+}
+
+impl<I> Sealed for WithTabWidth<I> {}
+impl<'a, I: StrInput<'a, char>> Input<'a> for WithTabWidth<I> {
+    type Offset = usize;
+    type Token = char;
+    type Span = SimpleSpan<span::LineCol>;
+
+    #[inline(always)]
+    fn start(&self) -> Self::Offset {
+        self.input.start()
+    }
+
+    type TokenMaybe = I::TokenMaybe;
+
+    #[inline(always)]
+    unsafe fn next_maybe(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::TokenMaybe>) {
+        self.input.next_maybe(offset)
+    }
+
+    #[inline(always)]
+    unsafe fn token_at(&self, offset: Self::Offset) -> Option<Self::TokenMaybe> {
+        self.input.token_at(offset)
+    }
+
     #[inline]
-    pub(crate) fn secondary_errors_since(&mut self, err_count: usize) -> &mut [Located<T, E>] {
-        self.secondary.get_mut(err_count..).unwrap_or(&mut [])
+    unsafe fn span(&self, range: Range<Self::Offset>) -> Self::Span {
+        SimpleSpan::new(self.line_col(range.start), self.line_col(range.end))
+    }
+
+    #[inline(always)]
+    fn prev(offs: Self::Offset) -> Self::Offset {
+        I::prev(offs)
+    }
+
+    #[inline(always)]
+    fn is_boundary(&self, offset: Self::Offset) -> bool {
+        self.input.is_boundary(offset)
+    }
+
+    #[inline(always)]
+    unsafe fn token_distance(&self, from: Self::Offset, to: Self::Offset) -> usize {
+        self.input.token_distance(from, to)
+    }
+
+    #[inline(always)]
+    fn backtrack_exceeded_at(&self) -> Option<Self::Offset> {
+        self.input.backtrack_exceeded_at()
+    }
+
+    #[inline(always)]
+    fn starved_at(&self) -> Option<Self::Offset> {
+        self.input.starved_at()
+    }
+
+    #[inline(always)]
+    unsafe fn committed_to(&self, offset: Self::Offset) {
+        self.input.committed_to(offset)
     }
 }
 
-impl<T, E> Default for Errors<T, E> {
-    fn default() -> Self {
-        Self {
-            alt: None,
-            secondary: Vec::new(),
-        }
+impl<'a, I: StrInput<'a, char>> ExactSizeInput<'a> for WithTabWidth<I> {
+    #[inline]
+    unsafe fn span_from(&self, range: RangeFrom<Self::Offset>) -> Self::Span {
+        SimpleSpan::new(self.line_col(range.start), self.line_col(self.end))
     }
 }
 
-/// Internal type representing the owned parts of an input - used at the top level by a call to
-/// `parse`.
-pub(crate) struct InputOwn<'a, 's, I: Input<'a>, E: ParserExtra<'a, I>> {
-    pub(crate) input: I,
-    pub(crate) errors: Errors<I::Offset, E::Error>,
-    pub(crate) state: MaybeMut<'s, E::State>,
-    pub(crate) ctx: E::Context,
-    #[cfg(feature = "memoization")]
-    pub(crate) memos: HashMap<(I::Offset, usize), Option<Located<I::Offset, E::Error>>>,
+impl<'a, I: StrInput<'a, char>> ValueInput<'a> for WithTabWidth<I> {
+    #[inline(always)]
+    unsafe fn next(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::Token>) {
+        self.input.next(offset)
+    }
+
+    #[inline(always)]
+    unsafe fn find_token(&self, offset: Self::Offset, token: &Self::Token) -> Self::Offset
+    where
+        Self::Token: PartialEq,
+    {
+        self.input.find_token(offset, token)
+    }
 }
 
-impl<'a, 's, I, E> InputOwn<'a, 's, I, E>
+impl<'a, I: StrInput<'a, char>> SliceInput<'a> for WithTabWidth<I> {
+    type Slice = I::Slice;
+
+    #[inline(always)]
+    fn slice(&self, range: Range<Self::Offset>) -> Self::Slice {
+        <I as SliceInput>::slice(&self.input, range)
+    }
+
+    #[inline(always)]
+    fn try_slice(&self, range: Range<Self::Offset>) -> Result<Self::Slice, SliceError> {
+        <I as SliceInput>::try_slice(&self.input, range)
+    }
+
+    #[inline(always)]
+    fn slice_from(&self, from: RangeFrom<Self::Offset>) -> Self::Slice {
+        <I as SliceInput>::slice_from(&self.input, from)
+    }
+
+    #[inline(always)]
+    fn slices(&self, ranges: &[Range<Self::Offset>]) -> Vec<Self::Slice> {
+        <I as SliceInput>::slices(&self.input, ranges)
+    }
+}
+
+impl<'a, I: StrInput<'a, char>> StrInput<'a, char> for WithTabWidth<I> {}
+
+/// An input wrapper that annotates the spans of the wrapped input with the direction of the text they cover. See
+/// [`Input::with_bidi`].
+#[derive(Copy, Clone)]
+pub struct WithBidi<I, F> {
+    input: I,
+    direction: F,
+}
+
+impl<'a, I: Input<'a>, F: Fn(&I::Token) -> Option<span::Direction>> WithBidi<I, F> {
+    /// Find the direction of the first strongly-directional token between `start` and `end` (if any).
+    ///
+    /// # Safety
+    ///
+    /// `start` must have been generated by the input.
+    unsafe fn direction_of(&self, start: I::Offset, end: Option<I::Offset>) -> span::Direction {
+        let mut offset = start;
+        while !matches!(end, Some(end) if offset >= end) {
+            match self.input.next_maybe(offset) {
+                (next, Some(tok)) => {
+                    if let Some(dir) = (self.direction)(tok.borrow()) {
+                        return dir;
+                    }
+                    offset = next;
+                }
+                (_, None) => break,
+            }
+        }
+        span::Direction::Ltr
+    }
+}
+
+impl<I, F> Sealed for WithBidi<I, F> {}
+impl<'a, I, F> Input<'a> for WithBidi<I, F>
 where
     I: Input<'a>,
-    E: ParserExtra<'a, I>,
+    F: Fn(&I::Token) -> Option<span::Direction> + 'a,
 {
-    #[cfg_attr(not(test), allow(dead_code))]
-    pub(crate) fn new(input: I) -> InputOwn<'a, 's, I, E>
-    where
-        E::State: Default,
-        E::Context: Default,
-    {
-        InputOwn {
-            input,
-            errors: Errors::default(),
-            state: MaybeMut::Val(E::State::default()),
-            ctx: E::Context::default(),
-            #[cfg(feature = "memoization")]
-            memos: HashMap::default(),
-        }
+    type Offset = I::Offset;
+    type Token = I::Token;
+    type Span = span::DirectionalSpan<I::Span>;
+
+    #[inline(always)]
+    fn start(&self) -> Self::Offset {
+        self.input.start()
+    }
+
+    type TokenMaybe = I::TokenMaybe;
+
+    #[inline(always)]
+    unsafe fn next_maybe(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::TokenMaybe>) {
+        self.input.next_maybe(offset)
+    }
+
+    #[inline(always)]
+    unsafe fn token_at(&self, offset: Self::Offset) -> Option<Self::TokenMaybe> {
+        self.input.token_at(offset)
+    }
+
+    #[inline]
+    unsafe fn span(&self, range: Range<Self::Offset>) -> Self::Span {
+        span::DirectionalSpan {
+            direction: self.direction_of(range.start, Some(range.end)),
+            span: self.input.span(range),
+        }
+    }
+
+    #[inline(always)]
+    fn prev(offs: Self::Offset) -> Self::Offset {
+        I::prev(offs)
+    }
+
+    #[inline(always)]
+    fn is_boundary(&self, offset: Self::Offset) -> bool {
+        self.input.is_boundary(offset)
+    }
+
+    #[inline(always)]
+    unsafe fn token_distance(&self, from: Self::Offset, to: Self::Offset) -> usize {
+        self.input.token_distance(from, to)
+    }
+
+    #[inline(always)]
+    fn backtrack_exceeded_at(&self) -> Option<Self::Offset> {
+        self.input.backtrack_exceeded_at()
+    }
+
+    #[inline(always)]
+    fn starved_at(&self) -> Option<Self::Offset> {
+        self.input.starved_at()
+    }
+
+    #[inline(always)]
+    unsafe fn committed_to(&self, offset: Self::Offset) {
+        self.input.committed_to(offset)
+    }
+}
+
+impl<'a, I, F> ExactSizeInput<'a> for WithBidi<I, F>
+where
+    I: ExactSizeInput<'a>,
+    F: Fn(&I::Token) -> Option<span::Direction> + 'a,
+{
+    #[inline]
+    unsafe fn span_from(&self, range: RangeFrom<Self::Offset>) -> Self::Span {
+        span::DirectionalSpan {
+            direction: self.direction_of(range.start, None),
+            span: self.input.span_from(range),
+        }
+    }
+}
+
+impl<'a, I, F> ValueInput<'a> for WithBidi<I, F>
+where
+    I: ValueInput<'a>,
+    F: Fn(&I::Token) -> Option<span::Direction> + 'a,
+{
+    #[inline(always)]
+    unsafe fn next(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::Token>) {
+        self.input.next(offset)
+    }
+}
+
+impl<'a, I, F> BorrowInput<'a> for WithBidi<I, F>
+where
+    I: BorrowInput<'a>,
+    F: Fn(&I::Token) -> Option<span::Direction> + 'a,
+{
+    #[inline(always)]
+    unsafe fn next_ref(&self, offset: Self::Offset) -> (Self::Offset, Option<&'a Self::Token>) {
+        self.input.next_ref(offset)
+    }
+}
+
+impl<'a, I, F> SliceInput<'a> for WithBidi<I, F>
+where
+    I: SliceInput<'a>,
+    F: Fn(&I::Token) -> Option<span::Direction> + 'a,
+{
+    type Slice = I::Slice;
+
+    #[inline(always)]
+    fn slice(&self, range: Range<Self::Offset>) -> Self::Slice {
+        <I as SliceInput>::slice(&self.input, range)
+    }
+
+    #[inline(always)]
+    fn try_slice(&self, range: Range<Self::Offset>) -> Result<Self::Slice, SliceError> {
+        <I as SliceInput>::try_slice(&self.input, range)
+    }
+
+    #[inline(always)]
+    fn slice_from(&self, from: RangeFrom<Self::Offset>) -> Self::Slice {
+        <I as SliceInput>::slice_from(&self.input, from)
+    }
+
+    #[inline(always)]
+    fn slices(&self, ranges: &[Range<Self::Offset>]) -> Vec<Self::Slice> {
+        <I as SliceInput>::slices(&self.input, ranges)
+    }
+}
+
+impl<'a, C, I, F> StrInput<'a, C> for WithBidi<I, F>
+where
+    I: StrInput<'a, C>,
+    F: Fn(&I::Token) -> Option<span::Direction> + 'a,
+    C: Char,
+{
+}
+
+/// An input wrapper that reports each token of the wrapped input to a callback once parsing can no longer backtrack to
+/// it. See [`Input::tee`].
+pub struct Tee<'a, I: Input<'a>, F> {
+    input: I,
+    f: F,
+    // The offset up to which tokens have been reported
+    reported: Cell<I::Offset>,
+}
+
+impl<'a, I: Input<'a>, F: Fn(&I::Token, I::Span)> Tee<'a, I, F> {
+    /// Report all tokens that have not yet been reported and that lie before `offset`.
+    ///
+    /// # Safety
+    ///
+    /// `offset` must have been generated by the input.
+    #[inline]
+    unsafe fn report_to(&self, offset: I::Offset) {
+        let mut reported = self.reported.get();
+        while reported < offset {
+            match self.input.next_maybe(reported) {
+                (next, Some(tok)) => {
+                    (self.f)(tok.borrow(), self.input.span(reported..next));
+                    reported = next;
+                }
+                (_, None) => break,
+            }
+        }
+        self.reported.set(reported);
+    }
+}
+
+impl<'a, I: Input<'a>, F> Sealed for Tee<'a, I, F> {}
+impl<'a, I, F> Input<'a> for Tee<'a, I, F>
+where
+    I: Input<'a>,
+    F: Fn(&I::Token, I::Span) + 'a,
+{
+    type Offset = I::Offset;
+    type Token = I::Token;
+    type Span = I::Span;
+
+    #[inline(always)]
+    fn start(&self) -> Self::Offset {
+        self.input.start()
+    }
+
+    type TokenMaybe = I::TokenMaybe;
+
+    #[inline(always)]
+    unsafe fn next_maybe(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::TokenMaybe>) {
+        self.input.next_maybe(offset)
+    }
+
+    #[inline(always)]
+    unsafe fn token_at(&self, offset: Self::Offset) -> Option<Self::TokenMaybe> {
+        self.input.token_at(offset)
+    }
+
+    #[inline(always)]
+    unsafe fn span(&self, range: Range<Self::Offset>) -> Self::Span {
+        self.input.span(range)
+    }
+
+    #[inline(always)]
+    fn prev(offs: Self::Offset) -> Self::Offset {
+        I::prev(offs)
+    }
+
+    #[inline(always)]
+    fn is_boundary(&self, offset: Self::Offset) -> bool {
+        self.input.is_boundary(offset)
+    }
+
+    #[inline(always)]
+    unsafe fn token_distance(&self, from: Self::Offset, to: Self::Offset) -> usize {
+        self.input.token_distance(from, to)
+    }
+
+    #[inline(always)]
+    fn backtrack_exceeded_at(&self) -> Option<Self::Offset> {
+        self.input.backtrack_exceeded_at()
+    }
+
+    #[inline(always)]
+    fn starved_at(&self) -> Option<Self::Offset> {
+        self.input.starved_at()
+    }
+
+    #[inline]
+    unsafe fn committed_to(&self, offset: Self::Offset) {
+        self.report_to(offset);
+        self.input.committed_to(offset)
+    }
+}
+
+impl<'a, I, F> ExactSizeInput<'a> for Tee<'a, I, F>
+where
+    I: ExactSizeInput<'a>,
+    F: Fn(&I::Token, I::Span) + 'a,
+{
+    #[inline(always)]
+    unsafe fn span_from(&self, range: RangeFrom<Self::Offset>) -> Self::Span {
+        self.input.span_from(range)
+    }
+}
+
+impl<'a, I, F> ValueInput<'a> for Tee<'a, I, F>
+where
+    I: ValueInput<'a>,
+    F: Fn(&I::Token, I::Span) + 'a,
+{
+    #[inline(always)]
+    unsafe fn next(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::Token>) {
+        self.input.next(offset)
+    }
+}
+
+impl<'a, I, F> BorrowInput<'a> for Tee<'a, I, F>
+where
+    I: BorrowInput<'a>,
+    F: Fn(&I::Token, I::Span) + 'a,
+{
+    #[inline(always)]
+    unsafe fn next_ref(&self, offset: Self::Offset) -> (Self::Offset, Option<&'a Self::Token>) {
+        self.input.next_ref(offset)
+    }
+}
+
+impl<'a, I, F> SliceInput<'a> for Tee<'a, I, F>
+where
+    I: SliceInput<'a>,
+    F: Fn(&I::Token, I::Span) + 'a,
+{
+    type Slice = I::Slice;
+
+    #[inline(always)]
+    fn slice(&self, range: Range<Self::Offset>) -> Self::Slice {
+        <I as SliceInput>::slice(&self.input, range)
+    }
+
+    #[inline(always)]
+    fn try_slice(&self, range: Range<Self::Offset>) -> Result<Self::Slice, SliceError> {
+        <I as SliceInput>::try_slice(&self.input, range)
+    }
+
+    #[inline(always)]
+    fn slice_from(&self, from: RangeFrom<Self::Offset>) -> Self::Slice {
+        <I as SliceInput>::slice_from(&self.input, from)
+    }
+
+    #[inline(always)]
+    fn slices(&self, ranges: &[Range<Self::Offset>]) -> Vec<Self::Slice> {
+        <I as SliceInput>::slices(&self.input, ranges)
+    }
+}
+
+impl<'a, C, I, F> StrInput<'a, C> for Tee<'a, I, F>
+where
+    I: StrInput<'a, C>,
+    F: Fn(&C, I::Span) + 'a,
+    C: Char,
+{
+}
+
+/// An input wrapper that classifies the tokens of the wrapped input into kinds. See [`Input::with_kinds`].
+#[derive(Copy, Clone)]
+pub struct WithKinds<I, F> {
+    input: I,
+    classify: F,
+}
+
+impl<I, F> Sealed for WithKinds<I, F> {}
+impl<'a, I, F> Input<'a> for WithKinds<I, F>
+where
+    I: Input<'a>,
+    F: Fn(&I::Token) -> u8 + 'a,
+{
+    type Offset = I::Offset;
+    type Token = I::Token;
+    type Span = I::Span;
+
+    #[inline(always)]
+    fn start(&self) -> Self::Offset {
+        self.input.start()
+    }
+
+    type TokenMaybe = I::TokenMaybe;
+
+    #[inline(always)]
+    unsafe fn next_maybe(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::TokenMaybe>) {
+        self.input.next_maybe(offset)
+    }
+
+    #[inline(always)]
+    unsafe fn token_at(&self, offset: Self::Offset) -> Option<Self::TokenMaybe> {
+        self.input.token_at(offset)
+    }
+
+    #[inline(always)]
+    unsafe fn span(&self, range: Range<Self::Offset>) -> Self::Span {
+        self.input.span(range)
+    }
+
+    #[inline(always)]
+    fn prev(offs: Self::Offset) -> Self::Offset {
+        I::prev(offs)
+    }
+
+    #[inline(always)]
+    fn is_boundary(&self, offset: Self::Offset) -> bool {
+        self.input.is_boundary(offset)
+    }
+
+    #[inline(always)]
+    unsafe fn token_distance(&self, from: Self::Offset, to: Self::Offset) -> usize {
+        self.input.token_distance(from, to)
+    }
+
+    #[inline(always)]
+    fn backtrack_exceeded_at(&self) -> Option<Self::Offset> {
+        self.input.backtrack_exceeded_at()
+    }
+
+    #[inline(always)]
+    fn starved_at(&self) -> Option<Self::Offset> {
+        self.input.starved_at()
+    }
+
+    #[inline(always)]
+    unsafe fn committed_to(&self, offset: Self::Offset) {
+        self.input.committed_to(offset)
+    }
+}
+
+impl<'a, I, F> ExactSizeInput<'a> for WithKinds<I, F>
+where
+    I: ExactSizeInput<'a>,
+    F: Fn(&I::Token) -> u8 + 'a,
+{
+    #[inline(always)]
+    unsafe fn span_from(&self, range: RangeFrom<Self::Offset>) -> Self::Span {
+        self.input.span_from(range)
+    }
+}
+
+impl<'a, I, F> ValueInput<'a> for WithKinds<I, F>
+where
+    I: ValueInput<'a>,
+    F: Fn(&I::Token) -> u8 + 'a,
+{
+    #[inline(always)]
+    unsafe fn next(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::Token>) {
+        self.input.next(offset)
+    }
+
+    #[inline(always)]
+    unsafe fn find_token(&self, offset: Self::Offset, token: &Self::Token) -> Self::Offset
+    where
+        Self::Token: PartialEq,
+    {
+        self.input.find_token(offset, token)
+    }
+}
+
+impl<'a, I, F> BorrowInput<'a> for WithKinds<I, F>
+where
+    I: BorrowInput<'a>,
+    F: Fn(&I::Token) -> u8 + 'a,
+{
+    #[inline(always)]
+    unsafe fn next_ref(&self, offset: Self::Offset) -> (Self::Offset, Option<&'a Self::Token>) {
+        self.input.next_ref(offset)
+    }
+}
+
+impl<'a, I, F> SliceInput<'a> for WithKinds<I, F>
+where
+    I: SliceInput<'a>,
+    F: Fn(&I::Token) -> u8 + 'a,
+{
+    type Slice = I::Slice;
+
+    #[inline(always)]
+    fn slice(&self, range: Range<Self::Offset>) -> Self::Slice {
+        <I as SliceInput>::slice(&self.input, range)
+    }
+
+    #[inline(always)]
+    fn try_slice(&self, range: Range<Self::Offset>) -> Result<Self::Slice, SliceError> {
+        <I as SliceInput>::try_slice(&self.input, range)
+    }
+
+    #[inline(always)]
+    fn slice_from(&self, from: RangeFrom<Self::Offset>) -> Self::Slice {
+        <I as SliceInput>::slice_from(&self.input, from)
+    }
+
+    #[inline(always)]
+    fn slices(&self, ranges: &[Range<Self::Offset>]) -> Vec<Self::Slice> {
+        <I as SliceInput>::slices(&self.input, ranges)
+    }
+}
+
+impl<'a, C, I, F> StrInput<'a, C> for WithKinds<I, F>
+where
+    I: StrInput<'a, C>,
+    F: Fn(&C) -> u8 + 'a,
+    C: Char,
+{
+}
+
+/// An input wrapper that shifts the spans of the wrapped input forward by a fixed number of offsets. See
+/// [`Input::offset_by`].
+#[derive(Copy, Clone)]
+pub struct OffsetBy<I, O = usize> {
+    input: I,
+    base: O,
+}
+
+impl<I, O: Copy + core::ops::Add<Output = O>> OffsetBy<I, O> {
+    #[inline(always)]
+    fn shift<S: Span<Offset = O>>(&self, span: S) -> S {
+        S::new(
+            span.context(),
+            span.start() + self.base..span.end() + self.base,
+        )
+    }
+}
+
+impl<I, O> Sealed for OffsetBy<I, O> {}
+impl<'a, I: Input<'a>, O> Input<'a> for OffsetBy<I, O>
+where
+    I::Span: Span<Offset = O>,
+    O: Copy + core::ops::Add<Output = O> + 'a,
+{
+    type Offset = I::Offset;
+    type Token = I::Token;
+    type Span = I::Span;
+
+    #[inline(always)]
+    fn start(&self) -> Self::Offset {
+        self.input.start()
+    }
+
+    type TokenMaybe = I::TokenMaybe;
+
+    #[inline(always)]
+    unsafe fn next_maybe(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::TokenMaybe>) {
+        self.input.next_maybe(offset)
+    }
+
+    #[inline(always)]
+    unsafe fn token_at(&self, offset: Self::Offset) -> Option<Self::TokenMaybe> {
+        self.input.token_at(offset)
+    }
+
+    #[inline(always)]
+    unsafe fn span(&self, range: Range<Self::Offset>) -> Self::Span {
+        self.shift(self.input.span(range))
+    }
+
+    #[inline(always)]
+    fn prev(offs: Self::Offset) -> Self::Offset {
+        I::prev(offs)
+    }
+
+    #[inline(always)]
+    fn is_boundary(&self, offset: Self::Offset) -> bool {
+        self.input.is_boundary(offset)
+    }
+
+    #[inline(always)]
+    unsafe fn token_distance(&self, from: Self::Offset, to: Self::Offset) -> usize {
+        self.input.token_distance(from, to)
+    }
+
+    #[inline(always)]
+    fn backtrack_exceeded_at(&self) -> Option<Self::Offset> {
+        self.input.backtrack_exceeded_at()
+    }
+
+    #[inline(always)]
+    fn starved_at(&self) -> Option<Self::Offset> {
+        self.input.starved_at()
+    }
+
+    #[inline(always)]
+    unsafe fn committed_to(&self, offset: Self::Offset) {
+        self.input.committed_to(offset)
+    }
+}
+
+impl<'a, I: ExactSizeInput<'a>, O> ExactSizeInput<'a> for OffsetBy<I, O>
+where
+    I::Span: Span<Offset = O>,
+    O: Copy + core::ops::Add<Output = O> + 'a,
+{
+    #[inline(always)]
+    unsafe fn span_from(&self, range: RangeFrom<Self::Offset>) -> Self::Span {
+        self.shift(self.input.span_from(range))
+    }
+}
+
+impl<'a, I: ValueInput<'a>, O> ValueInput<'a> for OffsetBy<I, O>
+where
+    I::Span: Span<Offset = O>,
+    O: Copy + core::ops::Add<Output = O> + 'a,
+{
+    #[inline(always)]
+    unsafe fn next(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::Token>) {
+        self.input.next(offset)
+    }
+
+    #[inline(always)]
+    unsafe fn find_token(&self, offset: Self::Offset, token: &Self::Token) -> Self::Offset
+    where
+        Self::Token: PartialEq,
+    {
+        self.input.find_token(offset, token)
+    }
+}
+
+impl<'a, I: BorrowInput<'a>, O> BorrowInput<'a> for OffsetBy<I, O>
+where
+    I::Span: Span<Offset = O>,
+    O: Copy + core::ops::Add<Output = O> + 'a,
+{
+    #[inline(always)]
+    unsafe fn next_ref(&self, offset: Self::Offset) -> (Self::Offset, Option<&'a Self::Token>) {
+        self.input.next_ref(offset)
+    }
+}
+
+impl<'a, I: SliceInput<'a>, O> SliceInput<'a> for OffsetBy<I, O>
+where
+    I::Span: Span<Offset = O>,
+    O: Copy + core::ops::Add<Output = O> + 'a,
+{
+    type Slice = I::Slice;
+
+    #[inline(always)]
+    fn slice(&self, range: Range<Self::Offset>) -> Self::Slice {
+        <I as SliceInput>::slice(&self.input, range)
+    }
+
+    #[inline(always)]
+    fn try_slice(&self, range: Range<Self::Offset>) -> Result<Self::Slice, SliceError> {
+        <I as SliceInput>::try_slice(&self.input, range)
+    }
+
+    #[inline(always)]
+    fn slice_from(&self, from: RangeFrom<Self::Offset>) -> Self::Slice {
+        <I as SliceInput>::slice_from(&self.input, from)
+    }
+
+    #[inline(always)]
+    fn slices(&self, ranges: &[Range<Self::Offset>]) -> Vec<Self::Slice> {
+        <I as SliceInput>::slices(&self.input, ranges)
+    }
+}
+
+impl<'a, C, I, O> StrInput<'a, C> for OffsetBy<I, O>
+where
+    I: StrInput<'a, C>,
+    I::Span: Span<Offset = O>,
+    O: Copy + core::ops::Add<Output = O> + 'a,
+    C: Char,
+{
+}
+
+/// Create a string input for a fragment of a larger document, whose spans are given in terms of the document rather
+/// than the fragment, as if the fragment began at offset `base`.
+///
+/// This is equivalent to [`s.offset_by(base)`](Input::offset_by): offsets within the fragment remain zero-based, and
+/// `base` is only added to the spans that the parser generates.
+///
+/// # Examples
+///
+/// ```
+/// # use chumsky::{prelude::*, input::str_input_based_at};
+/// let doc = "# Example\n```\nfoo?\n```\n";
+/// let (lo, hi) = (doc.find("foo").unwrap(), doc.rfind("\n```").unwrap());
+///
+/// let ident = text::ident::<_, _, extra::Err<Rich<char>>>().then_ignore(end());
+/// let errs = ident.parse(str_input_based_at(&doc[lo..hi], lo)).into_errors();
+/// assert_eq!(errs[0].span(), &SimpleSpan::new(17, 18));
+/// ```
+#[inline]
+pub fn str_input_based_at(s: &str, base: usize) -> OffsetBy<&str> {
+    s.offset_by(base)
+}
+
+/// An input wrapper that restricts the wrapped input to a range of offsets, without renumbering them. See
+/// [`Input::window`].
+#[derive(Copy, Clone)]
+pub struct Window<I> {
+    input: I,
+    lo: usize,
+    // The end of the window, or `OPEN_WINDOW` if it extends to the end of the wrapped input
+    hi: usize,
+}
+
+// A window end beyond any real offset, used by `Input::require_prefix` to avoid finding the end of the input up front
+const OPEN_WINDOW: usize = usize::MAX;
+
+impl<I> Sealed for Window<I> {}
+impl<'a, I: Input<'a, Offset = usize>> Input<'a> for Window<I> {
+    type Offset = usize;
+    type Token = I::Token;
+    type Span = I::Span;
+
+    #[inline(always)]
+    fn start(&self) -> Self::Offset {
+        self.lo
+    }
+
+    type TokenMaybe = I::TokenMaybe;
+
+    #[inline(always)]
+    unsafe fn next_maybe(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::TokenMaybe>) {
+        if offset >= self.hi {
+            return (offset, None);
+        }
+        match self.input.next_maybe(offset) {
+            (next, _) if next > self.hi => (offset, None),
+            next => next,
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn span(&self, range: Range<Self::Offset>) -> Self::Span {
+        self.input.span(range)
+    }
+
+    #[inline(always)]
+    fn prev(offs: Self::Offset) -> Self::Offset {
+        I::prev(offs)
+    }
+
+    #[inline(always)]
+    fn is_boundary(&self, offset: Self::Offset) -> bool {
+        (self.lo..=self.hi).contains(&offset) && self.input.is_boundary(offset)
+    }
+
+    #[inline(always)]
+    unsafe fn token_distance(&self, from: Self::Offset, to: Self::Offset) -> usize {
+        self.input.token_distance(from, to)
+    }
+
+    #[inline(always)]
+    fn backtrack_exceeded_at(&self) -> Option<Self::Offset> {
+        self.input.backtrack_exceeded_at()
+    }
+
+    #[inline(always)]
+    fn starved_at(&self) -> Option<Self::Offset> {
+        self.input.starved_at()
+    }
+
+    #[inline(always)]
+    unsafe fn committed_to(&self, offset: Self::Offset) {
+        self.input.committed_to(offset)
+    }
+}
+
+impl<'a, I: Input<'a, Offset = usize>> ExactSizeInput<'a> for Window<I> {
+    #[inline(always)]
+    unsafe fn span_from(&self, range: RangeFrom<Self::Offset>) -> Self::Span {
+        let end = if self.hi == OPEN_WINDOW {
+            // The end of the wrapped input is only found when it is needed
+            let mut end = range.start;
+            // SAFETY: `end` is either the given offset or was generated by a previous call to `Input::next_maybe`
+            while let (next, Some(_)) = self.input.next_maybe(end) {
+                end = next;
+            }
+            end
+        } else {
+            self.hi
+        };
+        self.input.span(range.start..end)
+    }
+}
+
+impl<'a, I: ValueInput<'a, Offset = usize>> ValueInput<'a> for Window<I> {
+    #[inline(always)]
+    unsafe fn next(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::Token>) {
+        if offset >= self.hi {
+            return (offset, None);
+        }
+        match self.input.next(offset) {
+            (next, _) if next > self.hi => (offset, None),
+            next => next,
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn find_token(&self, offset: Self::Offset, token: &Self::Token) -> Self::Offset
+    where
+        Self::Token: PartialEq,
+    {
+        if offset >= self.hi {
+            return offset;
+        }
+        self.input.find_token(offset, token).min(self.hi)
+    }
+}
+
+impl<'a, I: BorrowInput<'a, Offset = usize>> BorrowInput<'a> for Window<I> {
+    #[inline(always)]
+    unsafe fn next_ref(&self, offset: Self::Offset) -> (Self::Offset, Option<&'a Self::Token>) {
+        if offset >= self.hi {
+            return (offset, None);
+        }
+        match self.input.next_ref(offset) {
+            (next, _) if next > self.hi => (offset, None),
+            next => next,
+        }
+    }
+}
+
+impl<'a, I: SliceInput<'a, Offset = usize>> SliceInput<'a> for Window<I> {
+    type Slice = I::Slice;
+
+    #[inline(always)]
+    fn slice(&self, range: Range<Self::Offset>) -> Self::Slice {
+        <I as SliceInput>::slice(&self.input, range)
+    }
+
+    #[inline(always)]
+    fn try_slice(&self, range: Range<Self::Offset>) -> Result<Self::Slice, SliceError> {
+        <I as SliceInput>::try_slice(&self.input, range)
+    }
+
+    #[inline(always)]
+    fn slice_from(&self, from: RangeFrom<Self::Offset>) -> Self::Slice {
+        if self.hi == OPEN_WINDOW {
+            <I as SliceInput>::slice_from(&self.input, from)
+        } else {
+            <I as SliceInput>::slice(&self.input, from.start..self.hi)
+        }
+    }
+
+    #[inline(always)]
+    fn slices(&self, ranges: &[Range<Self::Offset>]) -> Vec<Self::Slice> {
+        <I as SliceInput>::slices(&self.input, ranges)
+    }
+}
+
+impl<'a, C, I> StrInput<'a, C> for Window<I>
+where
+    I: StrInput<'a, C>,
+    C: Char,
+{
+}
+
+/// An input wrapper that presents the characters of the wrapped input in lowercase, while slices retain their original
+/// casing. See [`Input::case_fold`].
+#[derive(Copy, Clone)]
+pub struct CaseFold<I> {
+    input: I,
+}
+
+#[inline(always)]
+fn fold_char(c: char) -> char {
+    let mut lower = c.to_lowercase();
+    match (lower.next(), lower.next()) {
+        (Some(l), None) => l,
+        _ => c,
+    }
+}
+
+impl<I> Sealed for CaseFold<I> {}
+impl<'a, I: StrInput<'a, char>> Input<'a> for CaseFold<I> {
+    type Offset = I::Offset;
+    type Token = char;
+    type Span = I::Span;
+
+    #[inline(always)]
+    fn start(&self) -> Self::Offset {
+        self.input.start()
+    }
+
+    type TokenMaybe = char;
+
+    #[inline(always)]
+    unsafe fn next_maybe(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::TokenMaybe>) {
+        self.next(offset)
+    }
+
+    #[inline(always)]
+    unsafe fn span(&self, range: Range<Self::Offset>) -> Self::Span {
+        self.input.span(range)
+    }
+
+    #[inline(always)]
+    fn prev(offs: Self::Offset) -> Self::Offset {
+        I::prev(offs)
+    }
+
+    #[inline(always)]
+    fn is_boundary(&self, offset: Self::Offset) -> bool {
+        self.input.is_boundary(offset)
+    }
+
+    #[inline(always)]
+    unsafe fn token_distance(&self, from: Self::Offset, to: Self::Offset) -> usize {
+        self.input.token_distance(from, to)
+    }
+
+    #[inline(always)]
+    fn backtrack_exceeded_at(&self) -> Option<Self::Offset> {
+        self.input.backtrack_exceeded_at()
+    }
+
+    #[inline(always)]
+    fn starved_at(&self) -> Option<Self::Offset> {
+        self.input.starved_at()
+    }
+
+    #[inline(always)]
+    unsafe fn committed_to(&self, offset: Self::Offset) {
+        self.input.committed_to(offset)
+    }
+}
+
+impl<'a, I: StrInput<'a, char> + ExactSizeInput<'a>> ExactSizeInput<'a> for CaseFold<I> {
+    #[inline(always)]
+    unsafe fn span_from(&self, range: RangeFrom<Self::Offset>) -> Self::Span {
+        self.input.span_from(range)
+    }
+}
+
+impl<'a, I: StrInput<'a, char>> ValueInput<'a> for CaseFold<I> {
+    #[inline(always)]
+    unsafe fn next(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::Token>) {
+        let (offset, tok) = self.input.next(offset);
+        (offset, tok.map(fold_char))
+    }
+}
+
+impl<'a, I: StrInput<'a, char>> SliceInput<'a> for CaseFold<I> {
+    type Slice = I::Slice;
+
+    #[inline(always)]
+    fn slice(&self, range: Range<Self::Offset>) -> Self::Slice {
+        <I as SliceInput>::slice(&self.input, range)
+    }
+
+    #[inline(always)]
+    fn try_slice(&self, range: Range<Self::Offset>) -> Result<Self::Slice, SliceError> {
+        <I as SliceInput>::try_slice(&self.input, range)
+    }
+
+    #[inline(always)]
+    fn slice_from(&self, from: RangeFrom<Self::Offset>) -> Self::Slice {
+        <I as SliceInput>::slice_from(&self.input, from)
+    }
+
+    #[inline(always)]
+    fn slices(&self, ranges: &[Range<Self::Offset>]) -> Vec<Self::Slice> {
+        <I as SliceInput>::slices(&self.input, ranges)
+    }
+}
+
+impl<'a, I: StrInput<'a, char>> StrInput<'a, char> for CaseFold<I> {}
+
+/// An input wrapper that merges runs of consecutive tokens into single tokens. See [`Input::coalesce`].
+#[derive(Copy, Clone)]
+pub struct Coalesce<I, F> {
+    input: I,
+    f: F,
+}
+
+impl<I, F> Sealed for Coalesce<I, F> {}
+impl<'a, I, F> Input<'a> for Coalesce<I, F>
+where
+    I: ValueInput<'a>,
+    F: Fn(&I::Token, &I::Token) -> Option<I::Token> + 'a,
+{
+    type Offset = I::Offset;
+    type Token = I::Token;
+    type Span = I::Span;
+
+    #[inline(always)]
+    fn start(&self) -> Self::Offset {
+        self.input.start()
+    }
+
+    type TokenMaybe = I::Token;
+
+    #[inline(always)]
+    unsafe fn next_maybe(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::TokenMaybe>) {
+        self.next(offset)
+    }
+
+    #[inline(always)]
+    unsafe fn span(&self, range: Range<Self::Offset>) -> Self::Span {
+        self.input.span(range)
+    }
+
+    #[inline(always)]
+    fn prev(offs: Self::Offset) -> Self::Offset {
+        I::prev(offs)
+    }
+
+    #[inline(always)]
+    fn is_boundary(&self, offset: Self::Offset) -> bool {
+        self.input.is_boundary(offset)
+    }
+
+    #[inline(always)]
+    fn backtrack_exceeded_at(&self) -> Option<Self::Offset> {
+        self.input.backtrack_exceeded_at()
+    }
+
+    #[inline(always)]
+    fn starved_at(&self) -> Option<Self::Offset> {
+        self.input.starved_at()
+    }
+
+    #[inline(always)]
+    unsafe fn committed_to(&self, offset: Self::Offset) {
+        self.input.committed_to(offset)
+    }
+}
+
+impl<'a, I, F> ExactSizeInput<'a> for Coalesce<I, F>
+where
+    I: ValueInput<'a> + ExactSizeInput<'a>,
+    F: Fn(&I::Token, &I::Token) -> Option<I::Token> + 'a,
+{
+    #[inline(always)]
+    unsafe fn span_from(&self, range: RangeFrom<Self::Offset>) -> Self::Span {
+        self.input.span_from(range)
+    }
+}
+
+impl<'a, I, F> ValueInput<'a> for Coalesce<I, F>
+where
+    I: ValueInput<'a>,
+    F: Fn(&I::Token, &I::Token) -> Option<I::Token> + 'a,
+{
+    #[inline]
+    unsafe fn next(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::Token>) {
+        let (mut offset, mut tok) = match self.input.next(offset) {
+            (offset, Some(tok)) => (offset, tok),
+            (offset, None) => return (offset, None),
+        };
+        while let (next, Some(following)) = self.input.next(offset) {
+            match (self.f)(&tok, &following) {
+                Some(merged) => {
+                    tok = merged;
+                    offset = next;
+                }
+                None => break,
+            }
+        }
+        (offset, Some(tok))
+    }
+}
+
+impl<'a, I, F> SliceInput<'a> for Coalesce<I, F>
+where
+    I: ValueInput<'a> + SliceInput<'a>,
+    F: Fn(&I::Token, &I::Token) -> Option<I::Token> + 'a,
+{
+    type Slice = I::Slice;
+
+    #[inline(always)]
+    fn slice(&self, range: Range<Self::Offset>) -> Self::Slice {
+        <I as SliceInput>::slice(&self.input, range)
+    }
+
+    #[inline(always)]
+    fn try_slice(&self, range: Range<Self::Offset>) -> Result<Self::Slice, SliceError> {
+        <I as SliceInput>::try_slice(&self.input, range)
+    }
+
+    #[inline(always)]
+    fn slice_from(&self, from: RangeFrom<Self::Offset>) -> Self::Slice {
+        <I as SliceInput>::slice_from(&self.input, from)
+    }
+
+    #[inline(always)]
+    fn slices(&self, ranges: &[Range<Self::Offset>]) -> Vec<Self::Slice> {
+        <I as SliceInput>::slices(&self.input, ranges)
+    }
+}
+
+/// An input wrapper that yields the tokens of the wrapped input in reverse order. See [`Input::reversed`].
+#[derive(Clone)]
+pub struct Reversed<'a, I: Input<'a>> {
+    input: I,
+    // The offsets of the boundaries between the tokens of the wrapped input, in input order, including its start and
+    // end. The reversed offset `n` corresponds to the `n`th boundary from the end.
+    boundaries: Vec<I::Offset>,
+}
+
+impl<'a, I: Input<'a>> Reversed<'a, I> {
+    // Find the offset within the wrapped input that corresponds to the given reversed offset.
+    #[inline(always)]
+    fn boundary(&self, offset: usize) -> I::Offset {
+        self.boundaries[self.boundaries.len() - 1 - offset]
+    }
+
+    // Convert a reversed range into the range of the wrapped input that it covers.
+    #[inline(always)]
+    fn original(&self, range: Range<usize>) -> Range<I::Offset> {
+        self.boundary(range.end)..self.boundary(range.start)
+    }
+
+    // Find the reversed offset at which the token of the wrapped input that starts at the given offset begins. The end
+    // of the wrapped input corresponds to the start of the reversed input.
+    #[inline]
+    fn reversed_offset(&self, offset: I::Offset) -> usize {
+        let index = self
+            .boundaries
+            .partition_point(|boundary| *boundary < offset);
+        self.boundaries.len().saturating_sub(index + 2)
+    }
+}
+
+impl<'a, I: Input<'a>> Sealed for Reversed<'a, I> {}
+impl<'a, I: Input<'a>> Input<'a> for Reversed<'a, I> {
+    type Offset = usize;
+    type Token = I::Token;
+    type Span = I::Span;
+
+    #[inline(always)]
+    fn start(&self) -> Self::Offset {
+        0
+    }
+
+    type TokenMaybe = I::TokenMaybe;
+
+    #[inline(always)]
+    unsafe fn next_maybe(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::TokenMaybe>) {
+        match self.token_at(offset) {
+            Some(tok) => (offset + 1, Some(tok)),
+            None => (offset, None),
+        }
+    }
+
+    #[inline]
+    unsafe fn token_at(&self, offset: Self::Offset) -> Option<Self::TokenMaybe> {
+        if offset + 1 < self.boundaries.len() {
+            self.input.token_at(self.boundary(offset + 1))
+        } else {
+            None
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn span(&self, range: Range<Self::Offset>) -> Self::Span {
+        self.input.span(self.original(range))
+    }
+
+    #[inline(always)]
+    fn prev(offs: Self::Offset) -> Self::Offset {
+        offs.saturating_sub(1)
+    }
+
+    #[inline(always)]
+    fn is_boundary(&self, offset: Self::Offset) -> bool {
+        offset < self.boundaries.len()
+    }
+
+    #[inline(always)]
+    unsafe fn token_distance(&self, from: Self::Offset, to: Self::Offset) -> usize {
+        to.saturating_sub(from)
+    }
+
+    #[inline]
+    fn backtrack_exceeded_at(&self) -> Option<Self::Offset> {
+        self.input
+            .backtrack_exceeded_at()
+            .map(|offset| self.reversed_offset(offset))
+    }
+
+    // Committing to a reversed offset settles the end of the wrapped input rather than its start, so the wrapped input
+    // can only be told once all of it has been committed to
+    #[inline]
+    unsafe fn committed_to(&self, offset: Self::Offset) {
+        if offset + 1 == self.boundaries.len() {
+            self.input.committed_to(self.boundary(0));
+        }
+    }
+
+    #[inline]
+    fn starved_at(&self) -> Option<Self::Offset> {
+        self.input
+            .starved_at()
+            .map(|offset| self.reversed_offset(offset))
+    }
+}
+
+impl<'a, I: Input<'a>> ExactSizeInput<'a> for Reversed<'a, I> {
+    #[inline(always)]
+    unsafe fn span_from(&self, range: RangeFrom<Self::Offset>) -> Self::Span {
+        self.span(range.start..self.boundaries.len() - 1)
+    }
+}
+
+impl<'a, I: ValueInput<'a>> ValueInput<'a> for Reversed<'a, I> {
+    #[inline]
+    unsafe fn next(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::Token>) {
+        if offset + 1 < self.boundaries.len() {
+            (offset + 1, self.input.next(self.boundary(offset + 1)).1)
+        } else {
+            (offset, None)
+        }
+    }
+}
+
+impl<'a, I: BorrowInput<'a>> BorrowInput<'a> for Reversed<'a, I> {
+    #[inline]
+    unsafe fn next_ref(&self, offset: Self::Offset) -> (Self::Offset, Option<&'a Self::Token>) {
+        if offset + 1 < self.boundaries.len() {
+            (offset + 1, self.input.next_ref(self.boundary(offset + 1)).1)
+        } else {
+            (offset, None)
+        }
+    }
+}
+
+impl<'a, I: SliceInput<'a>> SliceInput<'a> for Reversed<'a, I> {
+    type Slice = I::Slice;
+
+    #[inline(always)]
+    fn slice(&self, range: Range<Self::Offset>) -> Self::Slice {
+        <I as SliceInput>::slice(&self.input, self.original(range))
+    }
+
+    #[inline(always)]
+    fn try_slice(&self, range: Range<Self::Offset>) -> Result<Self::Slice, SliceError> {
+        <I as SliceInput>::try_slice(&self.input, self.original(range))
+    }
+
+    #[inline(always)]
+    fn slice_from(&self, from: RangeFrom<Self::Offset>) -> Self::Slice {
+        self.slice(from.start..self.boundaries.len() - 1)
+    }
+}
+
+/// Which of the two inputs of a [`ChainedInput`] a span belongs to. See [`Input::chain`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ChainPart {
+    /// The first input.
+    First,
+    /// The second input.
+    Second,
+}
+
+/// An input wrapper that concatenates two inputs. See [`Input::chain`].
+#[derive(Copy, Clone)]
+pub struct ChainedInput<A, B> {
+    first: A,
+    second: B,
+    // The offset at which the first input ends and the second begins
+    split: usize,
+}
+
+impl<'a, A, B> ChainedInput<A, B>
+where
+    A: Input<'a, Offset = usize>,
+    B: Input<'a, Offset = usize>,
+{
+    #[inline(always)]
+    fn second_offset(&self, offset: usize) -> usize {
+        offset - self.split + self.second.start()
+    }
+
+    #[inline(always)]
+    fn chained_offset(&self, offset: usize) -> usize {
+        offset - self.second.start() + self.split
+    }
+}
+
+impl<A, B> Sealed for ChainedInput<A, B> {}
+impl<'a, A, B> Input<'a> for ChainedInput<A, B>
+where
+    A: Input<'a, Offset = usize>,
+    A::Span: Span<Context = ()>,
+    B: Input<'a, Offset = usize, Token = A::Token, Span = A::Span>,
+{
+    type Offset = usize;
+    type Token = A::Token;
+    type Span = (ChainPart, A::Span);
+
+    #[inline(always)]
+    fn start(&self) -> Self::Offset {
+        self.first.start()
+    }
+
+    type TokenMaybe = ChainedTokenMaybe<'a, A, B, A::Token>;
+
+    #[inline(always)]
+    unsafe fn next_maybe(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::TokenMaybe>) {
+        if offset < self.split {
+            let (next, tok) = self.first.next_maybe(offset);
+            (
+                next,
+                tok.map(|tok| ChainedTokenMaybe(Either::First(tok), PhantomData)),
+            )
+        } else {
+            let (next, tok) = self.second.next_maybe(self.second_offset(offset));
+            (
+                self.chained_offset(next),
+                tok.map(|tok| ChainedTokenMaybe(Either::Second(tok), PhantomData)),
+            )
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn span(&self, range: Range<Self::Offset>) -> Self::Span {
+        if range.start < self.split {
+            (
+                ChainPart::First,
+                self.first.span(range.start..range.end.min(self.split)),
+            )
+        } else {
+            (
+                ChainPart::Second,
+                self.second
+                    .span(self.second_offset(range.start)..self.second_offset(range.end)),
+            )
+        }
+    }
+
+    #[inline(always)]
+    fn prev(offs: Self::Offset) -> Self::Offset {
+        offs.saturating_sub(1)
+    }
+
+    #[inline(always)]
+    fn is_boundary(&self, offset: Self::Offset) -> bool {
+        if offset < self.split {
+            self.first.is_boundary(offset)
+        } else {
+            self.second.is_boundary(self.second_offset(offset))
+        }
+    }
+
+    #[inline]
+    fn backtrack_exceeded_at(&self) -> Option<Self::Offset> {
+        self.first.backtrack_exceeded_at().or_else(|| {
+            self.second
+                .backtrack_exceeded_at()
+                .map(|offset| self.chained_offset(offset))
+        })
+    }
+
+    #[inline]
+    unsafe fn committed_to(&self, offset: Self::Offset) {
+        self.first.committed_to(offset.min(self.split));
+        if offset > self.split {
+            self.second.committed_to(self.second_offset(offset));
+        }
+    }
+
+    #[inline]
+    fn starved_at(&self) -> Option<Self::Offset> {
+        self.first.starved_at().or_else(|| {
+            self.second
+                .starved_at()
+                .map(|offset| self.chained_offset(offset))
+        })
+    }
+}
+
+/// Utility type required to allow [`ChainedInput`] to implement [`Input`].
+#[doc(hidden)]
+pub struct ChainedTokenMaybe<'a, A: Input<'a>, B: Input<'a>, T>(
+    Either<A::TokenMaybe, B::TokenMaybe>,
+    PhantomData<T>,
+);
+
+enum Either<A, B> {
+    First(A),
+    Second(B),
+}
+
+impl<'a, A: Input<'a, Token = T>, B: Input<'a, Token = T>, T> Borrow<T>
+    for ChainedTokenMaybe<'a, A, B, T>
+{
+    #[inline(always)]
+    fn borrow(&self) -> &T {
+        match &self.0 {
+            Either::First(tok) => tok.borrow(),
+            Either::Second(tok) => tok.borrow(),
+        }
+    }
+}
+
+impl<'a, A: Input<'a, Token = T>, B: Input<'a, Token = T>, T> From<ChainedTokenMaybe<'a, A, B, T>>
+    for MaybeRef<'a, T>
+{
+    #[inline(always)]
+    fn from(tok: ChainedTokenMaybe<'a, A, B, T>) -> MaybeRef<'a, T> {
+        match tok.0 {
+            Either::First(tok) => tok.into(),
+            Either::Second(tok) => tok.into(),
+        }
+    }
+}
+
+impl<'a, A, B> ExactSizeInput<'a> for ChainedInput<A, B>
+where
+    A: ExactSizeInput<'a, Offset = usize>,
+    A::Span: Span<Context = ()>,
+    B: ExactSizeInput<'a, Offset = usize, Token = A::Token, Span = A::Span>,
+{
+    #[inline(always)]
+    unsafe fn span_from(&self, range: RangeFrom<Self::Offset>) -> Self::Span {
+        if range.start < self.split {
+            (ChainPart::First, self.first.span(range.start..self.split))
+        } else {
+            (
+                ChainPart::Second,
+                self.second.span_from(self.second_offset(range.start)..),
+            )
+        }
+    }
+}
+
+impl<'a, A, B> ValueInput<'a> for ChainedInput<A, B>
+where
+    A: ValueInput<'a, Offset = usize>,
+    A::Span: Span<Context = ()>,
+    B: ValueInput<'a, Offset = usize, Token = A::Token, Span = A::Span>,
+{
+    #[inline(always)]
+    unsafe fn next(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::Token>) {
+        if offset < self.split {
+            self.first.next(offset)
+        } else {
+            let (next, tok) = self.second.next(self.second_offset(offset));
+            (self.chained_offset(next), tok)
+        }
+    }
+}
+
+impl<'a, A, B> BorrowInput<'a> for ChainedInput<A, B>
+where
+    A: BorrowInput<'a, Offset = usize>,
+    A::Span: Span<Context = ()>,
+    B: BorrowInput<'a, Offset = usize, Token = A::Token, Span = A::Span>,
+{
+    #[inline(always)]
+    unsafe fn next_ref(&self, offset: Self::Offset) -> (Self::Offset, Option<&'a Self::Token>) {
+        if offset < self.split {
+            self.first.next_ref(offset)
+        } else {
+            let (next, tok) = self.second.next_ref(self.second_offset(offset));
+            (self.chained_offset(next), tok)
+        }
+    }
+}
+
+impl<'a, A, B> SliceInput<'a> for ChainedInput<A, B>
+where
+    A: SliceInput<'a, Offset = usize>,
+    A::Span: Span<Context = ()>,
+    B: SliceInput<'a, Offset = usize, Token = A::Token, Span = A::Span, Slice = A::Slice>,
+{
+    type Slice = A::Slice;
+
+    #[inline(always)]
+    fn slice(&self, range: Range<Self::Offset>) -> Self::Slice {
+        if range.end <= self.split {
+            self.first.slice(range)
+        } else if range.start >= self.split {
+            self.second
+                .slice(self.second_offset(range.start)..self.second_offset(range.end))
+        } else {
+            panic!("slices of a chained input may not cross the boundary between its inputs")
+        }
+    }
+
+    // Slices from an offset within the first input extend only to the end of the first input
+    #[inline(always)]
+    fn slice_from(&self, from: RangeFrom<Self::Offset>) -> Self::Slice {
+        if from.start < self.split {
+            self.first.slice(from.start..self.split)
+        } else {
+            self.second.slice_from(self.second_offset(from.start)..)
+        }
+    }
+
+    #[inline(always)]
+    fn try_slice(&self, range: Range<Self::Offset>) -> Result<Self::Slice, SliceError> {
+        if range.end <= self.split {
+            self.first.try_slice(range)
+        } else if range.start >= self.split {
+            self.second
+                .try_slice(self.second_offset(range.start)..self.second_offset(range.end))
+        } else {
+            Err(SliceError(()))
+        }
+    }
+}
+
+impl<'a, C, A, B> StrInput<'a, C> for ChainedInput<A, B>
+where
+    A: StrInput<'a, C>,
+    A::Span: Span<Context = ()>,
+    B: StrInput<'a, C, Span = A::Span>,
+    C: Char,
+{
+}
+
+/// An input wrapper that appends a synthetic end of input token to the wrapped input. See [`Input::with_eof_token`].
+#[derive(Copy, Clone)]
+pub struct WithEofToken<I, T> {
+    input: I,
+    eof: T,
+}
+
+/// The offset type of [`WithEofToken`], which can additionally point past the synthetic end of input token.
+#[doc(hidden)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EofOffset<O> {
+    offset: O,
+    past_eof: bool,
+}
+
+impl<O: Into<usize>> From<EofOffset<O>> for usize {
+    #[inline(always)]
+    fn from(offset: EofOffset<O>) -> Self {
+        offset.offset.into() + offset.past_eof as usize
+    }
+}
+
+/// Utility type required to allow [`WithEofToken`] to implement [`Input`].
+#[doc(hidden)]
+pub struct EofTokenMaybe<'a, I: Input<'a>, T>(Either<I::TokenMaybe, T>);
+
+impl<'a, I: Input<'a, Token = T>, T> Borrow<T> for EofTokenMaybe<'a, I, T> {
+    #[inline(always)]
+    fn borrow(&self) -> &T {
+        match &self.0 {
+            Either::First(tok) => tok.borrow(),
+            Either::Second(eof) => eof,
+        }
+    }
+}
+
+impl<'a, I: Input<'a, Token = T>, T> From<EofTokenMaybe<'a, I, T>> for MaybeRef<'a, T> {
+    #[inline(always)]
+    fn from(tok: EofTokenMaybe<'a, I, T>) -> MaybeRef<'a, T> {
+        match tok.0 {
+            Either::First(tok) => tok.into(),
+            Either::Second(eof) => MaybeRef::Val(eof),
+        }
+    }
+}
+
+impl<I, T> Sealed for WithEofToken<I, T> {}
+impl<'a, I> Input<'a> for WithEofToken<I, I::Token>
+where
+    I: Input<'a>,
+    I::Token: Clone,
+{
+    type Offset = EofOffset<I::Offset>;
+    type Token = I::Token;
+    type Span = I::Span;
+
+    #[inline(always)]
+    fn start(&self) -> Self::Offset {
+        EofOffset {
+            offset: self.input.start(),
+            past_eof: false,
+        }
+    }
+
+    type TokenMaybe = EofTokenMaybe<'a, I, I::Token>;
+
+    #[inline]
+    unsafe fn next_maybe(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::TokenMaybe>) {
+        if offset.past_eof {
+            return (offset, None);
+        }
+        match self.input.next_maybe(offset.offset) {
+            (next, Some(tok)) => (
+                EofOffset {
+                    offset: next,
+                    past_eof: false,
+                },
+                Some(EofTokenMaybe(Either::First(tok))),
+            ),
+            (end, None) => (
+                EofOffset {
+                    offset: end,
+                    past_eof: true,
+                },
+                Some(EofTokenMaybe(Either::Second(self.eof.clone()))),
+            ),
+        }
+    }
+
+    // The synthetic token sits at the end of the wrapped input, so its span is zero-width
+    #[inline(always)]
+    unsafe fn span(&self, range: Range<Self::Offset>) -> Self::Span {
+        self.input.span(range.start.offset..range.end.offset)
+    }
+
+    #[inline(always)]
+    fn prev(offs: Self::Offset) -> Self::Offset {
+        EofOffset {
+            offset: if offs.past_eof {
+                offs.offset
+            } else {
+                I::prev(offs.offset)
+            },
+            past_eof: false,
+        }
+    }
+
+    #[inline(always)]
+    fn is_boundary(&self, offset: Self::Offset) -> bool {
+        offset.past_eof || self.input.is_boundary(offset.offset)
+    }
+
+    #[inline(always)]
+    fn backtrack_exceeded_at(&self) -> Option<Self::Offset> {
+        self.input.backtrack_exceeded_at().map(|offset| EofOffset {
+            offset,
+            past_eof: false,
+        })
+    }
+
+    #[inline(always)]
+    fn starved_at(&self) -> Option<Self::Offset> {
+        self.input.starved_at().map(|offset| EofOffset {
+            offset,
+            past_eof: false,
+        })
+    }
+
+    #[inline(always)]
+    unsafe fn committed_to(&self, offset: Self::Offset) {
+        self.input.committed_to(offset.offset)
+    }
+}
+
+impl<'a, I> ExactSizeInput<'a> for WithEofToken<I, I::Token>
+where
+    I: ExactSizeInput<'a>,
+    I::Token: Clone,
+{
+    #[inline(always)]
+    unsafe fn span_from(&self, range: RangeFrom<Self::Offset>) -> Self::Span {
+        self.input.span_from(range.start.offset..)
+    }
+}
+
+impl<'a, I> ValueInput<'a> for WithEofToken<I, I::Token>
+where
+    I: ValueInput<'a>,
+    I::Token: Clone,
+{
+    #[inline]
+    unsafe fn next(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::Token>) {
+        if offset.past_eof {
+            return (offset, None);
+        }
+        match self.input.next(offset.offset) {
+            (next, Some(tok)) => (
+                EofOffset {
+                    offset: next,
+                    past_eof: false,
+                },
+                Some(tok),
+            ),
+            (end, None) => (
+                EofOffset {
+                    offset: end,
+                    past_eof: true,
+                },
+                Some(self.eof.clone()),
+            ),
+        }
+    }
+}
+
+impl<'a, I> SliceInput<'a> for WithEofToken<I, I::Token>
+where
+    I: SliceInput<'a>,
+    I::Token: Clone,
+{
+    type Slice = I::Slice;
+
+    #[inline(always)]
+    fn slice(&self, range: Range<Self::Offset>) -> Self::Slice {
+        self.input.slice(range.start.offset..range.end.offset)
+    }
+
+    #[inline(always)]
+    fn try_slice(&self, range: Range<Self::Offset>) -> Result<Self::Slice, SliceError> {
+        self.input.try_slice(range.start.offset..range.end.offset)
+    }
+
+    #[inline(always)]
+    fn slice_from(&self, from: RangeFrom<Self::Offset>) -> Self::Slice {
+        self.input.slice_from(from.start.offset..)
+    }
+}
+
+/// An input wrapper that appends a fixed sequence of tokens to the wrapped input. See [`Input::append_tokens`].
+pub struct AppendTokens<'a, I: Input<'a>> {
+    input: I,
+    extra: &'a [I::Token],
+}
+
+impl<'a, I: Input<'a> + Copy> Copy for AppendTokens<'a, I> {}
+impl<'a, I: Input<'a> + Clone> Clone for AppendTokens<'a, I> {
+    fn clone(&self) -> Self {
+        Self {
+            input: self.input.clone(),
+            extra: self.extra,
+        }
+    }
+}
+
+/// The offset type of [`AppendTokens`], which can additionally point into the appended tokens.
+#[doc(hidden)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AppendOffset<O> {
+    offset: O,
+    // The number of appended tokens that precede this offset. If non-zero, `offset` is the end of the wrapped input.
+    appended: usize,
+}
+
+impl<O: Into<usize>> From<AppendOffset<O>> for usize {
+    #[inline(always)]
+    fn from(offset: AppendOffset<O>) -> Self {
+        offset.offset.into() + offset.appended
+    }
+}
+
+/// Utility type required to allow [`AppendTokens`] to implement [`Input`].
+#[doc(hidden)]
+pub struct AppendedTokenMaybe<'a, I: Input<'a>, T>(Either<I::TokenMaybe, &'a T>);
+
+impl<'a, I: Input<'a, Token = T>, T> Borrow<T> for AppendedTokenMaybe<'a, I, T> {
+    #[inline(always)]
+    fn borrow(&self) -> &T {
+        match &self.0 {
+            Either::First(tok) => tok.borrow(),
+            Either::Second(tok) => tok,
+        }
+    }
+}
+
+impl<'a, I: Input<'a, Token = T>, T> From<AppendedTokenMaybe<'a, I, T>> for MaybeRef<'a, T> {
+    #[inline(always)]
+    fn from(tok: AppendedTokenMaybe<'a, I, T>) -> MaybeRef<'a, T> {
+        match tok.0 {
+            Either::First(tok) => tok.into(),
+            Either::Second(tok) => MaybeRef::Ref(tok),
+        }
+    }
+}
+
+impl<'a, I: Input<'a>> AppendTokens<'a, I> {
+    // Find the appended token (if any) at the given offset and the offset that follows it. `end` is the offset at
+    // which the wrapped input was found to end.
+    #[inline(always)]
+    fn next_appended(
+        &self,
+        end: I::Offset,
+        appended: usize,
+    ) -> (AppendOffset<I::Offset>, Option<&'a I::Token>) {
+        match self.extra.get(appended) {
+            Some(tok) => (
+                AppendOffset {
+                    offset: end,
+                    appended: appended + 1,
+                },
+                Some(tok),
+            ),
+            None => (
+                AppendOffset {
+                    offset: end,
+                    appended,
+                },
+                None,
+            ),
+        }
+    }
+}
+
+impl<'a, I: Input<'a>> Sealed for AppendTokens<'a, I> {}
+impl<'a, I: Input<'a>> Input<'a> for AppendTokens<'a, I>
+where
+    I::Token: 'a,
+{
+    type Offset = AppendOffset<I::Offset>;
+    type Token = I::Token;
+    type Span = I::Span;
+
+    #[inline(always)]
+    fn start(&self) -> Self::Offset {
+        AppendOffset {
+            offset: self.input.start(),
+            appended: 0,
+        }
+    }
+
+    type TokenMaybe = AppendedTokenMaybe<'a, I, I::Token>;
+
+    #[inline]
+    unsafe fn next_maybe(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::TokenMaybe>) {
+        if offset.appended == 0 {
+            if let (next, Some(tok)) = self.input.next_maybe(offset.offset) {
+                return (
+                    AppendOffset {
+                        offset: next,
+                        appended: 0,
+                    },
+                    Some(AppendedTokenMaybe(Either::First(tok))),
+                );
+            }
+        }
+        let (next, tok) = self.next_appended(offset.offset, offset.appended);
+        (next, tok.map(|tok| AppendedTokenMaybe(Either::Second(tok))))
+    }
+
+    // The appended tokens sit at the end of the wrapped input, so their spans are zero-width
+    #[inline(always)]
+    unsafe fn span(&self, range: Range<Self::Offset>) -> Self::Span {
+        self.input.span(range.start.offset..range.end.offset)
+    }
+
+    #[inline(always)]
+    fn prev(offs: Self::Offset) -> Self::Offset {
+        if offs.appended > 0 {
+            AppendOffset {
+                offset: offs.offset,
+                appended: offs.appended - 1,
+            }
+        } else {
+            AppendOffset {
+                offset: I::prev(offs.offset),
+                appended: 0,
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn is_boundary(&self, offset: Self::Offset) -> bool {
+        offset.appended > 0 || self.input.is_boundary(offset.offset)
+    }
+
+    #[inline(always)]
+    fn backtrack_exceeded_at(&self) -> Option<Self::Offset> {
+        self.input
+            .backtrack_exceeded_at()
+            .map(|offset| AppendOffset {
+                offset,
+                appended: 0,
+            })
+    }
+
+    #[inline(always)]
+    fn starved_at(&self) -> Option<Self::Offset> {
+        self.input.starved_at().map(|offset| AppendOffset {
+            offset,
+            appended: 0,
+        })
+    }
+
+    #[inline(always)]
+    unsafe fn committed_to(&self, offset: Self::Offset) {
+        self.input.committed_to(offset.offset)
+    }
+}
+
+impl<'a, I: ExactSizeInput<'a>> ExactSizeInput<'a> for AppendTokens<'a, I>
+where
+    I::Token: 'a,
+{
+    #[inline(always)]
+    unsafe fn span_from(&self, range: RangeFrom<Self::Offset>) -> Self::Span {
+        self.input.span_from(range.start.offset..)
+    }
+}
+
+impl<'a, I: ValueInput<'a>> ValueInput<'a> for AppendTokens<'a, I>
+where
+    I::Token: Clone + 'a,
+{
+    #[inline]
+    unsafe fn next(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::Token>) {
+        if offset.appended == 0 {
+            if let (next, Some(tok)) = self.input.next(offset.offset) {
+                return (
+                    AppendOffset {
+                        offset: next,
+                        appended: 0,
+                    },
+                    Some(tok),
+                );
+            }
+        }
+        let (next, tok) = self.next_appended(offset.offset, offset.appended);
+        (next, tok.cloned())
+    }
+}
+
+impl<'a, I: BorrowInput<'a>> BorrowInput<'a> for AppendTokens<'a, I>
+where
+    I::Token: 'a,
+{
+    #[inline]
+    unsafe fn next_ref(&self, offset: Self::Offset) -> (Self::Offset, Option<&'a Self::Token>) {
+        if offset.appended == 0 {
+            if let (next, Some(tok)) = self.input.next_ref(offset.offset) {
+                return (
+                    AppendOffset {
+                        offset: next,
+                        appended: 0,
+                    },
+                    Some(tok),
+                );
+            }
+        }
+        self.next_appended(offset.offset, offset.appended)
+    }
+}
+
+impl<'a, I> SliceInput<'a> for AppendTokens<'a, I>
+where
+    I: SliceInput<'a, Slice = &'a [<I as Input<'a>>::Token]>,
+    I::Token: 'a,
+{
+    type Slice = I::Slice;
+
+    #[inline]
+    fn slice(&self, range: Range<Self::Offset>) -> Self::Slice {
+        if range.end.appended == 0 {
+            self.input.slice(range.start.offset..range.end.offset)
+        } else if range.start.offset == range.end.offset {
+            // Both offsets lie at the end of the wrapped input, so the range covers only appended tokens
+            &self.extra[range.start.appended..range.end.appended]
+        } else {
+            panic!(
+                "slices of an input with appended tokens may not cross the boundary between them"
+            )
+        }
+    }
+
+    #[inline]
+    fn try_slice(&self, range: Range<Self::Offset>) -> Result<Self::Slice, SliceError> {
+        if range.end.appended == 0 {
+            self.input.try_slice(range.start.offset..range.end.offset)
+        } else if range.start.offset == range.end.offset {
+            Ok(&self.extra[range.start.appended..range.end.appended])
+        } else {
+            Err(SliceError(()))
+        }
+    }
+
+    // Slices from an offset within the wrapped input do not include the appended tokens
+    #[inline]
+    fn slice_from(&self, from: RangeFrom<Self::Offset>) -> Self::Slice {
+        if from.start.appended == 0 {
+            self.input.slice_from(from.start.offset..)
+        } else {
+            &self.extra[from.start.appended..]
+        }
+    }
+}
+
+/// An input wrapper that inserts a synthetic token after every line break in the wrapped string input. See
+/// [`Input::with_line_breaks`].
+#[derive(Copy, Clone)]
+pub struct WithLineBreaks<I> {
+    input: I,
+    line_break: char,
+}
+
+/// The offset type of [`WithLineBreaks`], which can additionally point at the synthetic token following a `\n`.
+#[doc(hidden)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LineBreakOffset {
+    offset: usize,
+    // Whether the synthetic token at this offset has yet to be produced
+    pending: bool,
+}
+
+impl PartialOrd for LineBreakOffset {
+    #[inline(always)]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for LineBreakOffset {
+    #[inline(always)]
+    fn cmp(&self, other: &Self) -> Ordering {
+        // The synthetic token comes before the character at the same offset
+        (self.offset, !self.pending).cmp(&(other.offset, !other.pending))
+    }
+}
+
+impl From<LineBreakOffset> for usize {
+    #[inline(always)]
+    fn from(offset: LineBreakOffset) -> Self {
+        offset.offset
+    }
+}
+
+impl<'a, I: StrInput<'a, char>> WithLineBreaks<I> {
+    #[inline(always)]
+    fn settled(offset: usize) -> LineBreakOffset {
+        LineBreakOffset {
+            offset,
+            pending: false,
+        }
+    }
+}
+
+impl<I> Sealed for WithLineBreaks<I> {}
+impl<'a, I: StrInput<'a, char>> Input<'a> for WithLineBreaks<I> {
+    type Offset = LineBreakOffset;
+    type Token = char;
+    type Span = I::Span;
+
+    #[inline(always)]
+    fn start(&self) -> Self::Offset {
+        Self::settled(self.input.start())
+    }
+
+    type TokenMaybe = char;
+
+    #[inline(always)]
+    unsafe fn next_maybe(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::TokenMaybe>) {
+        self.next(offset)
+    }
+
+    // The synthetic tokens lie between characters, so their spans are zero-width
+    #[inline(always)]
+    unsafe fn span(&self, range: Range<Self::Offset>) -> Self::Span {
+        self.input.span(range.start.offset..range.end.offset)
+    }
+
+    #[inline(always)]
+    fn prev(offs: Self::Offset) -> Self::Offset {
+        if offs.pending {
+            Self::settled(I::prev(offs.offset))
+        } else {
+            LineBreakOffset {
+                offset: offs.offset,
+                pending: true,
+            }
+        }
+    }
+
+    #[inline]
+    fn is_boundary(&self, offset: Self::Offset) -> bool {
+        if offset.pending {
+            // Only offsets immediately following a `\n` have a synthetic token
+            offset.offset > self.input.start()
+                && self.input.is_boundary(I::prev(offset.offset))
+                // SAFETY: the offset lies on a character boundary
+                && unsafe { self.input.next(I::prev(offset.offset)) } == (offset.offset, Some('\n'))
+        } else {
+            self.input.is_boundary(offset.offset)
+        }
+    }
+
+    #[inline(always)]
+    fn backtrack_exceeded_at(&self) -> Option<Self::Offset> {
+        self.input.backtrack_exceeded_at().map(Self::settled)
+    }
+
+    #[inline(always)]
+    fn starved_at(&self) -> Option<Self::Offset> {
+        self.input.starved_at().map(Self::settled)
+    }
+
+    #[inline(always)]
+    unsafe fn committed_to(&self, offset: Self::Offset) {
+        self.input.committed_to(offset.offset)
+    }
+}
+
+impl<'a, I: StrInput<'a, char>> ExactSizeInput<'a> for WithLineBreaks<I> {
+    #[inline(always)]
+    unsafe fn span_from(&self, range: RangeFrom<Self::Offset>) -> Self::Span {
+        self.input.span_from(range.start.offset..)
+    }
+}
+
+impl<'a, I: StrInput<'a, char>> ValueInput<'a> for WithLineBreaks<I> {
+    #[inline]
+    unsafe fn next(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::Token>) {
+        if offset.pending {
+            return (Self::settled(offset.offset), Some(self.line_break));
+        }
+        match self.input.next(offset.offset) {
+            (next, Some(c)) => (
+                LineBreakOffset {
+                    offset: next,
+                    pending: c == '\n',
+                },
+                Some(c),
+            ),
+            (end, None) => (Self::settled(end), None),
+        }
+    }
+}
+
+impl<'a, I: StrInput<'a, char>> SliceInput<'a> for WithLineBreaks<I> {
+    type Slice = I::Slice;
+
+    #[inline(always)]
+    fn slice(&self, range: Range<Self::Offset>) -> Self::Slice {
+        <I as SliceInput>::slice(&self.input, range.start.offset..range.end.offset)
+    }
+
+    #[inline(always)]
+    fn try_slice(&self, range: Range<Self::Offset>) -> Result<Self::Slice, SliceError> {
+        <I as SliceInput>::try_slice(&self.input, range.start.offset..range.end.offset)
+    }
+
+    #[inline(always)]
+    fn slice_from(&self, from: RangeFrom<Self::Offset>) -> Self::Slice {
+        <I as SliceInput>::slice_from(&self.input, from.start.offset..)
+    }
+}
+
+/// An input wrapper that presents every line ending of the wrapped string input as a single `\n`. See
+/// [`Input::normalize_newlines`].
+#[derive(Copy, Clone)]
+pub struct NormalizeNewlines<I> {
+    input: I,
+}
+
+impl<I> Sealed for NormalizeNewlines<I> {}
+impl<'a, I: StrInput<'a, char>> Input<'a> for NormalizeNewlines<I> {
+    type Offset = usize;
+    type Token = char;
+    type Span = I::Span;
+
+    #[inline(always)]
+    fn start(&self) -> Self::Offset {
+        self.input.start()
+    }
+
+    type TokenMaybe = char;
+
+    #[inline(always)]
+    unsafe fn next_maybe(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::TokenMaybe>) {
+        self.next(offset)
+    }
+
+    #[inline(always)]
+    unsafe fn span(&self, range: Range<Self::Offset>) -> Self::Span {
+        self.input.span(range)
+    }
+
+    #[inline(always)]
+    fn prev(offs: Self::Offset) -> Self::Offset {
+        I::prev(offs)
+    }
+
+    #[inline]
+    fn is_boundary(&self, offset: Self::Offset) -> bool {
+        // The offset between the `\r` and `\n` of a `\r\n` lies within a single token
+        self.input.is_boundary(offset)
+            && !(offset > self.input.start()
+                && self.input.is_boundary(I::prev(offset))
+                // SAFETY: the offset lies on a character boundary
+                && unsafe { self.input.next(I::prev(offset)) } == (offset, Some('\r'))
+                // SAFETY: `offset` lies on a character boundary
+                && unsafe { self.input.next(offset).1 } == Some('\n'))
+    }
+
+    #[inline(always)]
+    fn backtrack_exceeded_at(&self) -> Option<Self::Offset> {
+        self.input.backtrack_exceeded_at()
+    }
+
+    #[inline(always)]
+    fn starved_at(&self) -> Option<Self::Offset> {
+        self.input.starved_at()
+    }
+
+    #[inline(always)]
+    unsafe fn committed_to(&self, offset: Self::Offset) {
+        self.input.committed_to(offset)
+    }
+}
+
+impl<'a, I: StrInput<'a, char>> ExactSizeInput<'a> for NormalizeNewlines<I> {
+    #[inline(always)]
+    unsafe fn span_from(&self, range: RangeFrom<Self::Offset>) -> Self::Span {
+        self.input.span_from(range)
+    }
+}
+
+impl<'a, I: StrInput<'a, char>> ValueInput<'a> for NormalizeNewlines<I> {
+    #[inline]
+    unsafe fn next(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::Token>) {
+        match self.input.next(offset) {
+            (next, Some('\r')) => match self.input.next(next) {
+                (after, Some('\n')) => (after, Some('\n')),
+                _ => (next, Some('\n')),
+            },
+            other => other,
+        }
+    }
+}
+
+impl<'a, I: StrInput<'a, char>> StrInput<'a, char> for NormalizeNewlines<I> {}
+
+impl<'a, I: StrInput<'a, char>> SliceInput<'a> for NormalizeNewlines<I> {
+    type Slice = &'a str;
+
+    #[inline(always)]
+    fn slice(&self, range: Range<Self::Offset>) -> Self::Slice {
+        <I as SliceInput>::slice(&self.input, range)
+    }
+
+    #[inline(always)]
+    fn try_slice(&self, range: Range<Self::Offset>) -> Result<Self::Slice, SliceError> {
+        <I as SliceInput>::try_slice(&self.input, range)
+    }
+
+    #[inline(always)]
+    fn slice_from(&self, from: RangeFrom<Self::Offset>) -> Self::Slice {
+        <I as SliceInput>::slice_from(&self.input, from)
+    }
+}
+
+/// An iterator that rewrites the tokens of an input with a stateful function, pairing each new token with the span of
+/// the token it replaced. See [`Input::scan`].
+pub struct Scan<'a, I: Input<'a>, St, F, R: IntoIterator> {
+    input: I,
+    offset: I::Offset,
+    state: St,
+    f: F,
+    // The remaining tokens produced by the last call to `f`, along with the span of the token they replaced
+    pending: Option<(R::IntoIter, I::Span)>,
+    phantom: PhantomData<&'a ()>,
+}
+
+impl<'a, I, St, F, R> Iterator for Scan<'a, I, St, F, R>
+where
+    I: ValueInput<'a>,
+    I::Span: Clone,
+    F: FnMut(&mut St, I::Token) -> R,
+    R: IntoIterator,
+{
+    type Item = (R::Item, I::Span);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((toks, span)) = &mut self.pending {
+                if let Some(tok) = toks.next() {
+                    return Some((tok, span.clone()));
+                }
+            }
+            // SAFETY: `offset` is either the start of the input or was generated by a previous call to `Input::next`
+            let (next, tok) = unsafe { self.input.next(self.offset) };
+            let tok = tok?;
+            // SAFETY: As above
+            let span = unsafe { self.input.span(self.offset..next) };
+            self.offset = next;
+            self.pending = Some(((self.f)(&mut self.state, tok).into_iter(), span));
+        }
+    }
+}
+
+/// A string input made up of a sequence of borrowed chunks, allowing cheap edits that share the unchanged regions of
+/// the original string.
+///
+/// This is useful for editors and other incremental tooling: rather than allocating a new string each time the user
+/// types, an edit can be applied to an existing rope with [`StrRope::with_edit`]. Tokens are [`char`]s and offsets are
+/// byte offsets into the edited text, just as with [`&str`].
+///
+/// Because the text is not contiguous, this input does not implement [`SliceInput`].
+///
+/// # Examples
+///
+/// ```
+/// # use chumsky::{prelude::*, input::StrRope};
+/// let rope = StrRope::new("hello world").with_edit(6..11, "rope");
+///
+/// let words = any::<_, extra::Err<Simple<char>>>()
+///     .filter(|c: &char| c.is_alphabetic())
+///     .repeated()
+///     .at_least(1)
+///     .collect::<String>()
+///     .separated_by(just(' '))
+///     .collect::<Vec<_>>();
+///
+/// assert_eq!(words.parse(rope).into_result(), Ok(vec!["hello".to_string(), "rope".to_string()]));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct StrRope<'a> {
+    chunks: Vec<&'a str>,
+    // The byte offset at which each chunk starts
+    starts: Vec<usize>,
+    len: usize,
+}
+
+impl<'a> StrRope<'a> {
+    /// Create a new rope containing a single string.
+    pub fn new(s: &'a str) -> Self {
+        let mut rope = Self::default();
+        rope.push(s);
+        rope
+    }
+
+    /// Create a new rope in which the given byte range has been replaced with `replacement`.
+    ///
+    /// Unchanged regions of the rope are shared with the original text rather than being copied.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds, or if either end of the range does not lie on a [`char`] boundary.
+    pub fn with_edit(&self, range: Range<usize>, replacement: &'a str) -> Self {
+        assert!(
+            range.start <= range.end && range.end <= self.len,
+            "edit range {:?} is out of bounds for a rope of length {}",
+            range,
+            self.len,
+        );
+
+        let mut rope = Self::default();
+        for (&start, &chunk) in self.starts.iter().zip(&self.chunks) {
+            if start < range.start {
+                rope.push(&chunk[..(range.start - start).min(chunk.len())]);
+            }
+        }
+        rope.push(replacement);
+        for (&start, &chunk) in self.starts.iter().zip(&self.chunks) {
+            if start + chunk.len() > range.end {
+                rope.push(&chunk[range.end.saturating_sub(start)..]);
+            }
+        }
+        rope
+    }
+
+    /// The length of the text in this rope, in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if this rope contains no text.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn push(&mut self, s: &'a str) {
+        if !s.is_empty() {
+            self.chunks.push(s);
+            self.starts.push(self.len);
+            self.len += s.len();
+        }
+    }
+}
+
+impl<'a> Sealed for StrRope<'a> {}
+impl<'a> Input<'a> for StrRope<'a> {
+    type Offset = usize;
+    type Token = char;
+    type Span = SimpleSpan<usize>;
+
+    #[inline]
+    fn start(&self) -> Self::Offset {
+        0
+    }
+
+    type TokenMaybe = char;
+
+    #[inline(always)]
+    unsafe fn next_maybe(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::TokenMaybe>) {
+        self.next(offset)
+    }
+
+    #[inline(always)]
+    unsafe fn span(&self, range: Range<Self::Offset>) -> Self::Span {
+        range.into()
+    }
+
+    #[inline(always)]
+    fn prev(offs: Self::Offset) -> Self::Offset {
+        offs.saturating_sub(1)
+    }
+
+    #[inline]
+    fn is_boundary(&self, offset: Self::Offset) -> bool {
+        if offset < self.len {
+            let idx = self.starts.partition_point(|start| *start <= offset) - 1;
+            self.chunks[idx].is_char_boundary(offset - self.starts[idx])
+        } else {
+            offset == self.len
+        }
+    }
+}
+
+impl<'a> ExactSizeInput<'a> for StrRope<'a> {
+    #[inline(always)]
+    unsafe fn span_from(&self, range: RangeFrom<Self::Offset>) -> Self::Span {
+        (range.start..self.len).into()
+    }
+}
+
+impl<'a> ValueInput<'a> for StrRope<'a> {
+    #[inline]
+    unsafe fn next(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::Token>) {
+        if offset < self.len {
+            let idx = self.starts.partition_point(|start| *start <= offset) - 1;
+            let c = self.chunks[idx][offset - self.starts[idx]..]
+                .chars()
+                .next()
+                .expect("offset is not at a char boundary");
+            (offset + c.len_utf8(), Some(c))
+        } else {
+            (offset, None)
+        }
+    }
+}
+
+/// Represents a location in an input that can be rewound to.
+///
+/// Markers can be created with [`InputRef::save`] and rewound to with [`InputRef::rewind`].
+pub struct Marker<'a, 'parse, I: Input<'a>> {
+    pub(crate) offset: I::Offset,
+    pub(crate) err_count: usize,
+    trivia_count: usize,
+    note_count: usize,
+    committed: bool,
+    phantom: PhantomData<fn(&'parse ()) -> &'parse ()>, // Invariance
+}
+
+impl<'a, 'parse, I: Input<'a>> Marker<'a, 'parse, I> {
+    /// Get the [`Offset`] that this marker corresponds to.
+    pub fn offset(self) -> Offset<'a, 'parse, I> {
+        Offset {
+            offset: self.offset,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a, 'parse, I: Input<'a>> Copy for Marker<'a, 'parse, I> {}
+impl<'a, 'parse, I: Input<'a>> Clone for Marker<'a, 'parse, I> {
+    #[inline(always)]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+/// Calls `exit` on the wrapped value when dropped, even during unwinding. See [`InputRef::with_state_scope`].
+struct ScopeGuard<'r, T, F: FnOnce(&mut T)> {
+    inner: &'r mut T,
+    exit: Option<F>,
+}
+
+impl<'r, T, F: FnOnce(&mut T)> Drop for ScopeGuard<'r, T, F> {
+    fn drop(&mut self) {
+        if let Some(exit) = self.exit.take() {
+            exit(self.inner);
+        }
+    }
+}
+
+/// A snapshot of the entire state of an input, including the parser state and any pending alternative error.
+///
+/// Checkpoints can be created with [`InputRef::checkpoint`] and restored with [`InputRef::restore`].
+pub struct Checkpoint<'a, 'parse, I: Input<'a>, E: ParserExtra<'a, I>> {
+    marker: Marker<'a, 'parse, I>,
+    alt: Option<Located<I::Offset, E::Error>>,
+    state: E::State,
+}
+
+impl<'a, 'parse, I: Input<'a>, E: ParserExtra<'a, I>> Checkpoint<'a, 'parse, I, E> {
+    /// Get the [`Offset`] that this checkpoint corresponds to.
+    pub fn offset(&self) -> Offset<'a, 'parse, I> {
+        self.marker.offset()
+    }
+}
+
+impl<'a, 'parse, I: Input<'a>, E: ParserExtra<'a, I>> Clone for Checkpoint<'a, 'parse, I, E>
+where
+    E::State: Clone,
+    E::Error: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            marker: self.marker,
+            alt: self.alt.clone(),
+            state: self.state.clone(),
+        }
+    }
+}
+
+/// The non-fatal errors emitted within [`InputRef::with_isolated_errors`], each [`Located`] at the position at which
+/// it was produced.
+///
+/// Errors are moved into this collection with [`IsolatedErrors::collect`], after which they can be inspected, modified,
+/// removed, or added to (with [`Vec`]'s methods) before being merged back into the errors of the input.
+pub struct IsolatedErrors<'a, I: Input<'a>, E: ParserExtra<'a, I>> {
+    errs: Vec<Located<I::Offset, E::Error>>,
+    err_count: usize,
+}
+
+impl<'a, I: Input<'a>, E: ParserExtra<'a, I>> IsolatedErrors<'a, I, E> {
+    /// Move the errors that the input has emitted since isolation began (and that have not already been collected)
+    /// into this collection.
+    pub fn collect(&mut self, inp: &mut InputRef<'a, '_, I, E>) {
+        self.errs
+            .extend(inp.errors.take_secondary_since(self.err_count));
+    }
+}
+
+impl<'a, I: Input<'a>, E: ParserExtra<'a, I>> core::ops::Deref for IsolatedErrors<'a, I, E> {
+    type Target = Vec<Located<I::Offset, E::Error>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.errs
+    }
+}
+
+impl<'a, I: Input<'a>, E: ParserExtra<'a, I>> core::ops::DerefMut for IsolatedErrors<'a, I, E> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.errs
+    }
+}
+
+/// Information about the input that a parser's output was produced from, passed to the mapping function of
+/// [`Parser::map_with`].
+pub struct MapExtra<'a, 'b, 'parse, I: Input<'a>, E: ParserExtra<'a, I>> {
+    before: Offset<'a, 'parse, I>,
+    inp: &'b mut InputRef<'a, 'parse, I, E>,
+}
+
+impl<'a, 'b, 'parse, I: Input<'a>, E: ParserExtra<'a, I>> MapExtra<'a, 'b, 'parse, I, E> {
+    #[inline(always)]
+    pub(crate) fn new(
+        before: Offset<'a, 'parse, I>,
+        inp: &'b mut InputRef<'a, 'parse, I, E>,
+    ) -> Self {
+        Self { before, inp }
+    }
+
+    /// Get the span of the input that the output was produced from.
+    #[inline(always)]
+    pub fn span(&self) -> I::Span {
+        self.inp.span_since(self.before)
+    }
+
+    /// Get a mutable reference to the state associated with the current parse.
+    #[inline(always)]
+    pub fn state(&mut self) -> &mut E::State {
+        self.inp.state()
+    }
+
+    /// Get a reference to the context fed to the current parser.
+    #[inline(always)]
+    pub fn ctx(&self) -> &E::Context {
+        self.inp.ctx()
+    }
+
+    /// Get the spans of all trivia recorded while producing the output, in input order. See
+    /// [`RecordTrivia`](crate::extra::RecordTrivia) for more information.
+    ///
+    /// If trivia is not being recorded, this is always empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::{prelude::*, extra::RecordTrivia};
+    /// let word = text::ident::<_, _, RecordTrivia<extra::Default>>()
+    ///     .padded()
+    ///     .map_with(|word, e| (word, e.trivia()));
+    ///
+    /// assert_eq!(
+    ///     word.repeated().collect::<Vec<_>>().parse(" foo  bar").into_result(),
+    ///     Ok(vec![
+    ///         ("foo", vec![SimpleSpan::new(0, 1), SimpleSpan::new(4, 6)]),
+    ///         ("bar", vec![]),
+    ///     ]),
+    /// );
+    /// ```
+    #[inline]
+    pub fn trivia(&self) -> Vec<I::Span> {
+        self.inp.trivia_since(self.before)
+    }
+
+    /// Get the first character of the input that the output was produced from, paired with its byte offset. Returns
+    /// `None` if the output was produced without consuming any input.
+    ///
+    /// This is the counterpart of [`InputRef::char_index`] for mapping functions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let chars = any::<_, extra::Default>()
+    ///     .map_with(|_, e| e.char_index())
+    ///     .repeated()
+    ///     .collect::<Vec<_>>();
+    ///
+    /// assert_eq!(
+    ///     chars.parse("aé🙂").into_result(),
+    ///     Ok(vec![Some((0, 'a')), Some((1, 'é')), Some((3, '🙂'))]),
+    /// );
+    /// ```
+    #[inline(always)]
+    pub fn char_index<C: Char>(&self) -> Option<(usize, C)>
+    where
+        I: StrInput<'a, C>,
+    {
+        if self.before.offset >= self.inp.offset {
+            return None;
+        }
+        // SAFETY: `Offset` is invariant over 'parse, so we know that this offset came from the same input
+        let tok = unsafe { self.inp.input.next(self.before.offset).1 }?;
+        Some((self.before.offset, tok))
+    }
+}
+
+/// Represents a location in an input.
+///
+/// If you to rewind to an old input location, see [`Marker`].
+///
+/// Offsets are ordered by their position in the input, so comparing the offset before and after invoking a parser
+/// reveals whether it made progress. This is important for custom repetition combinators, which must stop when their
+/// inner parser consumes nothing to avoid looping forever.
+///
+/// # Examples
+///
+/// ```
+/// # use chumsky::prelude::*;
+/// let words = custom::<_, &str, _, extra::Default>(|inp| {
+///     let mut count = 0;
+///     loop {
+///         let before = inp.offset();
+///         // This parser can succeed without consuming anything
+///         inp.parse(text::ident().or_not().padded())?;
+///         if inp.offset() <= before {
+///             break Ok(count);
+///         }
+///         count += 1;
+///     }
+/// });
+///
+/// assert_eq!(words.parse("foo bar baz").into_result(), Ok(3));
+/// ```
+pub struct Offset<'a, 'parse, I: Input<'a>> {
+    pub(crate) offset: I::Offset,
+    phantom: PhantomData<fn(&'parse ()) -> &'parse ()>, // Invariance
+}
+
+impl<'a, 'parse, I: Input<'a>> Offset<'a, 'parse, I> {
+    /// Get the raw value of this offset, such as to store it in an AST node or compare it against external data.
+    ///
+    /// What this value represents depends on the input: for `&str` it is a byte index, while for slices and streams
+    /// it is the index of a token. No corresponding constructor exists, so it is not possible to turn the value back
+    /// into an [`Offset`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let pos = custom::<_, &str, _, extra::Default>(|inp| Ok(inp.offset().as_usize()));
+    ///
+    /// assert_eq!(just("ab").ignore_then(pos).then_ignore(just("€")).parse("ab€").into_result(), Ok(2));
+    /// ```
+    #[inline(always)]
+    pub fn as_usize(self) -> usize {
+        self.offset.into()
+    }
+}
+
+impl<'a, 'parse, I: Input<'a>> Copy for Offset<'a, 'parse, I> {}
+impl<'a, 'parse, I: Input<'a>> Clone for Offset<'a, 'parse, I> {
+    #[inline(always)]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, 'parse, I: Input<'a>> PartialEq for Offset<'a, 'parse, I> {
+    fn eq(&self, other: &Self) -> bool {
+        self.offset == other.offset
+    }
+}
+
+impl<'a, 'parse, I: Input<'a>> Eq for Offset<'a, 'parse, I> {}
+
+impl<'a, 'parse, I: Input<'a>> PartialOrd for Offset<'a, 'parse, I> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'a, 'parse, I: Input<'a>> Ord for Offset<'a, 'parse, I> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.offset.cmp(&other.offset)
+    }
+}
+
+pub(crate) struct Errors<T, E, S = Vec<Located<T, E>>> {
+    pub(crate) alt: Option<Located<T, E>>,
+    // Only ever accessed through the methods below, so that the storage used for secondary errors is decided in one
+    // place. See `extra::StoreErrors`.
+    secondary: S,
+    // The number of secondary errors emitted, including those that were dropped because `limit` had been reached or
+    // `secondary` was full. Only the first errors are stored, so the stored errors are always a prefix of those emitted.
+    emitted: usize,
+    limit: usize,
+}
+
+impl<T, E, S: ErrorStorage<Located<T, E>>> Errors<T, E, S> {
+    /// Create an empty error collection that stores at most `limit` secondary errors. See [`extra::MaxErrors`].
+    pub(crate) fn with_limit(limit: usize) -> Self {
+        Self {
+            alt: None,
+            secondary: S::default(),
+            emitted: 0,
+            limit,
+        }
+    }
+
+    /// Returns the number of secondary errors that have been emitted so far.
+    #[inline(always)]
+    pub(crate) fn secondary_len(&self) -> usize {
+        self.emitted
+    }
+
+    /// Returns the number of secondary errors that were emitted but not stored because the limit had been reached or
+    /// the storage was full.
+    #[inline(always)]
+    pub(crate) fn dropped(&self) -> usize {
+        self.emitted - self.secondary.as_slice().len()
+    }
+
+    /// Emit a new secondary error.
+    #[inline(always)]
+    pub(crate) fn push_secondary(&mut self, err: Located<T, E>) {
+        // Once an error has been dropped, no later one may be stored
+        if self.secondary.as_slice().len() == self.emitted && self.emitted < self.limit {
+            let _ = self.secondary.push(err);
+        }
+        self.emitted += 1;
+    }
+
+    /// Discard all secondary errors emitted after the first `err_count`.
+    #[inline(always)]
+    pub(crate) fn truncate_secondary(&mut self, err_count: usize) {
+        self.secondary.truncate(err_count);
+        self.emitted = err_count;
+    }
+
+    /// Returns a slice of the secondary errors (if any) have been emitted since the given marker was created.
+    #[inline]
+    pub(crate) fn secondary_errors_since(&mut self, err_count: usize) -> &mut [Located<T, E>] {
+        self.secondary
+            .as_mut_slice()
+            .get_mut(err_count..)
+            .unwrap_or(&mut [])
+    }
+
+    /// Returns a slice of the secondary errors (if any) have been emitted since the given marker was created.
+    #[inline]
+    pub(crate) fn secondary_errors_since_ref(&self, err_count: usize) -> &[Located<T, E>] {
+        self.secondary.as_slice().get(err_count..).unwrap_or(&[])
+    }
+
+    /// Remove and return the secondary errors (if any) have been emitted since the given marker was created.
+    #[inline]
+    pub(crate) fn take_secondary_since(
+        &mut self,
+        err_count: usize,
+    ) -> impl Iterator<Item = Located<T, E>> {
+        self.emitted = err_count;
+        let mut taken = Vec::new();
+        while self.secondary.as_slice().len() > err_count {
+            taken.extend(self.secondary.pop());
+        }
+        taken.into_iter().rev()
+    }
+
+    /// Consume the error collection, returning all secondary errors in the order they were emitted.
+    #[inline]
+    pub(crate) fn into_secondary(mut self) -> impl Iterator<Item = Located<T, E>> {
+        self.take_secondary_since(0)
+    }
+}
+
+impl<T, E, S: ErrorStorage<Located<T, E>>> Default for Errors<T, E, S> {
+    fn default() -> Self {
+        Self::with_limit(usize::MAX)
+    }
+}
+
+/// The memoisation tables of a parse.
+#[cfg(feature = "memoization")]
+pub(crate) struct Memos<T, E> {
+    // Failures of parsers created by `Parser::memoised`, keyed by offset and parser address
+    pub(crate) parsers: HashMap<(T, usize), Option<Located<T, E>>>,
+    // Results recorded by `InputRef::memo_put`, keyed by offset and a user-provided key
+    user: HashMap<(T, usize), UserMemo<T, E>>,
+}
+
+#[cfg(feature = "memoization")]
+impl<T, E> Default for Memos<T, E> {
+    fn default() -> Self {
+        Self {
+            parsers: HashMap::default(),
+            user: HashMap::default(),
+        }
+    }
+}
+
+#[cfg(feature = "memoization")]
+enum UserMemo<T, E> {
+    Ok(T, Box<dyn core::any::Any>),
+    Err(E),
+}
+
+/// Statistics about the backtracking performed during a parse. See [`ParseResult::backtrack_stats`].
+///
+/// Distances are measured in raw offsets (see [`Offset::as_usize`]): token indices for most inputs, and bytes for
+/// string inputs.
+#[cfg(feature = "backtrack-stats")]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BacktrackStats {
+    /// The number of times that the parser rewound to an earlier position in the input.
+    pub rewinds: usize,
+    /// The total distance rewound, i.e: the amount of input that may have been scanned more than once.
+    pub rescanned: usize,
+    /// The distance of the furthest single rewind.
+    pub deepest: usize,
+}
+
+#[cfg(feature = "backtrack-stats")]
+impl BacktrackStats {
+    #[inline(always)]
+    fn record(&mut self, from: usize, to: usize) {
+        if to < from {
+            self.rewinds += 1;
+            self.rescanned += from - to;
+            self.deepest = self.deepest.max(from - to);
+        }
+    }
+}
+
+/// A log of every change of position made during a parse, in the order in which they occurred. See
+/// [`ParseResult::consumption_log`].
+///
+/// Parsers are expected to be deterministic: parsing the same input twice should consume and backtrack over it in
+/// exactly the same way. Comparing the logs of two runs with [`ConsumptionLog::first_divergence`] can help to track
+/// down parsers that are not, such as those that depend on external state.
+///
+/// Offsets are raw offsets (see [`Offset::as_usize`]): token indices for most inputs, and bytes for string inputs.
+#[cfg(feature = "consumption-log")]
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ConsumptionLog {
+    transitions: Vec<(usize, usize)>,
+}
+
+#[cfg(feature = "consumption-log")]
+impl ConsumptionLog {
+    #[inline(always)]
+    fn record(&mut self, from: usize, to: usize) {
+        if from != to {
+            self.transitions.push((from, to));
+        }
+    }
+
+    /// The recorded transitions, as `(from, to)` pairs of offsets. Transitions where `to` is less than `from` are
+    /// rewinds.
+    pub fn transitions(&self) -> &[(usize, usize)] {
+        &self.transitions
+    }
+
+    /// Find the index of the first transition at which this log and another differ, or `None` if they are identical.
+    ///
+    /// If one log is a prefix of the other, the index returned is the length of the shorter log.
+    pub fn first_divergence(&self, other: &Self) -> Option<usize> {
+        self.transitions
+            .iter()
+            .zip(&other.transitions)
+            .position(|(a, b)| a != b)
+            .or_else(|| {
+                (self.transitions.len() != other.transitions.len())
+                    .then(|| self.transitions.len().min(other.transitions.len()))
+            })
+    }
+
+    /// Assert that a log recorded by replaying a parse is identical to this one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the logs differ, reporting the first transition at which they diverge.
+    #[track_caller]
+    pub fn assert_replayed_by(&self, replay: &Self) {
+        if let Some(idx) = self.first_divergence(replay) {
+            panic!(
+                "replay diverged at transition {idx}: expected {:?}, found {:?}",
+                self.transitions.get(idx),
+                replay.transitions.get(idx),
+            );
+        }
+    }
+}
+
+/// The amount of work that a parse may still perform, and the offset at which it ran out. See
+/// [`Parser::parse_with_fuel`].
+#[cfg(feature = "fuel")]
+pub(crate) struct Fuel<T> {
+    remaining: usize,
+    pub(crate) exhausted_at: Option<T>,
+}
+
+#[cfg(feature = "fuel")]
+impl<T> Fuel<T> {
+    pub(crate) fn new(remaining: usize) -> Self {
+        Self {
+            remaining,
+            exhausted_at: None,
+        }
+    }
+
+    pub(crate) fn unlimited() -> Self {
+        Self::new(usize::MAX)
+    }
+
+    /// Consume the given amount of fuel, returning `false` (and using up what was left) if there was not enough.
+    #[inline(always)]
+    pub(crate) fn consume(&mut self, amount: usize) -> bool {
+        if self.remaining < amount {
+            self.remaining = 0;
+            false
+        } else {
+            self.remaining -= amount;
+            true
+        }
+    }
+}
+
+/// Internal type representing the owned parts of an input - used at the top level by a call to
+/// `parse`.
+pub(crate) struct InputOwn<'a, 's, I: Input<'a>, E: ParserExtra<'a, I>> {
+    pub(crate) input: I,
+    pub(crate) errors: Errors<I::Offset, E::Error, E::ErrorStorage>,
+    pub(crate) trivia: Vec<Range<I::Offset>>,
+    pub(crate) notes: Vec<(I::Offset, Box<dyn core::any::Any>)>,
+    #[cfg(feature = "fuel")]
+    pub(crate) fuel: Fuel<I::Offset>,
+    pub(crate) state: MaybeMut<'s, E::State>,
+    pub(crate) ctx: E::Context,
+    #[cfg(feature = "memoization")]
+    pub(crate) memos: Memos<I::Offset, E::Error>,
+    #[cfg(feature = "backtrack-stats")]
+    pub(crate) backtracks: BacktrackStats,
+    #[cfg(feature = "consumption-log")]
+    pub(crate) log: ConsumptionLog,
+}
+
+impl<'a, 's, I, E> InputOwn<'a, 's, I, E>
+where
+    I: Input<'a>,
+    E: ParserExtra<'a, I>,
+{
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub(crate) fn new(input: I) -> InputOwn<'a, 's, I, E>
+    where
+        E::State: Default,
+        E::Context: Default,
+    {
+        InputOwn {
+            input,
+            errors: Errors::with_limit(E::MAX_ERRORS),
+            trivia: Vec::new(),
+            notes: Vec::new(),
+            #[cfg(feature = "fuel")]
+            fuel: Fuel::unlimited(),
+            state: MaybeMut::Val(E::State::default()),
+            ctx: E::Context::default(),
+            #[cfg(feature = "memoization")]
+            memos: Memos::default(),
+            #[cfg(feature = "backtrack-stats")]
+            backtracks: BacktrackStats::default(),
+            #[cfg(feature = "consumption-log")]
+            log: ConsumptionLog::default(),
+        }
+    }
+
+    pub(crate) fn new_state(input: I, state: &'s mut E::State) -> InputOwn<'a, 's, I, E>
+    where
+        E::Context: Default,
+    {
+        InputOwn {
+            input,
+            errors: Errors::with_limit(E::MAX_ERRORS),
+            trivia: Vec::new(),
+            notes: Vec::new(),
+            #[cfg(feature = "fuel")]
+            fuel: Fuel::unlimited(),
+            state: MaybeMut::Ref(state),
+            ctx: E::Context::default(),
+            #[cfg(feature = "memoization")]
+            memos: Memos::default(),
+            #[cfg(feature = "backtrack-stats")]
+            backtracks: BacktrackStats::default(),
+            #[cfg(feature = "consumption-log")]
+            log: ConsumptionLog::default(),
+        }
+    }
+
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub(crate) fn as_ref_start<'parse>(&'parse mut self) -> InputRef<'a, 'parse, I, E> {
+        InputRef {
+            offset: self.input.start(),
+            input: &self.input,
+            errors: &mut self.errors,
+            trivia: &mut self.trivia,
+            notes: &mut self.notes,
+            #[cfg(feature = "fuel")]
+            fuel: &mut self.fuel,
+            state: &mut self.state,
+            ctx: &self.ctx,
+            #[cfg(feature = "memoization")]
+            memos: &mut self.memos,
+            #[cfg(feature = "backtrack-stats")]
+            backtracks: &mut self.backtracks,
+            #[cfg(feature = "consumption-log")]
+            log: &mut self.log,
+            depth: 0,
+            depth_limit: usize::MAX,
+            committed: false,
+        }
+    }
+
+    pub(crate) fn as_ref_at<'parse>(
+        &'parse mut self,
+        offset: I::Offset,
+    ) -> InputRef<'a, 'parse, I, E> {
+        InputRef {
+            offset,
+            input: &self.input,
+            errors: &mut self.errors,
+            trivia: &mut self.trivia,
+            notes: &mut self.notes,
+            #[cfg(feature = "fuel")]
+            fuel: &mut self.fuel,
+            state: &mut self.state,
+            ctx: &self.ctx,
+            #[cfg(feature = "memoization")]
+            memos: &mut self.memos,
+            #[cfg(feature = "backtrack-stats")]
+            backtracks: &mut self.backtracks,
+            #[cfg(feature = "consumption-log")]
+            log: &mut self.log,
+            depth: 0,
+            depth_limit: usize::MAX,
+            committed: false,
+        }
+    }
+
+    /// Run a parser over the input, starting at the given offset and requiring that it consume all remaining input.
+    pub(crate) fn run<M: Mode, P, O>(
+        mut self,
+        parser: &P,
+        start: I::Offset,
+    ) -> ParseResult<M::Output<O>, E::Error>
+    where
+        P: Parser<'a, I, O, E>,
+    {
+        let mut inp = self.as_ref_at(start);
+        let res = parser.then_ignore(end()).go::<M>(&mut inp);
+        let reached = inp.offset;
+        let alt = inp.errors.alt.take();
+        #[cfg(feature = "backtrack-stats")]
+        let backtrack_stats = self.backtracks;
+        #[cfg(feature = "consumption-log")]
+        let log = core::mem::take(&mut self.log);
+        #[cfg(feature = "fuel")]
+        let exhausted_at = self.fuel.exhausted_at;
+        #[cfg(not(feature = "fuel"))]
+        let exhausted_at = None;
+        let exceeded_at = self.input.backtrack_exceeded_at();
+        let starved_at = self.input.starved_at();
+        // SAFETY: all three offsets were reached by the parser, so they were generated by the input
+        let stopped_at = exhausted_at
+            .or(exceeded_at)
+            .or(starved_at)
+            .map(|at| (at, unsafe { self.input.span(at..at) }));
+        if res.is_ok() && stopped_at.is_none() {
+            // SAFETY: the offset was reached by the parser, so it was generated by the input
+            unsafe { self.input.committed_to(reached) };
+        }
+        let dropped_errors = self.errors.dropped();
+        let (mut err_offsets, mut errs) = self.into_errs();
+        let mut fatal_offset = None;
+        let out = match (res, stopped_at) {
+            // Running out of fuel, backtracking further than the input allows, or reaching the end of the input
+            // before it is complete may cause parsing to fail, or to spuriously succeed by treating the point at which
+            // it stopped as the end of input, so they take priority over any other result
+            (_, Some((at, span))) => {
+                fatal_offset = Some(at.into());
+                err_offsets.push(at.into());
+                errs.push(E::Error::expected_found(None, None, span));
+                None
+            }
+            (Ok(out), None) => Some(out),
+            (Err(()), None) => {
+                let alt = alt.expect("error but no alt?");
+                fatal_offset = Some(alt.pos.into());
+                err_offsets.push(alt.pos.into());
+                errs.push(alt.err);
+                None
+            }
+        };
+        let res = ParseResult::new(out, errs)
+            .with_fatal_offset(fatal_offset)
+            .with_error_offsets(err_offsets)
+            .with_backtrack_limit_exceeded(exceeded_at.is_some())
+            .with_needs_more_input(starved_at.is_some())
+            .with_dropped_errors(dropped_errors);
+        #[cfg(feature = "fuel")]
+        let res = res.with_out_of_fuel(exhausted_at.is_some());
+        #[cfg(feature = "backtrack-stats")]
+        let res = res.with_backtrack_stats(backtrack_stats);
+        #[cfg(feature = "consumption-log")]
+        let res = res.with_consumption_log(log);
+        res
+    }
+
+    pub(crate) fn into_errs(self) -> (Vec<usize>, Vec<E::Error>) {
+        self.errors
+            .into_secondary()
+            .map(|err| (err.pos.into(), err.err))
+            .unzip()
+    }
+}
+
+/// Internal type representing an input as well as all the necessary context for parsing.
+pub struct InputRef<'a, 'parse, I: Input<'a>, E: ParserExtra<'a, I>> {
+    pub(crate) offset: I::Offset,
+    pub(crate) input: &'parse I,
+    pub(crate) errors: &'parse mut Errors<I::Offset, E::Error, E::ErrorStorage>,
+    // The ranges of trivia skipped so far, in input order. Only recorded if `ParserExtra::RECORD_TRIVIA` is set.
+    pub(crate) trivia: &'parse mut Vec<Range<I::Offset>>,
+    // Values attached to offsets with `InputRef::note_at`, in the order they were attached.
+    pub(crate) notes: &'parse mut Vec<(I::Offset, Box<dyn core::any::Any>)>,
+    // The amount of work that parsing may still perform. See `Parser::parse_with_fuel`.
+    #[cfg(feature = "fuel")]
+    pub(crate) fuel: &'parse mut Fuel<I::Offset>,
+    pub(crate) state: &'parse mut E::State,
+    pub(crate) ctx: &'parse E::Context,
+    #[cfg(feature = "memoization")]
+    pub(crate) memos: &'parse mut Memos<I::Offset, E::Error>,
+    #[cfg(feature = "backtrack-stats")]
+    pub(crate) backtracks: &'parse mut BacktrackStats,
+    #[cfg(feature = "consumption-log")]
+    pub(crate) log: &'parse mut ConsumptionLog,
+    // The number of recursive parsers currently being invoked, and the number beyond which they will fail. See
+    // `Parser::recursion_limit`.
+    pub(crate) depth: usize,
+    pub(crate) depth_limit: usize,
+    // Whether the current alternative has been committed to. See `InputRef::commit`.
+    pub(crate) committed: bool,
+}
+
+impl<'a, 'parse, I: Input<'a>, E: ParserExtra<'a, I>> InputRef<'a, 'parse, I, E> {
+    #[inline]
+    pub(crate) fn with_ctx<'sub_parse, C, O>(
+        &'sub_parse mut self,
+        new_ctx: &'sub_parse C,
+        f: impl FnOnce(&mut InputRef<'a, 'sub_parse, I, E::WithContext<C>>) -> O,
+    ) -> O
+    where
+        'parse: 'sub_parse,
+        C: 'a,
+    {
+        let mut new_inp = InputRef {
+            input: self.input,
+            offset: self.offset,
+            state: self.state,
+            ctx: new_ctx,
+            errors: self.errors,
+            trivia: self.trivia,
+            notes: self.notes,
+            #[cfg(feature = "fuel")]
+            fuel: self.fuel,
+            #[cfg(feature = "memoization")]
+            memos: self.memos,
+            #[cfg(feature = "backtrack-stats")]
+            backtracks: self.backtracks,
+            #[cfg(feature = "consumption-log")]
+            log: self.log,
+            depth: self.depth,
+            depth_limit: self.depth_limit,
+            committed: self.committed,
+        };
+        let res = f(&mut new_inp);
+        // The inner input shares the consumption log, so its changes of position have already been recorded
+        self.offset = new_inp.offset;
+        self.committed = new_inp.committed;
+        res
+    }
+
+    #[inline]
+    pub(crate) fn with_input<'sub_parse, O>(
+        &'sub_parse mut self,
+        new_input: &'sub_parse I,
+        f: impl FnOnce(&mut InputRef<'a, 'sub_parse, I, E>) -> O,
+        #[cfg(feature = "memoization")] memos: &'sub_parse mut Memos<I::Offset, E::Error>,
+    ) -> O
+    where
+        'parse: 'sub_parse,
+    {
+        let mut new_inp = InputRef {
+            offset: new_input.start(),
+            input: new_input,
+            state: self.state,
+            ctx: self.ctx,
+            errors: self.errors,
+            trivia: self.trivia,
+            notes: self.notes,
+            #[cfg(feature = "fuel")]
+            fuel: self.fuel,
+            #[cfg(feature = "memoization")]
+            memos,
+            #[cfg(feature = "backtrack-stats")]
+            backtracks: self.backtracks,
+            #[cfg(feature = "consumption-log")]
+            log: self.log,
+            depth: self.depth,
+            depth_limit: self.depth_limit,
+            committed: self.committed,
+        };
+        f(&mut new_inp)
+    }
+
+    /// Run a closure with an isolated error buffer, such that the non-fatal errors it emits can be post-processed before
+    /// they are merged back into the errors of this input.
+    ///
+    /// This is useful when parsing an embedded sub-language (an SQL string within a host language, for example) whose
+    /// errors need re-mapping or wrapping before being reported alongside those of the host. The closure is given the
+    /// [`IsolatedErrors`] that it may move its errors into with [`IsolatedErrors::collect`] and modify. Once it has
+    /// finished, every error in the buffer, along with any that were not collected, is emitted at the position at
+    /// which it is located.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let sub = just::<_, _, extra::Err<Rich<char>>>('a')
+    ///     .recover_with(via_parser(any().to('?')))
+    ///     .repeated()
+    ///     .collect::<String>();
+    ///
+    /// let parser = custom(move |inp| {
+    ///     inp.with_isolated_errors(|inp, errs| {
+    ///         let out = inp.parse(sub);
+    ///         errs.collect(inp);
+    ///         // Report only the first error of the sub-language
+    ///         errs.truncate(1);
+    ///         out
+    ///     })
+    /// });
+    ///
+    /// let res = parser.parse("abba");
+    /// assert_eq!(res.output().map(String::as_str), Some("a??a"));
+    /// assert_eq!(res.errors_located().map(|(at, _)| at).collect::<Vec<_>>(), [2]);
+    /// ```
+    pub fn with_isolated_errors<O>(
+        &mut self,
+        f: impl FnOnce(&mut Self, &mut IsolatedErrors<'a, I, E>) -> O,
+    ) -> O {
+        let mut errs = IsolatedErrors {
+            errs: Vec::new(),
+            err_count: self.errors.secondary_len(),
+        };
+        let out = f(self, &mut errs);
+        errs.collect(self);
+        for err in errs.errs {
+            self.emit(err.pos, err.err);
+        }
+        out
+    }
+
+    /// Get the internal offset of the input at this moment in time.
+    ///
+    /// Can be used for generating spans or slices. See [`InputRef::span`] and [`InputRef::slice`].
+    #[inline(always)]
+    pub fn offset(&self) -> Offset<'a, 'parse, I> {
+        Offset {
+            offset: self.offset,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Get a reference to the input being parsed.
+    ///
+    /// This allows inspecting the input through methods that are not part of chumsky's input traits, such as
+    /// [`str::len`]. Positions within the input should still be obtained through [`InputRef::offset`], since raw
+    /// offsets cannot be turned back into an [`Offset`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// // Succeed only if at least half of the input remains to be parsed
+    /// let first_half = custom::<_, &str, _, extra::Default>(|inp| {
+    ///     let remaining = inp.input().len() - inp.offset().as_usize();
+    ///     if remaining * 2 >= inp.input().len() {
+    ///         Ok(())
+    ///     } else {
+    ///         Err(EmptyErr::default())
+    ///     }
+    /// });
+    ///
+    /// let parser = any().then_ignore(first_half).repeated().collect::<Vec<_>>();
+    /// assert_eq!(parser.lazy().parse("abcd").into_result(), Ok(vec!['a', 'b']));
+    /// ```
+    #[inline(always)]
+    pub fn input(&self) -> &'parse I {
+        self.input
+    }
+
+    /// Save the current parse state as a [`Marker`].
+    ///
+    /// You can rewind back to this state later with [`InputRef::rewind`].
+    #[inline(always)]
+    pub fn save(&self) -> Marker<'a, 'parse, I> {
+        Marker {
+            offset: self.offset,
+            err_count: self.errors.secondary_len(),
+            trivia_count: if E::RECORD_TRIVIA {
+                self.trivia.len()
+            } else {
+                0
+            },
+            note_count: self.notes.len(),
+            committed: self.committed,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Reset the parse state to that represented by the given [`Marker`].
+    ///
+    /// You can create a marker with which to perform rewinding using [`InputRef::save`].
+    #[inline(always)]
+    pub fn rewind(&mut self, marker: Marker<'a, 'parse, I>) {
+        #[cfg(feature = "backtrack-stats")]
+        self.backtracks
+            .record(self.offset.into(), marker.offset.into());
+        self.errors.truncate_secondary(marker.err_count);
+        if E::RECORD_TRIVIA {
+            self.trivia.truncate(marker.trivia_count);
+        }
+        self.notes.truncate(marker.note_count);
+        #[cfg(feature = "fuel")]
+        self.fuel.consume(1);
+        self.set_offset(marker.offset);
+        self.committed = marker.committed;
+    }
+
+    /// Determine whether the input has advanced past the offset of the given [`Marker`].
+    ///
+    /// A repetition that invokes a parser which might succeed without consuming any input will loop forever unless it
+    /// checks for progress on each iteration. Hand-written repetitions in [`custom`] parsers should use this guard,
+    /// stopping once an iteration has failed to advance, rather than comparing raw offsets.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let maybe_digit = any::<_, extra::Default>().filter(char::is_ascii_digit).or_not();
+    /// let digits = custom(move |inp| {
+    ///     let mut count = 0;
+    ///     loop {
+    ///         let before = inp.save();
+    ///         inp.parse(&maybe_digit)?;
+    ///         if !inp.advanced_since(before) {
+    ///             break Ok(count);
+    ///         }
+    ///         count += 1;
+    ///     }
+    /// });
+    ///
+    /// assert_eq!(digits.then_ignore(any().repeated()).parse("123abc").into_result(), Ok(3));
+    /// ```
+    #[inline(always)]
+    pub fn advanced_since(&self, marker: Marker<'a, 'parse, I>) -> bool {
+        self.offset > marker.offset
+    }
+
+    /// Commit to the alternative currently being parsed, preventing backtracking out of it.
+    ///
+    /// If the alternative subsequently fails, the nearest enclosing [`Parser::or`], [`choice`](crate::primitive::choice)
+    /// or [`Parser::or_not`] will not try any further alternatives and will instead fail with the error produced by
+    /// this one. This is commonly known as a 'cut', and is useful for producing more precise errors: having seen
+    /// `let`, for example, a parser can report that it expected `=` rather than that it expected any statement at all.
+    ///
+    /// A commit only applies to the nearest enclosing choice: once that choice has failed, outer choices behave as
+    /// normal. Repetitions such as [`Parser::repeated`] and [`Parser::separated_by`] treat each item the same way: a
+    /// committed item that fails causes the whole repetition to fail instead of ending it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let commit = custom::<_, &str, _, extra::Err<Rich<char>>>(|inp| {
+    ///     inp.commit();
+    ///     Ok(())
+    /// });
+    ///
+    /// let ident = text::ident().padded();
+    /// let let_stmt = text::keyword("let")
+    ///     .then(commit)
+    ///     .ignore_then(ident)
+    ///     .then_ignore(just('='))
+    ///     .then(ident)
+    ///     .map(|(name, value)| format!("{name} := {value}"));
+    /// // Anything that isn't a `let` statement is treated as raw text
+    /// let stmt = let_stmt.or(any().repeated().slice().map(str::to_string));
+    ///
+    /// assert_eq!(stmt.parse("let x = y").into_result(), Ok("x := y".to_string()));
+    /// assert_eq!(stmt.parse("lettuce").into_result(), Ok("lettuce".to_string()));
+    /// // Without the commit, this would silently be treated as raw text
+    /// let errs = stmt.parse("let x y").into_errors();
+    /// assert_eq!(errs[0].span(), &SimpleSpan::new(6, 7));
+    /// ```
+    #[inline(always)]
+    pub fn commit(&mut self) {
+        self.committed = true;
+        // SAFETY: the current offset was generated by the input
+        unsafe { self.input.committed_to(self.offset) };
+    }
+
+    /// Attempt one alternative of a choice, taking commits (see [`InputRef::commit`]) into account.
+    ///
+    /// Returns `None` if the alternative failed without committing, meaning that the next alternative may be tried.
+    #[inline(always)]
+    pub(crate) fn go_alt<R>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<R, ()>,
+    ) -> Option<Result<R, ()>> {
+        let outer = core::mem::replace(&mut self.committed, false);
+        let res = f(self);
+        let committed = core::mem::replace(&mut self.committed, outer);
+        match res {
+            Err(()) if !committed => None,
+            res => Some(res),
+        }
+    }
+
+    /// Get an iterator over the non-fatal errors that have been emitted since the given [`Marker`] was created.
+    ///
+    /// This is useful for deciding whether to generate an error of your own, perhaps to avoid emitting cascading
+    /// diagnostics when an inner parser has already reported the problem.
+    #[inline]
+    pub fn errors_since(
+        &self,
+        marker: Marker<'a, 'parse, I>,
+    ) -> impl ExactSizeIterator<Item = &E::Error> + '_ {
+        self.errors
+            .secondary_errors_since_ref(marker.err_count)
+            .iter()
+            .map(|err| &err.err)
+    }
+
+    /// Take a snapshot of the entire parse state as a [`Checkpoint`].
+    ///
+    /// Unlike [`InputRef::save`], a checkpoint also captures a clone of the parser state and of any pending
+    /// alternative error, allowing [`InputRef::restore`] to put everything back exactly as it was. This is
+    /// considerably more expensive than creating a [`Marker`] and is intended for explicit, user-driven backtracking
+    /// (such as undo stacks in interactive parsers) rather than for use in hot paths.
+    #[inline]
+    pub fn checkpoint(&self) -> Checkpoint<'a, 'parse, I, E>
+    where
+        E::State: Clone,
+        E::Error: Clone,
+    {
+        Checkpoint {
+            marker: self.save(),
+            alt: self.errors.alt.clone(),
+            state: self.state.clone(),
+        }
+    }
+
+    /// Reset the entire parse state, including the parser state, to that represented by the given [`Checkpoint`].
+    ///
+    /// You can create a checkpoint with which to perform restoration using [`InputRef::checkpoint`].
+    #[inline]
+    pub fn restore(&mut self, checkpoint: Checkpoint<'a, 'parse, I, E>) {
+        self.rewind(checkpoint.marker);
+        self.errors.alt = checkpoint.alt;
+        *self.state = checkpoint.state;
+    }
+
+    /// Get a mutable reference to the state associated with the current parse.
+    #[inline(always)]
+    pub fn state(&mut self) -> &mut E::State {
+        self.state
+    }
+
+    /// Run `f` within a scope of the parser state, calling `enter` on the state beforehand and `exit` afterwards.
+    ///
+    /// `exit` is guaranteed to be called once `f` has finished, regardless of whether the parsing it performed
+    /// succeeded, failed, or rewound the input, and even if `f` panics. This makes it suitable for maintaining stacks
+    /// within the parser state (such as the scopes of nested blocks) without risking unbalanced pushes and pops.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let block = custom::<_, &str, _, extra::State<Vec<usize>>>(|inp| {
+    ///     inp.with_state_scope(
+    ///         |scopes| scopes.push(scopes.len()),
+    ///         |scopes| { scopes.pop(); },
+    ///         |inp| {
+    ///             let depth = inp.state().len();
+    ///             inp.parse(just('{').then(just('}')))?;
+    ///             Ok(depth)
+    ///         },
+    ///     )
+    /// });
+    ///
+    /// let mut scopes = Vec::new();
+    /// assert_eq!(block.parse_with_state("{}", &mut scopes).into_result(), Ok(1));
+    /// assert!(block.parse_with_state("{", &mut scopes).has_errors());
+    /// assert!(scopes.is_empty());
+    /// ```
+    #[inline]
+    pub fn with_state_scope<O>(
+        &mut self,
+        enter: impl FnOnce(&mut E::State),
+        exit: impl FnOnce(&mut E::State),
+        f: impl FnOnce(&mut Self) -> O,
+    ) -> O {
+        enter(self.state());
+        let guard = ScopeGuard {
+            inner: self,
+            exit: Some(move |inp: &mut Self| exit(inp.state())),
+        };
+        f(guard.inner)
+    }
+
+    /// Get a reference to the context fed to the current parser.
+    ///
+    /// See [`ConfigParser::configure`] and [`Parser::then_with_ctx`] for more information about context-sensitive
+    /// parsing.
+    #[inline(always)]
+    pub fn ctx(&self) -> &E::Context {
+        self.ctx
+    }
+
+    /// Move to the given offset, recording the change in the consumption log. Every change of position should be made
+    /// through this so that the log is complete.
+    #[inline(always)]
+    pub(crate) fn set_offset(&mut self, offset: I::Offset) {
+        #[cfg(feature = "consumption-log")]
+        self.log.record(self.offset.into(), offset.into());
+        self.offset = offset;
+    }
+
+    /// Consume the given amount of fuel, returning `false` and recording where it ran out if there was not enough. This
+    /// always succeeds unless the `fuel` feature is enabled.
+    #[inline(always)]
+    pub(crate) fn consume_fuel(&mut self, amount: usize) -> bool {
+        #[cfg(feature = "fuel")]
+        if !self.fuel.consume(amount) {
+            self.fuel.exhausted_at.get_or_insert(self.offset);
+            return false;
+        }
+        #[cfg(not(feature = "fuel"))]
+        let _ = amount;
+        true
+    }
+
+    #[inline]
+    pub(crate) fn skip_while<F: FnMut(&I::Token) -> bool>(&mut self, mut f: F)
+    where
+        I: ValueInput<'a>,
+    {
+        loop {
+            // SAFETY: offset was generated by previous call to `Input::next`
+            let (offset, token) = unsafe { self.input.next(self.offset) };
+            if token.filter(&mut f).is_none() || !self.consume_fuel(1) {
+                break;
+            } else {
+                self.set_offset(offset);
+            }
+        }
+    }
+
+    /// Skip over the longest run of tokens that match the given predicate, returning a slice of the input that covers
+    /// them.
+    ///
+    /// This is considerably faster than building the equivalent parser out of combinators (i.e:
+    /// `any().filter(f).repeated().slice()`) and is useful for scanning identifiers, numbers, or whitespace from
+    /// within a [`custom`] parser.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let ident = custom::<_, &str, _, extra::Default>(|inp| {
+    ///     Ok(inp.take_while_slice(|c: &char| c.is_alphanumeric()))
+    /// });
+    ///
+    /// assert_eq!(ident.then_ignore(any().repeated()).parse("hello world").into_result(), Ok("hello"));
+    /// ```
+    #[inline]
+    pub fn take_while_slice<F: FnMut(&I::Token) -> bool>(&mut self, f: F) -> I::Slice
+    where
+        I: ValueInput<'a> + SliceInput<'a>,
+    {
+        let before = self.offset;
+        self.skip_while(f);
+        self.slice_inner(before..self.offset)
+    }
+
+    /// Skip over tokens up to (but not including) the next token that is equal to the given one, or to the end of the
+    /// input if there is no such token.
+    ///
+    /// This is equivalent to `inp.take_while_slice(|t| t != token)`, but string inputs implement it with a fast search
+    /// of their bytes rather than decoding each character in turn, making it well-suited to skipping over the rest of a
+    /// line or the body of a comment.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let comment = just::<_, &str, extra::Default>("//").ignore_then(custom(|inp| {
+    ///     let before = inp.offset();
+    ///     inp.skip_until(&'\n');
+    ///     Ok(inp.slice(before..inp.offset()))
+    /// }));
+    ///
+    /// assert_eq!(comment.then_ignore(just('\n')).parse("// hello\n").into_result(), Ok(" hello"));
+    /// assert_eq!(comment.parse("// no newline").into_result(), Ok(" no newline"));
+    /// ```
+    #[inline]
+    pub fn skip_until(&mut self, token: &I::Token)
+    where
+        I: ValueInput<'a>,
+        I::Token: PartialEq,
+    {
+        // SAFETY: offset was generated by previous call to `Input::next`
+        let offset = unsafe { self.input.find_token(self.offset, token) };
+        // SAFETY: both offsets were generated by the input
+        #[cfg(feature = "fuel")]
+        if !self.consume_fuel(unsafe { self.input.token_distance(self.offset, offset) }) {
+            return;
+        }
+        self.set_offset(offset);
+    }
+
+    #[inline(always)]
+    pub(crate) fn next_inner(&mut self) -> (I::Offset, Option<I::Token>)
+    where
+        I: ValueInput<'a>,
+    {
+        if !self.consume_fuel(1) {
+            return (self.offset, None);
+        }
+        // SAFETY: offset was generated by previous call to `Input::next`
+        let (offset, token) = unsafe { self.input.next(self.offset) };
+        debug_assert!(
+            token.is_none() || offset > self.offset,
+            "input produced a token without advancing its offset, which would cause parsing to never terminate: this is \
+             likely a bug in the input's implementation of `next`",
+        );
+        self.set_offset(offset);
+        (self.offset, token)
+    }
+
+    #[inline(always)]
+    pub(crate) fn next_maybe_inner(&mut self) -> (I::Offset, Option<I::TokenMaybe>) {
+        if !self.consume_fuel(1) {
+            return (self.offset, None);
+        }
+        // SAFETY: offset was generated by previous call to `Input::next`
+        let (offset, token) = unsafe { self.input.next_maybe(self.offset) };
+        debug_assert!(
+            token.is_none() || offset > self.offset,
+            "input produced a token without advancing its offset, which would cause parsing to never terminate: this is \
+             likely a bug in the input's implementation of `next_maybe`",
+        );
+        self.set_offset(offset);
+        (self.offset, token)
+    }
+
+    #[inline(always)]
+    pub(crate) fn next_ref_inner(&mut self) -> (I::Offset, Option<&'a I::Token>)
+    where
+        I: BorrowInput<'a>,
+    {
+        if !self.consume_fuel(1) {
+            return (self.offset, None);
+        }
+        // SAFETY: offset was generated by previous call to `Input::next`
+        let (offset, token) = unsafe { self.input.next_ref(self.offset) };
+        debug_assert!(
+            token.is_none() || offset > self.offset,
+            "input produced a token without advancing its offset, which would cause parsing to never terminate: this is \
+             likely a bug in the input's implementation of `next_ref`",
+        );
+        self.set_offset(offset);
+        (self.offset, token)
+    }
+
+    /// Attempt to parse this input using the given parser.
+    ///
+    /// # Important Notice
+    ///
+    /// Parsers that return `Err(...)` are permitted to leave the input in an **unspecified** (but not
+    /// [undefined](https://en.wikipedia.org/wiki/Undefined_behavior)) state.
+    ///
+    /// The only well-specified action you are permitted to perform on the input after an error has occurred is
+    /// rewinding to a marker created *before* the error occurred via [`InputRef::rewind`].
+    ///
+    /// This state is not consistent between releases of chumsky, compilations of the final binary, or even invocations
+    /// of the parser. You should not rely on this state for anything, and choosing to rely on it means that your
+    /// parser may break in unexpected ways at any time.
+    ///
+    /// You have been warned.
+    pub fn parse<O, P: Parser<'a, I, O, E>>(&mut self, parser: P) -> Result<O, E::Error> {
+        match parser.go::<Emit>(self) {
+            Ok(out) => Ok(out),
+            Err(()) => Err(self.errors.alt.take().expect("error but no alt?").err),
+        }
+    }
+
+    /// A check-only version of [`InputRef::parse`].
+    ///
+    /// # Import Notice
+    ///
+    /// See [`InputRef::parse`] about unspecified behaviour associated with this function.
+    pub fn check<O, P: Parser<'a, I, O, E>>(&mut self, parser: P) -> Result<(), E::Error> {
+        match parser.go::<Check>(self) {
+            Ok(()) => Ok(()),
+            Err(()) => Err(self.errors.alt.take().expect("error but no alt?").err),
+        }
+    }
+
+    /// Determine whether the given parser would succeed at the current position, without any effect on the input.
+    ///
+    /// Unlike [`InputRef::check`], the position of the input, its errors (including the error that will be reported
+    /// if no alternatives succeed), and whether the current alternative has been committed to are all left exactly as
+    /// they were, whether or not the parser succeeds. This makes it suitable for testing whether a branch is viable
+    /// before running it for real. Note that changes made by the parser to the parser state are not undone.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let call_or_ident = custom::<_, &str, _, extra::Err<Simple<char>>>(|inp| {
+    ///     let ident = text::ident();
+    ///     if inp.probe(ident.then(just('('))) {
+    ///         inp.parse(ident.then_ignore(just("()"))).map(|name| format!("call {name}"))
+    ///     } else {
+    ///         inp.parse(ident).map(|name| format!("ident {name}"))
+    ///     }
+    /// });
+    ///
+    /// assert_eq!(call_or_ident.parse("foo()").into_result(), Ok("call foo".to_string()));
+    /// assert_eq!(call_or_ident.parse("foo").into_result(), Ok("ident foo".to_string()));
+    /// ```
+    pub fn probe<O, P: Parser<'a, I, O, E>>(&mut self, parser: P) -> bool {
+        let before = self.save();
+        let alt = self.errors.alt.take();
+        let ok = parser.go::<Check>(self).is_ok();
+        self.errors.alt = alt;
+        self.rewind(before);
+        ok
+    }
+
+    /// Run a parser over a region of the input that has already been consumed (such as the contents of a string
+    /// literal that need escape processing), returning its output or the error that it failed with.
+    ///
+    /// The parser must consume the whole region. Unlike parsing a new input built from the slice of the region, the
+    /// spans and errors that the parser produces are in the coordinates of this input rather than being relative to
+    /// the start of the region, so they do not need to be re-based. The position of this input is left unchanged.
+    ///
+    /// This is only available for inputs whose slices are also inputs of the same type, such as `&str` and `&[T]`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let escape = just('\\').ignore_then(just('n').to('\n'));
+    /// let contents = none_of("\\").or(escape).repeated().collect::<String>();
+    ///
+    /// let string = custom::<_, &str, _, extra::Err<Rich<char>>>(move |inp| {
+    ///     inp.parse(just('"'))?;
+    ///     let before = inp.offset();
+    ///     inp.take_while_slice(|c| *c != '"');
+    ///     let after = inp.offset();
+    ///     inp.parse(just('"'))?;
+    ///     inp.parse_slice(before..after, contents.clone())
+    /// });
+    ///
+    /// assert_eq!(string.parse(r#""a\nb""#).into_result(), Ok("a\nb".to_string()));
+    /// // The span of the invalid escape is in the coordinates of the whole input, not just the string's contents
+    /// let errs = string.parse(r#""a\qb""#).into_errors();
+    /// assert_eq!(errs[0].span(), &SimpleSpan::new(3, 4));
+    /// ```
+    pub fn parse_slice<O, P: Parser<'a, I, O, E>>(
+        &mut self,
+        range: Range<Offset<'a, 'parse, I>>,
+        parser: P,
+    ) -> Result<O, E::Error>
+    where
+        I: SliceInput<'a, Slice = I>,
+    {
+        // A slice that begins at the start of the input shares its offsets, so parsing the part of it that starts at
+        // `range.start` produces spans in the coordinates of this input
+        let prefix = self.input.slice(self.input.start()..range.end.offset);
+
+        let alt = self.errors.alt.take();
+        #[cfg(feature = "memoization")]
+        let mut memos = Memos::default();
+        let res = self.with_input(
+            &prefix,
+            |inp| {
+                inp.set_offset(range.start.offset);
+                inp.parse(parser.then_ignore(end()))
+            },
+            #[cfg(feature = "memoization")]
+            &mut memos,
+        );
+        self.errors.alt = alt;
+
+        res
+    }
+
+    /// Run a parser over a separate input of the same type (such as a fragment of an embedded language extracted from
+    /// this input), returning its output along with how far it got and the non-fatal errors it emitted.
+    ///
+    /// Unlike [`InputRef::parse`], the parser is not required to succeed or to consume the whole input for useful
+    /// information to be returned: the [`SubParse`] reports the offset that the parser reached, so a partial node can
+    /// be built and the problem reported without the whole fragment being treated as unparseable. The errors emitted
+    /// by the parser are returned rather than being added to those of this input, and the position of this input is
+    /// left unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let list = text::int(10).separated_by(just(',')).collect::<Vec<_>>();
+    ///
+    /// let fragment = custom::<_, &str, _, extra::Err<Rich<char>>>(move |inp| {
+    ///     let before = inp.offset();
+    ///     inp.take_while_slice(|c| *c != ';');
+    ///     let source = inp.slice(before..inp.offset());
+    ///
+    ///     // Keep whatever could be parsed, and note whether the whole fragment was understood
+    ///     let sub = inp.parse_nested(source, list.clone());
+    ///     Ok((sub.output.unwrap_or_default(), sub.offset == source.len()))
+    /// });
+    ///
+    /// let stmt = fragment.then_ignore(just(';'));
+    /// assert_eq!(stmt.parse("1,2,3;").into_result(), Ok((vec!["1", "2", "3"], true)));
+    /// assert_eq!(stmt.parse("1,2,x;").into_result(), Ok((vec!["1", "2"], false)));
+    /// ```
+    pub fn parse_nested<O, P: Parser<'a, I, O, E>>(
+        &mut self,
+        input: I,
+        parser: P,
+    ) -> SubParse<O, E::Error> {
+        let alt = self.errors.alt.take();
+        let err_count = self.errors.secondary_len();
+        #[cfg(feature = "memoization")]
+        let mut memos = Memos::default();
+        let (output, offset) = self.with_input(
+            &input,
+            |inp| match parser.go::<Emit>(inp) {
+                Ok(out) => (Ok(out), inp.offset),
+                Err(()) => {
+                    let alt = inp.errors.alt.take().expect("error but no alt?");
+                    (Err(alt.err), alt.pos)
+                }
+            },
+            #[cfg(feature = "memoization")]
+            &mut memos,
+        );
+        let errors = self
+            .errors
+            .take_secondary_since(err_count)
+            .map(|err| err.err)
+            .collect();
+        self.errors.alt = alt;
+
+        SubParse {
+            output,
+            offset: offset.into(),
+            errors,
+        }
+    }
+
+    /// Look up a result previously recorded with [`InputRef::memo_put`] under the given key at the current offset.
+    ///
+    /// If a successful result is found, the input skips ahead to the offset at which that parse ended. `None` is
+    /// returned if no result was recorded, or if the recorded output was not of type `O`.
+    ///
+    /// Together with [`InputRef::memo_put`], this allows custom parsers to participate in packrat-style caching in
+    /// the same way as [`Parser::memoised`]. Keys are independent of those used by [`Parser::memoised`], so it is up
+    /// to the caller to ensure that a key is only used for one parser.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// # use std::cell::Cell;
+    /// let calls = Cell::new(0);
+    /// let int = custom::<_, &str, _, extra::Err<Simple<char>>>(|inp| {
+    ///     let before = inp.offset();
+    ///     if let Some(res) = inp.memo_get::<u32>(0) {
+    ///         return res;
+    ///     }
+    ///     calls.set(calls.get() + 1);
+    ///     let res = inp.parse(text::int(10).from_str::<u32>().unwrapped());
+    ///     inp.memo_put(before, 0, res.clone());
+    ///     res
+    /// });
+    ///
+    /// // Both alternatives begin with the same integer, but it only gets parsed once
+    /// let sum = int.then_ignore(just('+')).then(int).map(|(a, b)| a + b);
+    /// let diff = int.then_ignore(just('-')).then(int).map(|(a, b)| a - b);
+    ///
+    /// assert_eq!(sum.or(diff).parse("42-2").into_result(), Ok(40));
+    /// assert_eq!(calls.get(), 2);
+    /// ```
+    #[cfg(feature = "memoization")]
+    pub fn memo_get<O: Clone + 'static>(&mut self, key: usize) -> Option<Result<O, E::Error>>
+    where
+        E::Error: Clone,
+    {
+        match self.memos.user.get(&(self.offset, key))? {
+            UserMemo::Ok(end, out) => {
+                let out = out.downcast_ref::<O>()?.clone();
+                self.set_offset(*end);
+                Some(Ok(out))
+            }
+            UserMemo::Err(err) => Some(Err(err.clone())),
+        }
+    }
+
+    /// Record the result of a parse that began at `before` and ended at the current offset under the given key, such
+    /// that it may later be retrieved with [`InputRef::memo_get`].
+    ///
+    /// Any result previously recorded under the same key at the same offset is replaced.
+    #[cfg(feature = "memoization")]
+    pub fn memo_put<O: 'static>(
+        &mut self,
+        before: Offset<'a, 'parse, I>,
+        key: usize,
+        result: Result<O, E::Error>,
+    ) {
+        let memo = match result {
+            Ok(out) => UserMemo::Ok(self.offset, Box::new(out)),
+            Err(err) => UserMemo::Err(err),
+        };
+        self.memos.user.insert((before.offset, key), memo);
+    }
+
+    /// Get the next token in the input. Returns `None` if the end of the input has been reached.
+    ///
+    /// This function is more flexible than either [`InputRef::next`] or [`InputRef::next_ref`] since it
+    /// only requires that the [`Input`] trait be implemented for `I` (instead of either [`ValueInput`] or
+    /// [`BorrowInput`]). However, that increased flexibility for the end user comes with a tradeoff for the
+    /// implementation: this function returns a [`MaybeRef<I::Token>`] that provides only a temporary reference to the
+    /// token.
+    ///
+    /// See [`InputRef::next_ref`] if you want get a reference to the next token instead.
+    #[inline(always)]
+    pub fn next_maybe(&mut self) -> Option<MaybeRef<'a, I::Token>> {
+        self.next_maybe_inner().1.map(Into::into)
+    }
+
+    /// Get the next token in the input by value. Returns `None` if the end of the input has been reached.
+    ///
+    /// See [`InputRef::next_ref`] if you want get a reference to the next token instead.
+    #[inline(always)]
+    pub fn next(&mut self) -> Option<I::Token>
+    where
+        I: ValueInput<'a>,
+    {
+        self.next_inner().1
+    }
+
+    /// Get a reference to the next token in the input. Returns `None` if the end of the input has been reached.
+    ///
+    /// See [`InputRef::next`] if you want get the next token by value instead.
+    #[inline(always)]
+    pub fn next_ref(&mut self) -> Option<&'a I::Token>
+    where
+        I: BorrowInput<'a>,
+    {
+        self.next_ref_inner().1
     }
 
-    pub(crate) fn new_state(input: I, state: &'s mut E::State) -> InputOwn<'a, 's, I, E>
+    /// Consume the next byte of the input if it is contained within the given [`ByteSet`], returning it.
+    ///
+    /// Returns `None` without consuming anything if the next byte is not in the set or the end of the input has been
+    /// reached. Membership is tested in constant time, making this useful for fast byte-oriented lexers.
+    #[inline(always)]
+    pub fn next_if_in_set(&mut self, set: &ByteSet) -> Option<u8>
     where
-        E::Context: Default,
+        I: ValueInput<'a, Token = u8>,
     {
-        InputOwn {
-            input,
-            errors: Errors::default(),
-            state: MaybeMut::Ref(state),
-            ctx: E::Context::default(),
-            #[cfg(feature = "memoization")]
-            memos: HashMap::default(),
-        }
+        // Consuming the byte through `next_inner` keeps fuel and the consumption log up to date
+        self.peek().filter(|b| set.contains(*b))?;
+        self.next_inner().1
     }
 
-    pub(crate) fn as_ref_start<'parse>(&'parse mut self) -> InputRef<'a, 'parse, I, E> {
-        InputRef {
-            offset: self.input.start(),
-            input: &self.input,
-            errors: &mut self.errors,
-            state: &mut self.state,
-            ctx: &self.ctx,
-            #[cfg(feature = "memoization")]
-            memos: &mut self.memos,
-        }
+    /// Peek the next token in the input. Returns `None` if the end of the input has been reached.
+    ///
+    /// See [`InputRef::next_maybe`] for more information about what this function guarantees.
+    #[inline(always)]
+    pub fn peek_maybe(&self) -> Option<MaybeRef<'a, I::Token>> {
+        // SAFETY: offset was generated by previous call to `Input::next`
+        unsafe { self.input.next_maybe(self.offset).1.map(Into::into) }
     }
 
-    #[cfg(test)]
-    pub(crate) fn as_ref_at<'parse>(
-        &'parse mut self,
-        offset: I::Offset,
-    ) -> InputRef<'a, 'parse, I, E> {
-        InputRef {
-            offset,
-            input: &self.input,
-            errors: &mut self.errors,
-            state: &mut self.state,
-            ctx: &self.ctx,
-            #[cfg(feature = "memoization")]
-            memos: &mut self.memos,
+    /// Peek the token `n` tokens ahead in the input, without consuming anything, such that `peek_maybe_nth(0)` is
+    /// equivalent to [`InputRef::peek_maybe`]. Returns `None` if the end of the input is reached first.
+    ///
+    /// Like [`InputRef::peek_maybe`], this is available for all inputs. Each call walks the input from the current
+    /// position, so looking far ahead may be slow.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::{prelude::*, util::MaybeRef};
+    /// let lookahead = custom::<_, &str, _, extra::Default>(|inp| {
+    ///     Ok((inp.peek_maybe_nth(0).map(MaybeRef::into_inner), inp.peek_maybe_nth(2).map(MaybeRef::into_inner)))
+    /// });
+    ///
+    /// assert_eq!(lookahead.then_ignore(any().repeated()).parse("abc").into_result(), Ok((Some('a'), Some('c'))));
+    /// assert_eq!(lookahead.then_ignore(any().repeated()).parse("ab").into_result(), Ok((Some('a'), None)));
+    /// ```
+    #[inline]
+    pub fn peek_maybe_nth(&self, n: usize) -> Option<MaybeRef<'a, I::Token>> {
+        let mut offset = self.offset;
+        for _ in 0..n {
+            // SAFETY: `offset` is either the current offset or was generated by a previous call to `Input::next_maybe`
+            match unsafe { self.input.next_maybe(offset) } {
+                (next, Some(_)) => offset = next,
+                (_, None) => return None,
+            }
         }
+        // SAFETY: As above
+        unsafe { self.input.next_maybe(offset).1.map(Into::into) }
     }
 
-    pub(crate) fn into_errs(self) -> Vec<E::Error> {
-        self.errors
-            .secondary
-            .into_iter()
-            .map(|err| err.err)
-            .collect()
+    /// Peek the next token in the input. Returns `None` if the end of the input has been reached.
+    #[inline(always)]
+    pub fn peek(&self) -> Option<I::Token>
+    where
+        I: ValueInput<'a>,
+    {
+        // SAFETY: offset was generated by previous call to `Input::next`
+        unsafe { self.input.next(self.offset).1 }
     }
-}
-
-/// Internal type representing an input as well as all the necessary context for parsing.
-pub struct InputRef<'a, 'parse, I: Input<'a>, E: ParserExtra<'a, I>> {
-    pub(crate) offset: I::Offset,
-    pub(crate) input: &'parse I,
-    pub(crate) errors: &'parse mut Errors<I::Offset, E::Error>,
-    pub(crate) state: &'parse mut E::State,
-    pub(crate) ctx: &'parse E::Context,
-    #[cfg(feature = "memoization")]
-    pub(crate) memos: &'parse mut HashMap<(I::Offset, usize), Option<Located<I::Offset, E::Error>>>,
-}
 
-impl<'a, 'parse, I: Input<'a>, E: ParserExtra<'a, I>> InputRef<'a, 'parse, I, E> {
-    #[inline]
-    pub(crate) fn with_ctx<'sub_parse, C, O>(
-        &'sub_parse mut self,
-        new_ctx: &'sub_parse C,
-        f: impl FnOnce(&mut InputRef<'a, 'sub_parse, I, extra::Full<E::Error, E::State, C>>) -> O,
-    ) -> O
+    /// Peek the next token in the input. Returns `None` if the end of the input has been reached.
+    #[inline(always)]
+    pub fn peek_ref(&self) -> Option<&'a I::Token>
     where
-        'parse: 'sub_parse,
-        C: 'a,
+        I: BorrowInput<'a>,
     {
-        let mut new_inp = InputRef {
-            input: self.input,
-            offset: self.offset,
-            state: self.state,
-            ctx: new_ctx,
-            errors: self.errors,
-            #[cfg(feature = "memoization")]
-            memos: self.memos,
-        };
-        let res = f(&mut new_inp);
-        self.offset = new_inp.offset;
-        res
+        // SAFETY: offset was generated by previous call to `Input::next`
+        unsafe { self.input.next_ref(self.offset).1 }
     }
 
+    /// Peek the token immediately before the current position, without moving backwards. Returns `None` if the
+    /// current position is the start of the input.
+    ///
+    /// Together with [`InputRef::peek`], this can be used to build zero-width assertions that depend on the tokens
+    /// either side of the current position, such as [`InputRef::at_word_boundary`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let after_dot = custom::<_, &str, _, extra::Default>(|inp| Ok(inp.peek_prev() == Some('.')));
+    /// let parser = any().repeated().exactly(2).ignore_then(after_dot).then_ignore(any().repeated());
+    ///
+    /// assert_eq!(parser.parse("é.x").into_result(), Ok(true));
+    /// assert_eq!(parser.parse(".éx").into_result(), Ok(false));
+    /// ```
     #[inline]
-    pub(crate) fn with_input<'sub_parse, O>(
-        &'sub_parse mut self,
-        new_input: &'sub_parse I,
-        f: impl FnOnce(&mut InputRef<'a, 'sub_parse, I, E>) -> O,
-        #[cfg(feature = "memoization")] memos: &'sub_parse mut HashMap<
-            (I::Offset, usize),
-            Option<Located<I::Offset, E::Error>>,
-        >,
-    ) -> O
+    pub fn peek_prev(&self) -> Option<I::Token>
     where
-        'parse: 'sub_parse,
+        I: ValueInput<'a>,
     {
-        let mut new_inp = InputRef {
-            offset: new_input.start(),
-            input: new_input,
-            state: self.state,
-            ctx: self.ctx,
-            errors: self.errors,
-            #[cfg(feature = "memoization")]
-            memos,
-        };
-        f(&mut new_inp)
+        if self.offset <= self.input.start() {
+            return None;
+        }
+        let mut prev = I::prev(self.offset);
+        while !self.input.is_boundary(prev) {
+            prev = I::prev(prev);
+        }
+        // SAFETY: `prev` lies on a token boundary before the current offset
+        unsafe { self.input.next(prev).1 }
     }
 
-    /// Get the internal offset of the input at this moment in time.
+    /// Determine whether the current position is the start of the input, as with `^` in regular expressions.
     ///
-    /// Can be used for generating spans or slices. See [`InputRef::span`] and [`InputRef::slice`].
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let at_start = custom::<_, &str, _, extra::Default>(|inp| Ok(inp.at_start()));
+    ///
+    /// assert_eq!(at_start.then_ignore(any().repeated()).parse("ab").into_result(), Ok(true));
+    /// assert_eq!(any().ignore_then(at_start).then_ignore(any().repeated()).parse("ab").into_result(), Ok(false));
+    /// ```
     #[inline(always)]
-    pub fn offset(&self) -> Offset<'a, 'parse, I> {
-        Offset {
-            offset: self.offset,
-            phantom: PhantomData,
-        }
+    pub fn at_start(&self) -> bool {
+        self.offset == self.input.start()
     }
 
-    /// Save the current parse state as a [`Marker`].
+    /// Determine whether the current position is the end of the input, as with `$` in regular expressions.
     ///
-    /// You can rewind back to this state later with [`InputRef::rewind`].
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let at_end = custom::<_, &str, _, extra::Default>(|inp| Ok(inp.at_end()));
+    ///
+    /// assert_eq!(any().ignore_then(at_end).then_ignore(any().repeated()).parse("ab").into_result(), Ok(false));
+    /// assert_eq!(any().repeated().ignore_then(at_end).parse("ab").into_result(), Ok(true));
+    /// ```
     #[inline(always)]
-    pub fn save(&self) -> Marker<'a, 'parse, I> {
-        Marker {
-            offset: self.offset,
-            err_count: self.errors.secondary.len(),
-            phantom: PhantomData,
-        }
+    pub fn at_end(&self) -> bool {
+        self.peek_maybe().is_none()
     }
 
-    /// Reset the parse state to that represented by the given [`Marker`].
+    /// Determine whether the current position of a string-like input lies on a word boundary, without consuming
+    /// anything.
     ///
-    /// You can create a marker with which to perform rewinding using [`InputRef::save`].
-    #[inline(always)]
-    pub fn rewind(&mut self, marker: Marker<'a, 'parse, I>) {
-        self.errors.secondary.truncate(marker.err_count);
-        self.offset = marker.offset;
+    /// As with `\b` in regular expressions, a word boundary is a position where a word character (alphanumeric or
+    /// `_`) lies on one side but not the other. The start and end of the input count as non-word characters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let boundary = custom::<_, &str, _, extra::Default>(|inp| {
+    ///     if inp.at_word_boundary() {
+    ///         Ok(())
+    ///     } else {
+    ///         Err(Default::default())
+    ///     }
+    /// });
+    /// // Only match `in` when it is a whole word
+    /// let word_in = boundary.then(just("in")).then(boundary);
+    /// let parser = word_in.to(true).or(any().to(false)).repeated().collect::<Vec<_>>();
+    ///
+    /// assert_eq!(parser.parse("in").into_result(), Ok(vec![true]));
+    /// assert_eq!(parser.parse("pin").into_result(), Ok(vec![false; 3]));
+    /// ```
+    #[inline]
+    pub fn at_word_boundary<C: Char>(&self) -> bool
+    where
+        I: StrInput<'a, C>,
+    {
+        let is_word =
+            |c: Option<C>| c.is_some_and(|c| c.to_char().is_alphanumeric() || c.to_char() == '_');
+        is_word(self.peek_prev()) != is_word(self.peek())
     }
 
-    /// Get a mutable reference to the state associated with the current parse.
+    /// Peek the next character of a string-like input, paired with its byte offset. Returns `None` if the end of the
+    /// input has been reached.
+    ///
+    /// This mirrors [`str::char_indices`], and saves reconstructing byte positions from spans in text grammars. Within
+    /// [`Parser::map_with`], the same is available through [`MapExtra::char_index`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// // Collect the byte offset of every non-ASCII character in the input
+    /// let non_ascii = custom::<_, &str, _, extra::Default>(|inp| {
+    ///     let mut found = Vec::new();
+    ///     while let Some((idx, c)) = inp.char_index() {
+    ///         if !c.is_ascii() {
+    ///             found.push(idx);
+    ///         }
+    ///         inp.skip();
+    ///     }
+    ///     Ok(found)
+    /// });
+    ///
+    /// assert_eq!(non_ascii.parse("aé🙂b").into_result(), Ok(vec![1, 3]));
+    /// ```
     #[inline(always)]
-    pub fn state(&mut self) -> &mut E::State {
-        self.state
+    pub fn char_index<C: Char>(&self) -> Option<(usize, C)>
+    where
+        I: StrInput<'a, C>,
+    {
+        // SAFETY: offset was generated by previous call to `Input::next`
+        let tok = unsafe { self.input.next(self.offset).1 }?;
+        Some((self.offset, tok))
     }
 
-    /// Get a reference to the context fed to the current parser.
+    /// Peek the next `N` tokens in the input as an array, without consuming them. Returns `None` if fewer than `N`
+    /// tokens remain.
     ///
-    /// See [`ConfigParser::configure`] and [`Parser::then_with_ctx`] for more information about context-sensitive
-    /// parsing.
-    #[inline(always)]
-    pub fn ctx(&self) -> &E::Context {
-        self.ctx
+    /// This is useful for parsers that operate on fixed-size windows of tokens, since the array can be matched on
+    /// directly.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// // Count the number of times the input contains a run of three identical characters
+    /// let triples = custom::<_, &str, _, extra::Default>(|inp| {
+    ///     let mut count = 0;
+    ///     while let Some([a, b, c]) = inp.peek_array() {
+    ///         count += (a == b && b == c) as usize;
+    ///         inp.skip();
+    ///     }
+    ///     Ok(count)
+    /// });
+    ///
+    /// assert_eq!(triples.then_ignore(any().repeated()).parse("aaabbccccd").into_result(), Ok(3));
+    /// assert_eq!(triples.then_ignore(any().repeated()).parse("aa").into_result(), Ok(0));
+    /// ```
+    #[inline]
+    pub fn peek_array<const N: usize>(&self) -> Option<[I::Token; N]>
+    where
+        I: ValueInput<'a>,
+    {
+        let mut offset = self.offset;
+        let toks: [Option<I::Token>; N] = core::array::from_fn(|_| {
+            // SAFETY: `offset` is either the current offset or was generated by a previous call to `Input::next`
+            let (next, tok) = unsafe { self.input.next(offset) };
+            offset = next;
+            tok
+        });
+        // Tokens can only be missing at the end of the input, so checking the last is enough
+        if N > 0 && toks[N - 1].is_none() {
+            None
+        } else {
+            Some(toks.map(|tok| tok.expect("token should be present")))
+        }
     }
 
+    /// Peek a slice of up to `len` tokens from the current position of the input, without consuming them.
+    ///
+    /// If fewer than `len` tokens remain, the slice is clamped to the end of the input (and so may be empty).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let peek = custom::<_, &[u8], _, extra::Default>(|inp| {
+    ///     let _ = inp.next();
+    ///     Ok(inp.peek_slice(4))
+    /// });
+    ///
+    /// assert_eq!(peek.then_ignore(any().repeated()).parse(b"abcdefg").into_result(), Ok(&b"bcde"[..]));
+    /// assert_eq!(peek.then_ignore(any().repeated()).parse(b"abc").into_result(), Ok(&b"bc"[..]));
+    /// ```
     #[inline]
-    pub(crate) fn skip_while<F: FnMut(&I::Token) -> bool>(&mut self, mut f: F)
+    pub fn peek_slice(&self, len: usize) -> I::Slice
     where
-        I: ValueInput<'a>,
+        I: SliceInput<'a> + ExactSizeInput<'a>,
     {
-        loop {
-            // SAFETY: offset was generated by previous call to `Input::next`
-            let (offset, token) = unsafe { self.input.next(self.offset) };
-            if token.filter(&mut f).is_none() {
-                break;
-            } else {
-                self.offset = offset;
+        let mut end = self.offset;
+        for _ in 0..len {
+            // SAFETY: `end` is either the current offset or was generated by a previous call to `Input::next_maybe`
+            match unsafe { self.input.next_maybe(end) } {
+                (next, Some(_)) => end = next,
+                (_, None) => break,
             }
         }
+        self.slice_inner(self.offset..end)
     }
 
+    /// Skip the next token in the input.
     #[inline(always)]
-    pub(crate) fn next_inner(&mut self) -> (I::Offset, Option<I::Token>)
+    pub fn skip(&mut self)
     where
         I: ValueInput<'a>,
     {
-        // SAFETY: offset was generated by previous call to `Input::next`
-        let (offset, token) = unsafe { self.input.next(self.offset) };
-        self.offset = offset;
-        (self.offset, token)
+        let _ = self.next_inner();
     }
 
-    #[inline(always)]
-    pub(crate) fn next_maybe_inner(&mut self) -> (I::Offset, Option<I::TokenMaybe>) {
-        // SAFETY: offset was generated by previous call to `Input::next`
-        let (offset, token) = unsafe { self.input.next_maybe(self.offset) };
-        self.offset = offset;
-        (self.offset, token)
+    /// Skip the next `n` tokens in the input, stopping early if the end of the input is reached.
+    ///
+    /// This is the counterpart to [`InputRef::peek_slice`]: having inspected upcoming tokens in bulk, this can be used
+    /// to consume them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let keyword = custom::<_, &[u8], _, extra::Default>(|inp| {
+    ///     if inp.peek_slice(6) == b"return" {
+    ///         inp.advance(6);
+    ///         Ok(())
+    ///     } else {
+    ///         Err(Default::default())
+    ///     }
+    /// });
+    ///
+    /// assert!(keyword.parse(b"return").has_output());
+    /// assert!(keyword.parse(b"ret").has_errors());
+    /// ```
+    #[inline]
+    pub fn advance(&mut self, n: usize)
+    where
+        I: ValueInput<'a>,
+    {
+        for _ in 0..n {
+            if self.next_inner().1.is_none() {
+                break;
+            }
+        }
     }
 
-    #[inline(always)]
-    pub(crate) fn next_ref_inner(&mut self) -> (I::Offset, Option<&'a I::Token>)
+    /// Skip tokens until the next token is one of those in `sync` (leaving it unconsumed) or the end of the input is
+    /// reached, returning the span of the tokens that were skipped.
+    ///
+    /// This is the building block of panic-mode error recovery: after a parse error, the input is synchronised to the
+    /// next token in a 'follow set' (such as `;`, `}`, or `)`), and the returned span can be used to produce a single
+    /// diagnostic for the whole malformed region.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let garbage = custom::<_, &str, _, extra::Default>(|inp| Ok(inp.recover_to(&[';', '}'])));
+    ///
+    /// assert_eq!(garbage.then(any()).parse("1 + ;").into_result(), Ok((SimpleSpan::new(0, 4), ';')));
+    /// // Recovery stops at the end of the input if no synchronisation token is found
+    /// assert_eq!(garbage.parse("1 +").into_result(), Ok(SimpleSpan::new(0, 3)));
+    /// ```
+    #[inline]
+    pub fn recover_to(&mut self, sync: &[I::Token]) -> I::Span
     where
-        I: BorrowInput<'a>,
+        I: ValueInput<'a>,
+        I::Token: PartialEq,
     {
-        // SAFETY: offset was generated by previous call to `Input::next`
-        let (offset, token) = unsafe { self.input.next_ref(self.offset) };
-        self.offset = offset;
-        (self.offset, token)
+        let before = self.offset();
+        while let Some(tok) = self.peek() {
+            if sync.contains(&tok) {
+                break;
+            }
+            self.skip();
+        }
+        self.span_since(before)
     }
 
-    /// Attempt to parse this input using the given parser.
-    ///
-    /// # Important Notice
+    /// Get a slice of the input that covers the given offset range.
+    #[inline]
+    pub fn slice(&self, range: Range<Offset<'a, 'parse, I>>) -> I::Slice
+    where
+        I: SliceInput<'a>,
+    {
+        self.slice_inner(range.start.offset..range.end.offset)
+    }
+
+    /// Get a slice of the input that covers at most the first `max_len` offsets of the given offset range (bytes, for
+    /// string inputs), truncated to the last whole token that fits.
     ///
-    /// Parsers that return `Err(...)` are permitted to leave the input in an **unspecified** (but not
-    /// [undefined](https://en.wikipedia.org/wiki/Undefined_behavior)) state.
+    /// This is useful for rendering a short preview of a region of the input that may be very large, such as an
+    /// unterminated comment that extends to the end of the input. String inputs are never truncated in the middle of a
+    /// character. Only the tokens that fit are visited, so the cost does not depend on the size of the range.
     ///
-    /// The only well-specified action you are permitted to perform on the input after an error has occurred is
-    /// rewinding to a marker created *before* the error occurred via [`InputRef::rewind`].
+    /// # Examples
     ///
-    /// This state is not consistent between releases of chumsky, compilations of the final binary, or even invocations
-    /// of the parser. You should not rely on this state for anything, and choosing to rely on it means that your
-    /// parser may break in unexpected ways at any time.
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let preview = custom::<_, &str, _, extra::Default>(|inp| {
+    ///     let before = inp.offset();
+    ///     inp.skip_until(&'\n');
+    ///     Ok(inp.slice_clamped(before..inp.offset(), 4))
+    /// });
     ///
-    /// You have been warned.
-    pub fn parse<O, P: Parser<'a, I, O, E>>(&mut self, parser: P) -> Result<O, E::Error> {
-        match parser.go::<Emit>(self) {
-            Ok(out) => Ok(out),
-            Err(()) => Err(self.errors.alt.take().expect("error but no alt?").err),
+    /// assert_eq!(preview.parse("/* unterminated").into_result(), Ok("/* u"));
+    /// assert_eq!(preview.parse("/*").into_result(), Ok("/*"));
+    /// // `é` occupies two bytes, and does not fit
+    /// assert_eq!(preview.parse("abcé").into_result(), Ok("abc"));
+    /// ```
+    #[inline]
+    pub fn slice_clamped(&self, range: Range<Offset<'a, 'parse, I>>, max_len: usize) -> I::Slice
+    where
+        I: SliceInput<'a>,
+    {
+        let (start, limit) = (range.start.offset, range.end.offset);
+        let mut end = start;
+        while end < limit {
+            // SAFETY: `end` is either the start of the range or was generated by a previous call to `Input::next_maybe`
+            match unsafe { self.input.next_maybe(end) } {
+                (next, Some(_)) if next <= limit && next.into() - start.into() <= max_len => {
+                    end = next
+                }
+                _ => break,
+            }
         }
+        self.slice_inner(start..end)
     }
 
-    /// A check-only version of [`InputRef::parse`].
+    /// Get a slice of the input that covers the given offset range, or an error if the input can no longer produce it.
     ///
-    /// # Import Notice
+    /// This fails for ranges that the input cannot cover with a single slice, such as those that cross the boundary
+    /// between two chained inputs (see [`SliceInput::try_slice`]). Parsers that must work with such inputs should
+    /// prefer this to [`InputRef::slice`] and report a parse error on failure, as [`Parser::slice`] does.
     ///
-    /// See [`InputRef::parse`] about unspecified behaviour associated with this function.
-    pub fn check<O, P: Parser<'a, I, O, E>>(&mut self, parser: P) -> Result<(), E::Error> {
-        match parser.go::<Check>(self) {
-            Ok(()) => Ok(()),
-            Err(()) => Err(self.errors.alt.take().expect("error but no alt?").err),
-        }
-    }
-
-    /// Get the next token in the input. Returns `None` if the end of the input has been reached.
+    /// # Examples
     ///
-    /// This function is more flexible than either [`InputRef::next`] or [`InputRef::next_ref`] since it
-    /// only requires that the [`Input`] trait be implemented for `I` (instead of either [`ValueInput`] or
-    /// [`BorrowInput`]). However, that increased flexibility for the end user comes with a tradeoff for the
-    /// implementation: this function returns a [`MaybeRef<I::Token>`] that provides only a temporary reference to the
-    /// token.
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let word = custom::<_, &str, _, extra::Default>(|inp| {
+    ///     let before = inp.offset();
+    ///     inp.parse(text::ident())?;
+    ///     inp.try_slice(before..inp.offset()).map_err(|_| EmptyErr::default())
+    /// });
     ///
-    /// See [`InputRef::next_ref`] if you want get a reference to the next token instead.
-    #[inline(always)]
-    pub fn next_maybe(&mut self) -> Option<MaybeRef<'a, I::Token>> {
-        self.next_maybe_inner().1.map(Into::into)
+    /// assert_eq!(word.parse("hello").into_result(), Ok("hello"));
+    /// ```
+    #[inline]
+    pub fn try_slice(&self, range: Range<Offset<'a, 'parse, I>>) -> Result<I::Slice, SliceError>
+    where
+        I: SliceInput<'a>,
+    {
+        self.input.try_slice(range.start.offset..range.end.offset)
     }
 
-    /// Get the next token in the input by value. Returns `None` if the end of the input has been reached.
-    ///
-    /// See [`InputRef::next_ref`] if you want get a reference to the next token instead.
-    #[inline(always)]
-    pub fn next(&mut self) -> Option<I::Token>
+    /// Get a slice of the input that covers the given offset range.
+    #[inline]
+    pub fn slice_from(&self, range: RangeFrom<Offset<'a, 'parse, I>>) -> I::Slice
     where
-        I: ValueInput<'a>,
+        I: SliceInput<'a>,
     {
-        self.next_inner().1
+        self.slice_from_inner(range.start.offset..)
     }
 
-    /// Get a reference to the next token in the input. Returns `None` if the end of the input has been reached.
+    /// Get slices of the input that cover each of the given offset ranges, in order.
     ///
-    /// See [`InputRef::next`] if you want get the next token by value instead.
-    #[inline(always)]
-    pub fn next_ref(&mut self) -> Option<&'a I::Token>
+    /// This is equivalent to calling [`InputRef::slice`] for each range, but allows inputs for which random access is
+    /// expensive to obtain all of the slices more efficiently.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let words = custom::<_, &str, _, extra::Default>(|inp| {
+    ///     let mut ranges = Vec::new();
+    ///     loop {
+    ///         let before = inp.offset();
+    ///         inp.take_while_slice(|c: &char| c.is_alphabetic());
+    ///         ranges.push(before..inp.offset());
+    ///         if inp.next().is_none() {
+    ///             break Ok(inp.slices(&ranges));
+    ///         }
+    ///     }
+    /// });
+    ///
+    /// assert_eq!(words.parse("foo bar baz").into_result(), Ok(vec!["foo", "bar", "baz"]));
+    /// ```
+    #[inline]
+    pub fn slices(&self, ranges: &[Range<Offset<'a, 'parse, I>>]) -> Vec<I::Slice>
     where
-        I: BorrowInput<'a>,
+        I: SliceInput<'a>,
     {
-        self.next_ref_inner().1
+        let ranges = ranges
+            .iter()
+            .map(|range| range.start.offset..range.end.offset)
+            .collect::<Vec<_>>();
+        self.input.slices(&ranges)
     }
 
-    /// Peek the next token in the input. Returns `None` if the end of the input has been reached.
+    /// Get a slice of the whole input, from its start to its end, regardless of the current position.
     ///
-    /// See [`InputRef::next_maybe`] for more information about what this function guarantees.
-    #[inline(always)]
-    pub fn peek_maybe(&self) -> Option<MaybeRef<'a, I::Token>> {
-        // SAFETY: offset was generated by previous call to `Input::next`
-        unsafe { self.input.next_maybe(self.offset).1.map(Into::into) }
-    }
-
-    /// Peek the next token in the input. Returns `None` if the end of the input has been reached.
-    #[inline(always)]
-    pub fn peek(&self) -> Option<I::Token>
+    /// This is useful when global context is needed deep within a parser, such as when computing line numbers or
+    /// rendering a snippet of source far from the current position.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let line = custom::<_, &str, _, extra::Default>(|inp| {
+    ///     let before = inp.offset();
+    ///     Ok(inp.full_slice()[..before.as_usize()].lines().count())
+    /// });
+    ///
+    /// assert_eq!(just("a\nb\nc").ignore_then(line).parse("a\nb\nc").into_result(), Ok(3));
+    /// ```
+    #[inline]
+    pub fn full_slice(&self) -> I::Slice
     where
-        I: ValueInput<'a>,
+        I: SliceInput<'a>,
     {
-        // SAFETY: offset was generated by previous call to `Input::next`
-        unsafe { self.input.next(self.offset).1 }
+        self.input.slice_from(self.input.start()..)
     }
 
-    /// Peek the next token in the input. Returns `None` if the end of the input has been reached.
-    #[inline(always)]
-    pub fn peek_ref(&self) -> Option<&'a I::Token>
-    where
-        I: BorrowInput<'a>,
-    {
-        // SAFETY: offset was generated by previous call to `Input::next`
-        unsafe { self.input.next_ref(self.offset).1 }
+    /// Determine whether the given offset lies on a token boundary within the input.
+    ///
+    /// This is useful for validating offsets obtained from elsewhere (such as byte positions received from a language
+    /// server client) before using them. For string inputs, this checks that the offset lies on a UTF-8 character
+    /// boundary within the string. For slices, it checks that the offset is no greater than the slice's length. Inputs
+    /// that cannot cheaply determine their length, such as [`Stream`], always return `true`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let boundaries = custom::<_, &str, _, extra::Default>(|inp| {
+    ///     Ok((0..8).filter(|i| inp.is_boundary(*i)).collect::<Vec<_>>())
+    /// });
+    ///
+    /// assert_eq!(boundaries.then_ignore(any().repeated()).parse("aé€").into_result(), Ok(vec![0, 1, 3, 6]));
+    /// ```
+    #[inline]
+    pub fn is_boundary(&self, offset: I::Offset) -> bool {
+        self.input.is_boundary(offset)
     }
 
-    /// Skip the next token in the input.
-    #[inline(always)]
-    pub fn skip(&mut self)
+    /// Get a slice of the input that covers the given range of raw offsets, such as byte positions received from an
+    /// external tool.
+    ///
+    /// Unlike [`InputRef::slice`], the range is checked: `None` is returned if the range is reversed or either end does
+    /// not lie on a token boundary (see [`InputRef::is_boundary`]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let slices = custom::<_, &str, _, extra::Default>(|inp| {
+    ///     Ok([inp.slice_bytes(1..3), inp.slice_bytes(1..2), inp.slice_bytes(3..1), inp.slice_bytes(0..9)])
+    /// });
+    ///
+    /// assert_eq!(slices.then_ignore(any().repeated()).parse("aéb").into_result(), Ok([Some("é"), None, None, None]));
+    /// ```
+    #[inline]
+    pub fn slice_bytes(&self, range: Range<usize>) -> Option<I::Slice>
     where
-        I: ValueInput<'a>,
+        I: SliceInput<'a, Offset = usize>,
     {
-        let _ = self.next_inner();
+        self.check_bytes(&range).then(|| self.input.slice(range))
     }
 
-    /// Get a slice of the input that covers the given offset range.
+    /// Get a span over the input that covers the given range of raw offsets, such as byte positions received from an
+    /// external tool.
+    ///
+    /// Unlike [`InputRef::span`], the range is checked: `None` is returned if the range is reversed or either end does
+    /// not lie on a token boundary (see [`InputRef::is_boundary`]).
     #[inline]
-    pub fn slice(&self, range: Range<Offset<'a, 'parse, I>>) -> I::Slice
+    pub fn span_bytes(&self, range: Range<usize>) -> Option<I::Span>
     where
-        I: SliceInput<'a>,
+        I: Input<'a, Offset = usize>,
     {
-        self.slice_inner(range.start.offset..range.end.offset)
+        // SAFETY: Both ends of the range have been checked to lie on token boundaries within the input
+        self.check_bytes(&range)
+            .then(|| unsafe { self.input.span(range) })
     }
 
-    /// Get a slice of the input that covers the given offset range.
-    #[inline]
-    pub fn slice_from(&self, range: RangeFrom<Offset<'a, 'parse, I>>) -> I::Slice
+    #[inline(always)]
+    fn check_bytes(&self, range: &Range<usize>) -> bool
     where
-        I: SliceInput<'a>,
+        I: Input<'a, Offset = usize>,
     {
-        self.slice_from_inner(range.start.offset..)
+        range.start <= range.end
+            && self.input.is_boundary(range.start)
+            && self.input.is_boundary(range.end)
     }
 
     // TODO: Unofy with `InputRef::slice`
@@ -1056,6 +6759,23 @@ impl<'a, 'parse, I: Input<'a>, E: ParserExtra<'a, I>> InputRef<'a, 'parse, I, E>
         self.input.slice(range)
     }
 
+    /// Like [`InputRef::slice_inner`], but generates an error at the end of the range if the input cannot slice it.
+    #[inline(always)]
+    pub(crate) fn slice_or_fail(&mut self, range: Range<I::Offset>) -> Result<I::Slice, ()>
+    where
+        I: SliceInput<'a>,
+    {
+        match self.input.try_slice(range.start..range.end) {
+            Ok(slice) => Ok(slice),
+            Err(SliceError(())) => {
+                // SAFETY: the range was produced by offsets of this input
+                let span = unsafe { self.input.span(range.start..range.end) };
+                self.add_alt(range.end, None, None, span);
+                Err(())
+            }
+        }
+    }
+
     #[allow(dead_code)]
     #[inline(always)]
     pub(crate) fn slice_from_inner(&self, range: RangeFrom<I::Offset>) -> I::Slice
@@ -1065,7 +6785,6 @@ impl<'a, 'parse, I: Input<'a>, E: ParserExtra<'a, I>> InputRef<'a, 'parse, I, E>
         self.input.slice_from(range)
     }
 
-    #[cfg_attr(not(feature = "regex"), allow(dead_code))]
     #[inline(always)]
     pub(crate) fn slice_trailing_inner(&self) -> I::Slice
     where
@@ -1102,19 +6821,293 @@ impl<'a, 'parse, I: Input<'a>, E: ParserExtra<'a, I>> InputRef<'a, 'parse, I, E>
         unsafe { self.input.span(before.offset..self.offset) }
     }
 
+    /// Record the input between the given offset and the current offset as trivia, such as whitespace or comments that
+    /// have been skipped over. Empty ranges are not recorded.
+    ///
+    /// Trivia is only recorded when parsing with [`RecordTrivia`](crate::extra::RecordTrivia), and this function does
+    /// nothing otherwise. [`Parser::padded`] and [`Parser::padded_by`] record the trivia that they skip automatically.
+    #[inline]
+    pub fn record_trivia(&mut self, before: Offset<'a, 'parse, I>) {
+        if E::RECORD_TRIVIA && before.offset < self.offset {
+            self.trivia.push(before.offset..self.offset);
+        }
+    }
+
+    /// Get the spans of all trivia recorded since the given offset, in input order. See
+    /// [`RecordTrivia`](crate::extra::RecordTrivia) for more information.
+    ///
+    /// If trivia is not being recorded, this is always empty.
+    #[inline]
+    pub fn trivia_since(&self, before: Offset<'a, 'parse, I>) -> Vec<I::Span> {
+        if !E::RECORD_TRIVIA {
+            return Vec::new();
+        }
+        let count = self
+            .trivia
+            .iter()
+            .rev()
+            .take_while(|range| range.start >= before.offset)
+            .count();
+        self.trivia[self.trivia.len() - count..]
+            .iter()
+            // SAFETY: trivia is only ever recorded between offsets that were generated by the input
+            .map(|range| unsafe { self.input.span(range.clone()) })
+            .collect()
+    }
+
+    /// Attach a value to the given offset, such that it can be retrieved later with [`InputRef::notes_in`].
+    ///
+    /// This is a side-channel for metadata that belongs to a position in the input rather than to any particular
+    /// output, such as the place at which a doc comment ended so that it can be associated with the item that follows.
+    /// Notes attached by an alternative that is later backtracked out of are discarded along with it. Any number of
+    /// notes, of any types, may be attached to the same offset.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let doc = just::<_, _, extra::Err<Simple<char>>>("///")
+    ///     .ignore_then(any().and_is(just('\n').not()).repeated().map_slice(|s: &str| s.trim()))
+    ///     .padded();
+    ///
+    /// // Note each doc comment at the offset where it ends
+    /// let note_doc = custom(move |inp| {
+    ///     let text = inp.parse(&doc)?;
+    ///     let end = inp.offset();
+    ///     inp.note_at(end, text.to_string());
+    ///     Ok(())
+    /// });
+    ///
+    /// // Items collect the doc comments noted just before they start
+    /// let item = custom(|inp| {
+    ///     let start = inp.offset();
+    ///     let name = inp.parse(text::ident().padded())?;
+    ///     let docs = inp.notes_in::<String>(..=start).into_iter().cloned().collect::<Vec<_>>();
+    ///     Ok((name, docs))
+    /// });
+    ///
+    /// let file = note_doc.repeated().ignore_then(item);
+    /// assert_eq!(
+    ///     file.parse("/// Hello\n/// World\nfoo").into_result(),
+    ///     Ok(("foo", vec!["Hello".to_string(), "World".to_string()])),
+    /// );
+    /// ```
+    #[inline]
+    pub fn note_at<T: 'static>(&mut self, offset: Offset<'a, 'parse, I>, value: T) {
+        self.notes.push((offset.offset, Box::new(value)));
+    }
+
+    /// Get the values of type `T` attached to offsets within the given range with [`InputRef::note_at`], in the order
+    /// they were attached. Notes of other types are skipped.
+    #[inline]
+    pub fn notes_in<T: 'static>(&self, range: impl RangeBounds<Offset<'a, 'parse, I>>) -> Vec<&T> {
+        let bound = |bound: Bound<&Offset<'a, 'parse, I>>| match bound {
+            Bound::Included(offset) => Bound::Included(offset.offset),
+            Bound::Excluded(offset) => Bound::Excluded(offset.offset),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        let range = (bound(range.start_bound()), bound(range.end_bound()));
+        self.notes
+            .iter()
+            .filter(|(offset, _)| RangeBounds::contains(&range, offset))
+            .filter_map(|(_, value)| value.downcast_ref())
+            .collect()
+    }
+
+    /// Generate a span that covers all of the given offsets, extending from the earliest to the latest.
+    ///
+    /// This is useful for giving a single span to a construct made up of several children that are not necessarily
+    /// contiguous, or not stored in order. If no offsets are given, a zero-width span at the current input position is
+    /// returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let pair = custom::<_, &str, _, extra::Default>(|inp| {
+    ///     let mut offsets = Vec::new();
+    ///     inp.parse(just(' ').repeated())?;
+    ///     offsets.push(inp.offset());
+    ///     inp.parse(just("ab"))?;
+    ///     offsets.push(inp.offset());
+    ///     inp.parse(just(' ').repeated())?;
+    ///     Ok((inp.span_join(&offsets), inp.span_join(&[])))
+    /// });
+    ///
+    /// assert_eq!(pair.parse(" ab  ").into_result(), Ok((SimpleSpan::new(1, 3), SimpleSpan::new(5, 5))));
+    /// ```
+    #[inline]
+    pub fn span_join(&self, offsets: &[Offset<'a, 'parse, I>]) -> I::Span {
+        let start = offsets.iter().min().map_or(self.offset, |o| o.offset);
+        let end = offsets.iter().max().map_or(self.offset, |o| o.offset);
+        // SAFETY: `Offset` is invariant over 'parse, so we know that these offsets came from the same input
+        // See `https://plv.mpi-sws.org/rustbelt/ghostcell/`
+        unsafe { self.input.span(start..end) }
+    }
+
+    /// Count the number of tokens between two offsets: that is, the number of tokens that must be consumed to advance
+    /// from `from` to `to`. If `to` is not after `from`, this is zero.
+    ///
+    /// Unlike subtracting the raw offsets, this is correct for inputs with variable-width tokens, such as [`&str`]
+    /// (whose offsets are byte positions). Inputs that use token indices as offsets, such as slices, can compute this
+    /// without walking the input.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let distance = custom::<_, &str, _, extra::Default>(|inp| {
+    ///     let before = inp.offset();
+    ///     inp.parse(any().repeated())?;
+    ///     Ok(inp.token_distance(before, inp.offset()))
+    /// });
+    ///
+    /// assert_eq!(distance.parse("aé🙂").into_result(), Ok(3));
+    /// ```
+    #[inline]
+    pub fn token_distance(&self, from: Offset<'a, 'parse, I>, to: Offset<'a, 'parse, I>) -> usize {
+        // SAFETY: `Offset` is invariant over 'parse, so we know that these offsets came from the same input
+        // See `https://plv.mpi-sws.org/rustbelt/ghostcell/`
+        unsafe { self.input.token_distance(from.offset, to.offset) }
+    }
+
+    /// Get the spans of the first and last tokens within the given offset range, in that order.
+    ///
+    /// This is useful for diagnostics that want to label the two ends of a construct separately (such as the opening
+    /// and closing delimiters of a block) rather than the whole range. For a [`SpannedInput`], these are the spans of
+    /// the tokens themselves. If the range contains only a single token, both spans are the same. If the range is
+    /// empty, both spans are equal to [`InputRef::span`] over the range.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::{prelude::*, input::SpannedInput};
+    /// let tokens: &[(char, SimpleSpan)] = &[
+    ///     ('(', SimpleSpan::new(0, 1)),
+    ///     ('x', SimpleSpan::new(2, 3)),
+    ///     (')', SimpleSpan::new(5, 6)),
+    /// ];
+    /// let block = custom::<_, SpannedInput<char, SimpleSpan, &[(char, SimpleSpan)]>, _, extra::Default>(|inp| {
+    ///     let before = inp.offset();
+    ///     while inp.next().is_some() {}
+    ///     Ok(inp.endpoint_spans(before..inp.offset()))
+    /// });
+    ///
+    /// assert_eq!(
+    ///     block.parse(tokens.spanned(SimpleSpan::new(6, 6))).into_result(),
+    ///     Ok((SimpleSpan::new(0, 1), SimpleSpan::new(5, 6))),
+    /// );
+    /// ```
+    #[inline]
+    pub fn endpoint_spans(&self, range: Range<Offset<'a, 'parse, I>>) -> (I::Span, I::Span) {
+        let (start, end) = (range.start.offset, range.end.offset);
+        // SAFETY: `Offset` is invariant over 'parse, so we know that these offsets came from the same input, and
+        // offsets derived from them via `next_maybe` or `prev` within the range are valid for the same reason.
+        unsafe {
+            if start >= end {
+                (self.input.span(start..end), self.input.span(start..end))
+            } else {
+                let first_end = self.input.next_maybe(start).0;
+                // Tokens may span several offsets (such as multi-byte characters), so walk back to the last boundary
+                let mut last_start = I::prev(end);
+                while last_start > start && !self.input.is_boundary(last_start) {
+                    last_start = I::prev(last_start);
+                }
+                (
+                    self.input.span(start..first_end),
+                    self.input.span(last_start..end),
+                )
+            }
+        }
+    }
+
+    /// Attempt to match the given regex at the current position of the input, consuming and returning the matched
+    /// slice if successful.
+    ///
+    /// The regex is anchored to the current position: a match that begins later in the input is not considered.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let re = ::regex::Regex::new("[0-9]+").unwrap();
+    /// let digits = custom::<_, &str, _, extra::Default>(move |inp| {
+    ///     Ok(inp.try_match_regex(&re))
+    /// })
+    /// .then_ignore(any().repeated());
+    ///
+    /// assert_eq!(digits.parse("42abc").into_result(), Ok(Some("42")));
+    /// assert_eq!(digits.parse("abc42").into_result(), Ok(None));
+    /// ```
     #[cfg(feature = "regex")]
-    #[inline(always)]
-    pub(crate) fn skip_bytes<C>(&mut self, skip: usize)
+    #[inline]
+    pub fn try_match_regex<C>(&mut self, re: &C::Regex) -> Option<I::Slice>
+    where
+        C: Char,
+        I: StrInput<'a, C>,
+    {
+        let len = C::match_regex(re, self.slice_trailing_inner())?;
+        let before = self.offset;
+        self.skip_bytes(len);
+        Some(self.slice_inner(before..self.offset))
+    }
+
+    /// Get the remainder of the input, from the current offset to the end.
+    ///
+    /// Together with [`InputRef::skip_bytes`], this allows parts of the input to be handed to an external parser, such
+    /// as the standard library's (or a third-party crate's) parsing of floating point numbers, from within a
+    /// [`custom`] parser.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let float = custom::<_, &str, _, extra::Default>(|inp| {
+    ///     let rest = inp.rest_str();
+    ///     let len = rest
+    ///         .find(|c: char| !c.is_ascii_digit() && c != '.')
+    ///         .unwrap_or(rest.len());
+    ///     let x = rest[..len].parse::<f64>().map_err(|_| Default::default())?;
+    ///     inp.skip_bytes(len);
+    ///     Ok(x)
+    /// });
+    ///
+    /// assert_eq!(float.then_ignore(just('f')).parse("1.5f").into_result(), Ok(1.5));
+    /// assert!(float.parse("f").has_errors());
+    /// ```
+    #[inline]
+    pub fn rest_str<C>(&self) -> &'a C::Str
+    where
+        C: Char,
+        I: StrInput<'a, C>,
+    {
+        self.slice_trailing_inner()
+    }
+
+    /// Skip over the next `skip` bytes of the input, such as those consumed by an external parser that was given the
+    /// result of [`InputRef::rest_str`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the resulting offset would not lie on a token boundary (see [`InputRef::is_boundary`]), such as when
+    /// it would be past the end of the input or in the middle of a multi-byte character.
+    #[inline]
+    pub fn skip_bytes<C>(&mut self, skip: usize)
     where
         C: Char,
         I: StrInput<'a, C>,
     {
-        self.offset += skip;
+        let offset = self.offset + skip;
+        assert!(
+            self.input.is_boundary(offset),
+            "skipping {skip} bytes does not end on a token boundary",
+        );
+        self.set_offset(offset);
     }
 
     #[inline]
     pub(crate) fn emit(&mut self, pos: I::Offset, error: E::Error) {
-        self.errors.secondary.push(Located::at(pos, error));
+        self.errors.push_secondary(Located::at(pos, error));
     }
 
     #[inline]
@@ -1127,12 +7120,14 @@ impl<'a, 'parse, I: Input<'a>, E: ParserExtra<'a, I>> InputRef<'a, 'parse, I, E>
     ) {
         // Prioritize errors before choosing whether to generate the alt (avoids unnecessary error creation)
         self.errors.alt = Some(match self.errors.alt.take() {
-            Some(alt) => match alt.pos.into().cmp(&at.into()) {
+            Some(alt) => match alt.pos.cmp(&at) {
+                // Always merge so that the expected set is the same regardless of the order in which alternatives fail.
+                // At the end of input, the span of the most recent error is preferred.
                 Ordering::Equal => {
-                    if found.is_none() {
+                    if found.is_none() && !E::MERGE_EOF {
                         Located::at(
                             alt.pos,
-                            alt.err.replace_expected_found(expected, found, span),
+                            E::Error::expected_found(expected, found, span).merge(alt.err),
                         )
                     } else {
                         Located::at(alt.pos, alt.err.merge_expected_found(expected, found, span))
@@ -1151,7 +7146,7 @@ impl<'a, 'parse, I: Input<'a>, E: ParserExtra<'a, I>> InputRef<'a, 'parse, I, E>
     pub(crate) fn add_alt_err(&mut self, at: I::Offset, err: E::Error) {
         // Prioritize errors
         self.errors.alt = Some(match self.errors.alt.take() {
-            Some(alt) => match alt.pos.into().cmp(&at.into()) {
+            Some(alt) => match alt.pos.cmp(&at) {
                 Ordering::Equal => Located::at(alt.pos, alt.err.merge(err)),
                 Ordering::Greater => alt,
                 Ordering::Less => Located::at(at, err),
@@ -1161,16 +7156,129 @@ impl<'a, 'parse, I: Input<'a>, E: ParserExtra<'a, I>> InputRef<'a, 'parse, I, E>
     }
 }
 
+impl<'a, 'parse, Ctx, I, E> InputRef<'a, 'parse, WithContext<Ctx, I>, E>
+where
+    Ctx: Clone + 'a,
+    I: Input<'a>,
+    I::Span: Span<Context = ()>,
+    E: ParserExtra<'a, WithContext<Ctx, I>>,
+{
+    /// Run a closure against the inner, context-free input of a [`WithContext`], allowing parsers written for
+    /// plainly-spanned inputs to be reused within a parse that carries context. This is the inverse of
+    /// [`Input::with_context`].
+    ///
+    /// The closure shares the position, state, and context of this input, and the position it reaches is propagated
+    /// back out once it returns. Because the error type of the inner input differs from that of this input, any errors
+    /// left behind by the closure (including non-fatal errors emitted by recovery) are converted with `map_err` before
+    /// being added to the errors of this input. Errors that the closure handles itself, such as those returned by
+    /// [`InputRef::parse`], must be converted by the caller.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::{prelude::*, input::WithContext};
+    /// // A parser from elsewhere that knows nothing about file names
+    /// let ident = text::ident::<_, _, extra::Err<Rich<char>>>();
+    ///
+    /// let rewrap = |e: Rich<char>| Rich::custom(("main.rs", *e.span()), e);
+    /// let decl = just::<_, WithContext<&str, &str>, extra::Err<Rich<char, (&str, SimpleSpan)>>>("let ")
+    ///     .ignore_then(custom(move |inp| {
+    ///         inp.without_context(|inp| inp.parse(ident), rewrap)
+    ///             .map_err(rewrap)
+    ///     }));
+    ///
+    /// assert_eq!(decl.parse("let x".with_context("main.rs")).into_result(), Ok("x"));
+    /// let errs = decl.parse("let 1".with_context("main.rs")).into_errors();
+    /// assert_eq!(errs[0].span(), &("main.rs", (4..5).into()));
+    /// ```
+    pub fn without_context<Err2, O>(
+        &mut self,
+        f: impl FnOnce(&mut InputRef<'a, '_, I, extra::Full<Err2, E::State, E::Context>>) -> O,
+        mut map_err: impl FnMut(Err2) -> E::Error,
+    ) -> O
+    where
+        Err2: Error<'a, I> + 'a,
+    {
+        let mut errors = Errors::default();
+        #[cfg(feature = "memoization")]
+        let mut memos = Memos::default();
+        let mut new_inp = InputRef {
+            offset: self.offset,
+            input: &self.input.input,
+            errors: &mut errors,
+            trivia: self.trivia,
+            notes: self.notes,
+            #[cfg(feature = "fuel")]
+            fuel: self.fuel,
+            state: self.state,
+            ctx: self.ctx,
+            #[cfg(feature = "memoization")]
+            memos: &mut memos,
+            #[cfg(feature = "backtrack-stats")]
+            backtracks: self.backtracks,
+            #[cfg(feature = "consumption-log")]
+            log: self.log,
+            depth: self.depth,
+            depth_limit: self.depth_limit,
+            committed: self.committed,
+        };
+        let res = f(&mut new_inp);
+        // The inner input shares the consumption log, so its changes of position have already been recorded
+        self.offset = new_inp.offset;
+        self.committed = new_inp.committed;
+
+        for err in errors.take_secondary_since(0) {
+            self.emit(err.pos, map_err(err.err));
+        }
+        if let Some(alt) = errors.alt {
+            self.add_alt_err(alt.pos, map_err(alt.err));
+        }
+        res
+    }
+}
+
+impl<'a, 'parse, I, F, E> InputRef<'a, 'parse, WithKinds<I, F>, E>
+where
+    I: Input<'a>,
+    F: Fn(&I::Token) -> u8 + 'a,
+    E: ParserExtra<'a, WithKinds<I, F>>,
+{
+    /// Peek the kind of the next token in the input, as classified by the function given to [`Input::with_kinds`],
+    /// without consuming it. Returns `None` if the end of the input has been reached.
+    #[inline(always)]
+    pub fn peek_kind(&self) -> Option<u8> {
+        // SAFETY: offset was generated by previous call to `Input::next`
+        let tok = unsafe { self.input.input.next_maybe(self.offset).1 }?;
+        Some((self.input.classify)(tok.borrow()))
+    }
+}
+
+/// The outcome of running a parser over a separate input with [`InputRef::parse_nested`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubParse<O, E> {
+    /// The output of the parser, or the error that caused it to fail.
+    pub output: Result<O, E>,
+    /// The offset within the nested input that the parser reached. If the parser failed, this is the furthest offset
+    /// that it reached before failing (that is, just after the token that caused the failure).
+    pub offset: usize,
+    /// The non-fatal errors emitted by the parser, in the order they were emitted.
+    pub errors: Vec<E>,
+}
+
 /// Struct used in [`Parser::validate`] to collect user-emitted errors
-pub struct Emitter<E> {
+///
+/// The emitter also carries the span of the output being validated, which is used by [`Emitter::emit_here`].
+pub struct Emitter<E, S = SimpleSpan<usize>> {
     emitted: Vec<E>,
+    span: S,
 }
 
-impl<E> Emitter<E> {
+impl<E, S> Emitter<E, S> {
     #[inline]
-    pub(crate) fn new() -> Emitter<E> {
+    pub(crate) fn new(span: S) -> Emitter<E, S> {
         Emitter {
             emitted: Vec::new(),
+            span,
         }
     }
 
@@ -1179,9 +7287,97 @@ impl<E> Emitter<E> {
         self.emitted
     }
 
+    /// Get the span of the output being validated.
+    #[inline]
+    pub fn span(&self) -> &S {
+        &self.span
+    }
+
     /// Emit a non-fatal error
     #[inline]
     pub fn emit(&mut self, err: E) {
         self.emitted.push(err)
     }
+
+    /// Emit a non-fatal error with the given message, spanning the output being validated (see [`Emitter::span`]).
+    ///
+    /// This saves passing the same span around when validating a construct produces several errors. To give the error
+    /// a different span, use [`Emitter::emit_at`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let ident = text::ident::<_, _, extra::Err<Rich<char>>>().validate(|ident: &str, _, emitter| {
+    ///     if ident.len() > 4 {
+    ///         emitter.emit_here("identifier is too long");
+    ///     }
+    ///     if ident.starts_with('_') {
+    ///         emitter.emit_here("identifier must not start with an underscore");
+    ///     }
+    ///     ident
+    /// });
+    ///
+    /// let errs = ident.parse("_hello").into_errors();
+    /// assert_eq!(errs.len(), 2);
+    /// assert!(errs.iter().all(|e| e.span() == &SimpleSpan::new(0, 6)));
+    /// ```
+    #[inline]
+    pub fn emit_here<M: ToString>(&mut self, msg: M)
+    where
+        S: Clone,
+        E: crate::error::LabelledError<S>,
+    {
+        self.emit_at(self.span.clone(), msg)
+    }
+
+    /// Emit a non-fatal error with the given span and message.
+    ///
+    /// See [`Emitter::emit_here`] for an equivalent that uses the span of the output being validated.
+    #[inline]
+    pub fn emit_at<M: ToString>(&mut self, span: S, msg: M)
+    where
+        E: crate::error::LabelledError<S>,
+    {
+        self.emit(E::labelled(span, msg))
+    }
+
+    /// Emit a non-fatal error with a primary span and message, along with any number of secondary labels.
+    ///
+    /// See [`LabelledError`](crate::error::LabelledError) for more information.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let block = just::<_, _, extra::Err<Rich<char>>>('{')
+    ///     .map_with_span(|_, span| span)
+    ///     .then(any().filter(|c: &char| c.is_alphabetic()).repeated())
+    ///     .then(just('}').or_not())
+    ///     .validate(|((open, _), close), span: SimpleSpan, emitter| {
+    ///         if close.is_none() {
+    ///             emitter.emit_labeled(
+    ///                 SimpleSpan::new(span.end, span.end),
+    ///                 "unclosed block",
+    ///                 vec![(open, "opening brace here".to_string())],
+    ///             );
+    ///         }
+    ///     });
+    ///
+    /// let errs = block.parse("{abc").into_errors();
+    /// assert_eq!(errs.len(), 1);
+    /// assert_eq!(errs[0].span(), &SimpleSpan::new(4, 4));
+    /// assert_eq!(errs[0].labels().collect::<Vec<_>>(), vec![(&SimpleSpan::new(0, 1), "opening brace here")]);
+    /// ```
+    #[inline]
+    pub fn emit_labeled<M: ToString>(&mut self, span: S, msg: M, labels: Vec<(S, String)>)
+    where
+        E: crate::error::LabelledError<S>,
+    {
+        let mut err = E::labelled(span, msg);
+        for (span, msg) in labels {
+            err.add_label(span, msg);
+        }
+        self.emit(err)
+    }
 }
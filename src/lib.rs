@@ -91,7 +91,14 @@ pub mod prelude {
 }
 
 use crate::input::InputOwn;
-use alloc::{boxed::Box, rc::Rc, string::String, sync::Arc, vec, vec::Vec};
+use alloc::{
+    boxed::Box,
+    rc::Rc,
+    string::{String, ToString},
+    sync::Arc,
+    vec,
+    vec::Vec,
+};
 use core::{
     borrow::Borrow,
     cell::{Cell, RefCell, UnsafeCell},
@@ -111,15 +118,17 @@ use self::label::{LabelError, Labelled};
 use self::{
     combinator::*,
     container::*,
-    error::Error,
+    error::{Error, Located},
     extra::ParserExtra,
-    input::{BorrowInput, Emitter, ExactSizeInput, InputRef, SliceInput, StrInput, ValueInput},
+    input::{
+        BorrowInput, Emitter, ExactSizeInput, InputRef, MapExtra, SliceInput, StrInput, ValueInput,
+    },
     pratt::Pratt,
     prelude::*,
     primitive::Any,
     private::{
         Check, ConfigIterParserSealed, ConfigParserSealed, Emit, IPResult, IterParserSealed,
-        Located, MaybeUninitExt, Mode, PResult, ParserSealed, Sealed,
+        MaybeUninitExt, Mode, PResult, ParserSealed, Sealed,
     },
     recovery::{RecoverWith, Strategy},
     span::Span,
@@ -190,11 +199,95 @@ use sync::{DynParser, MaybeSync, RefC, RefW};
 pub struct ParseResult<T, E> {
     output: Option<T>,
     errs: Vec<E>,
+    fatal_offset: Option<usize>,
+    err_offsets: Vec<usize>,
+    #[cfg(feature = "fuel")]
+    out_of_fuel: bool,
+    backtrack_limit_exceeded: bool,
+    needs_more_input: bool,
+    dropped_errors: usize,
+    #[cfg(feature = "backtrack-stats")]
+    backtrack_stats: input::BacktrackStats,
+    #[cfg(feature = "consumption-log")]
+    consumption_log: input::ConsumptionLog,
 }
 
 impl<T, E> ParseResult<T, E> {
     pub(crate) fn new(output: Option<T>, errs: Vec<E>) -> ParseResult<T, E> {
-        ParseResult { output, errs }
+        ParseResult {
+            output,
+            errs,
+            fatal_offset: None,
+            err_offsets: Vec::new(),
+            #[cfg(feature = "fuel")]
+            out_of_fuel: false,
+            backtrack_limit_exceeded: false,
+            needs_more_input: false,
+            dropped_errors: 0,
+            #[cfg(feature = "backtrack-stats")]
+            backtrack_stats: input::BacktrackStats::default(),
+            #[cfg(feature = "consumption-log")]
+            consumption_log: input::ConsumptionLog::default(),
+        }
+    }
+
+    fn with_error_offsets(self, err_offsets: Vec<usize>) -> Self {
+        ParseResult {
+            err_offsets,
+            ..self
+        }
+    }
+
+    #[cfg(feature = "fuel")]
+    fn with_out_of_fuel(self, out_of_fuel: bool) -> Self {
+        ParseResult {
+            out_of_fuel,
+            ..self
+        }
+    }
+
+    fn with_backtrack_limit_exceeded(self, backtrack_limit_exceeded: bool) -> Self {
+        ParseResult {
+            backtrack_limit_exceeded,
+            ..self
+        }
+    }
+
+    fn with_needs_more_input(self, needs_more_input: bool) -> Self {
+        ParseResult {
+            needs_more_input,
+            ..self
+        }
+    }
+
+    fn with_dropped_errors(self, dropped_errors: usize) -> Self {
+        ParseResult {
+            dropped_errors,
+            ..self
+        }
+    }
+
+    fn with_fatal_offset(self, fatal_offset: Option<usize>) -> Self {
+        ParseResult {
+            fatal_offset,
+            ..self
+        }
+    }
+
+    #[cfg(feature = "backtrack-stats")]
+    fn with_backtrack_stats(self, backtrack_stats: input::BacktrackStats) -> Self {
+        ParseResult {
+            backtrack_stats,
+            ..self
+        }
+    }
+
+    #[cfg(feature = "consumption-log")]
+    fn with_consumption_log(self, consumption_log: input::ConsumptionLog) -> Self {
+        ParseResult {
+            consumption_log,
+            ..self
+        }
     }
 
     /// Whether this result contains output
@@ -217,6 +310,159 @@ impl<T, E> ParseResult<T, E> {
         self.errs.iter()
     }
 
+    /// Get the parse errors for this result, ordered by the input offset at which each was produced.
+    ///
+    /// The offsets are those given by [`ParseResult::errors_located`]. Errors are emitted at the offset that parsing
+    /// had reached when they were produced: in particular, an error that was recovered from is only emitted once
+    /// recovery has finished, and so is ordered after any errors emitted by the recovery itself. The sort is stable,
+    /// so errors produced at the same offset keep their emission order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let pair = any::<_, extra::Err<Rich<char>>>()
+    ///     .then(any())
+    ///     .validate(|out, span, emitter| {
+    ///         emitter.emit(Rich::custom(span, "odd pair"));
+    ///         out
+    ///     });
+    /// let parser = just('a')
+    ///     .then(just('b'))
+    ///     .recover_with(via_parser(pair))
+    ///     .then(pair);
+    ///
+    /// let res = parser.parse("xbcd");
+    /// assert_eq!(res.errors_located().map(|(at, _)| at).collect::<Vec<_>>(), [2, 2, 4]);
+    /// // The error produced by the recovery parser is emitted before the error that triggered recovery, at the same
+    /// // offset, so it stays first
+    /// assert_eq!(res.errors_sorted().map(|e| e.span().end).collect::<Vec<_>>(), [2, 1, 4]);
+    /// ```
+    pub fn errors_sorted(&self) -> impl ExactSizeIterator<Item = &E> {
+        debug_assert_eq!(self.err_offsets.len(), self.errs.len());
+        let mut order = (0..self.errs.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&i| self.err_offsets[i]);
+        order.into_iter().map(move |i| &self.errs[i])
+    }
+
+    /// Get the parse errors for this result, each paired with the raw input offset at which it was produced.
+    ///
+    /// This allows positions to be attached to errors that do not carry a span of their own (such as [`EmptyErr`],
+    /// or a small enum of error kinds), without having to make the error type larger. Offsets have the same meaning
+    /// as that returned by [`ParseResult::fatal_error_offset`]: for most inputs they are token indices and, for string
+    /// inputs, byte offsets.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::{prelude::*, error::EmptyErr};
+    /// let parser = just::<_, _, extra::Err<EmptyErr>>('a')
+    ///     .recover_with(via_parser(any()))
+    ///     .repeated()
+    ///     .collect::<String>();
+    ///
+    /// let res = parser.parse("abab");
+    /// assert_eq!(res.errors_located().map(|(offset, _)| offset).collect::<Vec<_>>(), [2, 4]);
+    /// ```
+    pub fn errors_located(&self) -> impl ExactSizeIterator<Item = (usize, &E)> {
+        self.err_offsets.iter().copied().zip(&self.errs)
+    }
+
+    /// If parsing failed, get the raw input offset at which the fatal error (always the last of
+    /// [`ParseResult::errors`]) was produced.
+    ///
+    /// This is the offset that the parser had reached when the error was generated, and is the same offset used to
+    /// prioritise between competing errors: when an unexpected token was found, it points just past that token. Unlike
+    /// the span carried by the error itself, this offset is unaffected by combinators that transform errors, making it
+    /// useful for correlating failures with source positions (for telemetry, caching, etc.). For most inputs this is a
+    /// token index or, for string inputs, a byte offset.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let digits = text::digits::<_, _, extra::Err<Simple<char>>>(10);
+    ///
+    /// assert_eq!(digits.parse("123x").fatal_error_offset(), Some(4));
+    /// assert_eq!(digits.parse("123").fatal_error_offset(), None);
+    /// ```
+    pub fn fatal_error_offset(&self) -> Option<usize> {
+        self.fatal_offset
+    }
+
+    /// Whether parsing failed because it ran out of fuel. See [`Parser::parse_with_fuel`].
+    #[cfg(feature = "fuel")]
+    pub fn ran_out_of_fuel(&self) -> bool {
+        self.out_of_fuel
+    }
+
+    /// Whether parsing failed because the parser tried to backtrack further than the input allows. See
+    /// [`Stream::with_backtrack_limit`](input::Stream::with_backtrack_limit).
+    pub fn exceeded_backtrack_limit(&self) -> bool {
+        self.backtrack_limit_exceeded
+    }
+
+    /// Whether parsing failed because the parser reached the end of the tokens available so far, before the input was
+    /// known to be complete. Parsing should be tried again once more tokens have arrived. See
+    /// [`GrowableBuffer`](input::GrowableBuffer).
+    pub fn needs_more_input(&self) -> bool {
+        self.needs_more_input
+    }
+
+    /// The number of errors that were emitted but not kept because the limit set with
+    /// [`MaxErrors`](extra::MaxErrors) had been reached.
+    pub fn dropped_errors(&self) -> usize {
+        self.dropped_errors
+    }
+
+    /// Get statistics about the backtracking that the parser performed, such as the number of times that it rewound
+    /// and how far.
+    ///
+    /// A grammar that backtracks excessively can exhibit quadratic (or worse) performance. These statistics can help
+    /// to identify such grammars, and to check whether refactoring or memoising (see [`Parser::memoised`]) particular
+    /// rules helps.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// // Both alternatives begin with the same digits, which must be parsed again when the first fails
+    /// let num = text::digits::<_, _, extra::Err<Simple<char>>>(10);
+    /// let parser = num.then_ignore(just('+')).or(num.then_ignore(just('-')));
+    ///
+    /// // The furthest rewind is from just after the `-` (which the first alternative rejected) back to the start
+    /// assert_eq!(parser.parse("1234-").backtrack_stats().deepest, 5);
+    /// ```
+    #[cfg(feature = "backtrack-stats")]
+    pub fn backtrack_stats(&self) -> input::BacktrackStats {
+        self.backtrack_stats
+    }
+
+    /// Get a log of every change of position in the input that was made while parsing, including both the consumption
+    /// of tokens and rewinds.
+    ///
+    /// Running the same parser over the same input again should produce an identical log. If it does not, the parser
+    /// is not deterministic, and [`input::ConsumptionLog::first_divergence`] can be used to find where the two runs
+    /// went their separate ways.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let parser = just::<_, _, extra::Err<Simple<char>>>("ab").or(just("ac"));
+    ///
+    /// let log = parser.parse("ac").consumption_log().clone();
+    /// // Consume `a` and then `c` (which is not the expected `b`), rewind, then consume `a` and `c` again
+    /// assert_eq!(log.transitions(), &[(0, 1), (1, 2), (2, 0), (0, 1), (1, 2)]);
+    ///
+    /// // Parsing again produces the same log
+    /// log.assert_replayed_by(parser.parse("ac").consumption_log());
+    /// ```
+    #[cfg(feature = "consumption-log")]
+    pub fn consumption_log(&self) -> &input::ConsumptionLog {
+        &self.consumption_log
+    }
+
     /// Convert this `ParseResult` into an option containing the output, if any exists
     pub fn into_output(self) -> Option<T> {
         self.output
@@ -228,6 +474,33 @@ impl<T, E> ParseResult<T, E> {
         self.errs
     }
 
+    /// Convert this `ParseResult` into a vector containing any errors, each [`Located`] at the raw input offset at
+    /// which it was produced. See [`ParseResult::errors_located`].
+    ///
+    /// This is useful for bespoke error post-processing, such as grouping errors by region of the input or collapsing
+    /// errors that were produced at the same position.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let parser = just::<_, _, extra::Err<Rich<char>>>('a')
+    ///     .recover_with(via_parser(any()))
+    ///     .repeated()
+    ///     .collect::<String>();
+    ///
+    /// let errs = parser.parse("abab").into_errors_located();
+    /// assert_eq!(errs.iter().map(|e| *e.pos()).collect::<Vec<_>>(), [2, 4]);
+    /// assert_eq!(errs[0].err().span(), &SimpleSpan::new(1, 2));
+    /// ```
+    pub fn into_errors_located(self) -> Vec<Located<usize, E>> {
+        self.err_offsets
+            .into_iter()
+            .zip(self.errs)
+            .map(|(pos, err)| Located::at(pos, err))
+            .collect()
+    }
+
     /// Convert this `ParseResult` into a tuple containing the output, if any existed, and errors, if any were
     /// encountered.
     pub fn into_output_errors(self) -> (Option<T>, Vec<E>) {
@@ -306,19 +579,139 @@ pub trait Parser<'a, I: Input<'a>, O, E: ParserExtra<'a, I> = extra::Default>:
         I: Input<'a>,
         E::Context: Default,
     {
+        let start = input.start();
+        self.parse_from_with_state(input, start, state)
+    }
+
+    /// Parse a stream of tokens, beginning at the given offset rather than at the start of the input.
+    ///
+    /// This is useful for incremental parsing: when a prefix of the input is known to be unchanged since a previous
+    /// parse, only the remainder need be parsed again. Offsets are not renumbered, so spans and error offsets (see
+    /// [`ParseResult::fatal_error_offset`]) continue to refer to positions within the whole input. For most inputs
+    /// the offset is a token index or, for string inputs, a byte offset.
+    ///
+    /// If you want to include non-default state, use [`Parser::parse_from_with_state`] instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the offset does not lie on a token boundary within the input (for example, if it is out of bounds
+    /// or, for string inputs, falls within a multi-byte character).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let word = text::ident::<_, _, extra::Err<Simple<char>>>().map_with_span(|word, span| (word, span));
+    /// let words = word.padded().repeated().collect::<Vec<_>>();
+    ///
+    /// // Skip over the first word, which has already been parsed
+    /// assert_eq!(
+    ///     words.parse_from("foo bar baz", 3).into_result(),
+    ///     Ok(vec![("bar", SimpleSpan::new(4, 7)), ("baz", SimpleSpan::new(8, 11))]),
+    /// );
+    /// assert_eq!(words.parse_from("foo bar 9", 3).fatal_error_offset(), Some(9));
+    /// ```
+    fn parse_from(&self, input: I, start: I::Offset) -> ParseResult<O, E::Error>
+    where
+        Self: Sized,
+        I: Input<'a>,
+        E::State: Default,
+        E::Context: Default,
+    {
+        self.parse_from_with_state(input, start, &mut E::State::default())
+    }
+
+    /// Parse a stream of tokens, beginning at the given offset rather than at the start of the input. The provided
+    /// state will be passed on to parsers that expect it, such as [`map_with_state`](Parser::map_with_state).
+    ///
+    /// See [`Parser::parse_from`] for more information.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the offset does not lie on a token boundary within the input.
+    fn parse_from_with_state(
+        &self,
+        input: I,
+        start: I::Offset,
+        state: &mut E::State,
+    ) -> ParseResult<O, E::Error>
+    where
+        Self: Sized,
+        I: Input<'a>,
+        E::Context: Default,
+    {
+        assert!(
+            start >= input.start() && input.is_boundary(start),
+            "parse start offset does not lie on a token boundary within the input",
+        );
+        InputOwn::new_state(input, state).run::<Emit, _, _>(self, start)
+    }
+
+    /// Parse a stream of tokens as with [`Parser::parse`], but stop once a limited amount of work has been performed.
+    ///
+    /// Each token read by the parser and each time that the parser backtracks consumes one unit of `fuel`. If the fuel
+    /// runs out, parsing stops and fails with an error at the position that had been reached, which can be detected
+    /// with [`ParseResult::ran_out_of_fuel`]. This is useful for bounding the work done when parsing untrusted input
+    /// (which may be crafted to make an ambiguous grammar backtrack excessively) without resorting to timeouts.
+    /// Running out of fuel never panics.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let digits = text::digits::<_, _, extra::Err<Simple<char>>>(10).slice();
+    ///
+    /// let res = digits.parse_with_fuel("12345", 100);
+    /// assert_eq!(res.into_result(), Ok("12345"));
+    ///
+    /// let res = digits.parse_with_fuel("12345", 3);
+    /// assert!(res.ran_out_of_fuel());
+    /// assert_eq!(res.fatal_error_offset(), Some(3));
+    /// ```
+    #[cfg(feature = "fuel")]
+    fn parse_with_fuel(&self, input: I, fuel: usize) -> ParseResult<O, E::Error>
+    where
+        Self: Sized,
+        I: Input<'a>,
+        E::State: Default,
+        E::Context: Default,
+    {
+        self.parse_with_fuel_and_state(input, &mut E::State::default(), fuel)
+    }
+
+    /// Parse a stream of tokens with the given state as with [`Parser::parse_with_state`], but stop once a limited
+    /// amount of work has been performed. See [`Parser::parse_with_fuel`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let count = any::<_, extra::Full<Simple<char>, usize, ()>>()
+    ///     .map_with_state(|_, _, n: &mut usize| *n += 1)
+    ///     .repeated()
+    ///     .collect::<Vec<_>>();
+    ///
+    /// let mut n = 0;
+    /// let res = count.parse_with_fuel_and_state("abcdef", &mut n, 3);
+    /// assert!(res.ran_out_of_fuel());
+    /// assert_eq!(n, 3);
+    /// ```
+    #[cfg(feature = "fuel")]
+    fn parse_with_fuel_and_state(
+        &self,
+        input: I,
+        state: &mut E::State,
+        fuel: usize,
+    ) -> ParseResult<O, E::Error>
+    where
+        Self: Sized,
+        I: Input<'a>,
+        E::Context: Default,
+    {
+        let start = input.start();
         let mut own = InputOwn::new_state(input, state);
-        let mut inp = own.as_ref_start();
-        let res = self.then_ignore(end()).go::<Emit>(&mut inp);
-        let alt = inp.errors.alt.take();
-        let mut errs = own.into_errs();
-        let out = match res {
-            Ok(out) => Some(out),
-            Err(()) => {
-                errs.push(alt.expect("error but no alt?").err);
-                None
-            }
-        };
-        ParseResult::new(out, errs)
+        own.fuel = input::Fuel::new(fuel);
+        own.run::<Emit, _, _>(self, start)
     }
 
     /// Parse a stream of tokens, ignoring any output, and returning any errors encountered along the way.
@@ -351,19 +744,8 @@ pub trait Parser<'a, I: Input<'a>, O, E: ParserExtra<'a, I> = extra::Default>:
         I: Input<'a>,
         E::Context: Default,
     {
-        let mut own = InputOwn::new_state(input, state);
-        let mut inp = own.as_ref_start();
-        let res = self.then_ignore(end()).go::<Check>(&mut inp);
-        let alt = inp.errors.alt.take();
-        let mut errs = own.into_errs();
-        let out = match res {
-            Ok(()) => Some(()),
-            Err(()) => {
-                errs.push(alt.expect("error but no alt?").err);
-                None
-            }
-        };
-        ParseResult::new(out, errs)
+        let start = input.start();
+        InputOwn::new_state(input, state).run::<Check, _, _>(self, start)
     }
 
     /// Map from a slice of the input based on the current parser's span to a value.
@@ -533,6 +915,92 @@ pub trait Parser<'a, I: Input<'a>, O, E: ParserExtra<'a, I> = extra::Default>:
         }
     }
 
+    /// Map the output of this parser to another value, making use of information about the input that it was produced
+    /// from, such as its span, the parser's state, or the first character and its byte offset. See [`MapExtra`] for
+    /// everything that is available.
+    ///
+    /// The output type of this parser is `U`, the same as the function's output.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// // Pair each word with the byte offset at which it begins
+    /// let words = any::<_, extra::Default>()
+    ///     .filter(|c: &char| c.is_alphabetic())
+    ///     .repeated()
+    ///     .at_least(1)
+    ///     .slice()
+    ///     .map_with(|word, e| (e.char_index::<char>().unwrap().0, word))
+    ///     .padded()
+    ///     .repeated()
+    ///     .collect::<Vec<_>>();
+    ///
+    /// assert_eq!(words.parse("héllo  world").into_result(), Ok(vec![(0, "héllo"), (8, "world")]));
+    /// ```
+    fn map_with<U, F: Fn(O, &mut MapExtra<'a, '_, '_, I, E>) -> U>(
+        self,
+        f: F,
+    ) -> MapWith<Self, O, F>
+    where
+        Self: Sized,
+    {
+        MapWith {
+            parser: self,
+            mapper: f,
+            phantom: EmptyPhantom::new(),
+        }
+    }
+
+    /// Map from a slice of the input based on the current parser's span to a value, making use of the parser's state
+    /// when doing so.
+    ///
+    /// This is the natural integration point for interning: identifiers can be mapped to small, cheaply-copied symbols
+    /// as they are parsed, using an interner stored in the parser's state.
+    ///
+    /// The output type of this parser is `U`, the same as the function's output.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// #[derive(Default)]
+    /// struct Interner<'a>(Vec<&'a str>);
+    ///
+    /// impl<'a> Interner<'a> {
+    ///     fn intern(&mut self, s: &'a str) -> usize {
+    ///         self.0.iter().position(|x| *x == s).unwrap_or_else(|| {
+    ///             self.0.push(s);
+    ///             self.0.len() - 1
+    ///         })
+    ///     }
+    /// }
+    ///
+    /// let ident = text::ident::<_, _, extra::Full<Simple<char>, Interner, ()>>()
+    ///     .map_slice_with_state(|ident, interner: &mut Interner| interner.intern(ident))
+    ///     .padded()
+    ///     .repeated()
+    ///     .collect::<Vec<_>>();
+    ///
+    /// let mut interner = Interner::default();
+    /// assert_eq!(ident.parse_with_state("foo bar foo baz bar", &mut interner).into_result(), Ok(vec![0, 1, 0, 2, 1]));
+    /// assert_eq!(interner.0, vec!["foo", "bar", "baz"]);
+    /// ```
+    fn map_slice_with_state<U, F: Fn(I::Slice, &mut E::State) -> U>(
+        self,
+        f: F,
+    ) -> MapSliceWithState<Self, O, F>
+    where
+        Self: Sized,
+        I: SliceInput<'a>,
+    {
+        MapSliceWithState {
+            parser: self,
+            mapper: f,
+            phantom: EmptyPhantom::new(),
+        }
+    }
+
     /// After a successful parse, apply a fallible function to the output. If the function produces an error, treat it
     /// as a parsing error.
     ///
@@ -686,6 +1154,19 @@ pub trait Parser<'a, I: Input<'a>, O, E: ParserExtra<'a, I> = extra::Default>:
         }
     }
 
+    /// Label this parser with the name of the type `T`, as given by [`core::any::type_name`].
+    ///
+    /// This is useful for parsers over type-erased tokens, such as those that downcast with [`select_ref!`], whose
+    /// errors would otherwise have no way to name the type that was expected.
+    #[cfg(feature = "label")]
+    fn labelled_type<T: ?Sized>(self) -> Labelled<Self, &'static str>
+    where
+        Self: Sized,
+        E::Error: LabelError<'a, I, &'static str>,
+    {
+        self.labelled(core::any::type_name::<T>())
+    }
+
     /// Parse one thing and then another thing, yielding a tuple of the two outputs.
     ///
     /// The output type of this parser is `(O, U)`, a combination of the outputs of both parsers.
@@ -869,14 +1350,11 @@ pub trait Parser<'a, I: Input<'a>, O, E: ParserExtra<'a, I> = extra::Default>:
     /// assert_eq!(successive_letters.parse(b"ab").into_result(), Ok(b'b')); // 'b' follows 'a'
     /// assert!(successive_letters.parse(b"ac").has_errors()); // 'c' does not follow 'a'
     /// ```
-    fn then_with_ctx<U, P>(
-        self,
-        then: P,
-    ) -> ThenWithCtx<Self, P, O, I, extra::Full<E::Error, E::State, O>>
+    fn then_with_ctx<U, P>(self, then: P) -> ThenWithCtx<Self, P, O, I, E>
     where
         Self: Sized,
         O: 'a,
-        P: Parser<'a, I, U, extra::Full<E::Error, E::State, O>>,
+        P: Parser<'a, I, U, E::WithContext<O>>,
     {
         ThenWithCtx {
             parser: self,
@@ -1161,6 +1639,9 @@ pub trait Parser<'a, I: Input<'a>, O, E: ParserExtra<'a, I> = extra::Default>:
     /// Input is eagerly parsed. Be aware that the parser will accept no occurences of the pattern too. Consider using
     /// [`Repeated::at_least`] instead if it better suits your use-case.
     ///
+    /// Each repetition is treated like an alternative: if the pattern fails after committing (see
+    /// [`InputRef::commit`](input::InputRef::commit)), the repetition fails too rather than stopping early.
+    ///
     /// The output type of this parser can be any [`Container`].
     ///
     /// # Examples
@@ -1342,6 +1823,39 @@ pub trait Parser<'a, I: Input<'a>, O, E: ParserExtra<'a, I> = extra::Default>:
         Rewind { parser: self }
     }
 
+    /// Limit the depth to which [`recursive`](recursive::recursive) parsers may nest within this parser.
+    ///
+    /// Deeply nested input (thousands of opening parentheses, for example) can otherwise overflow the stack, aborting
+    /// the process. With a limit in place, a recursive parser that would exceed it instead fails with an error at the
+    /// offending position, just as if it had encountered an unexpected token. This is recommended when parsing
+    /// untrusted input. If limits are nested, the most restrictive one applies.
+    ///
+    /// The output type of this parser is `O`, the same as the original parser.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::prelude::*;
+    /// let deep = "(".repeat(1000) + "x" + &")".repeat(1000);
+    ///
+    /// let nested = recursive::<_, _, extra::Err<Simple<char>>, _, _>(|nested| {
+    ///     nested.delimited_by(just('('), just(')')).or(just('x'))
+    /// })
+    /// .recursion_limit(8);
+    ///
+    /// assert_eq!(nested.parse("(((x)))").into_result(), Ok('x'));
+    /// assert!(nested.parse(&deep).has_errors());
+    /// ```
+    fn recursion_limit(self, limit: usize) -> RecursionLimit<Self>
+    where
+        Self: Sized,
+    {
+        RecursionLimit {
+            parser: self,
+            limit,
+        }
+    }
+
     /// Make the parser lazy, such that it parses as much as it validly can and then finished successfully, leaving
     /// trailing input untouched.
     ///
@@ -1550,7 +2064,7 @@ pub trait Parser<'a, I: Input<'a>, O, E: ParserExtra<'a, I> = extra::Default>:
     fn validate<U, F>(self, f: F) -> Validate<Self, O, F>
     where
         Self: Sized,
-        F: Fn(O, I::Span, &mut Emitter<E::Error>) -> U,
+        F: Fn(O, I::Span, &mut Emitter<E::Error, I::Span>) -> U,
     {
         Validate {
             parser: self,
@@ -2273,7 +2787,7 @@ where
 macro_rules! select {
     ($($p:pat $(= $span:ident)? $(if $guard:expr)? $(=> $out:expr)?),+ $(,)?) => ({
         $crate::primitive::select(
-            move |x, span| match x {
+            move |x, #[allow(unused_variables)] span| match x {
                 $($p $(if $guard)? => ::core::option::Option::Some({ $(let $span = span;)? () $(;$out)? })),+,
                 _ => ::core::option::Option::None,
             }
@@ -2285,12 +2799,68 @@ macro_rules! select {
 ///
 /// Useful if you want to extract elements from a token in a zero-copy manner.
 ///
-/// `select_ref` requires that the parser input implements [`BorrowInput`].
-#[macro_export]
+/// `select_ref` requires that the parser input implements [`BorrowInput`]. Inputs that only produce tokens by value,
+/// such as [`Stream`](input::Stream), do not implement it: use [`select!`] with them instead.
+///
+/// # Type-erased tokens
+///
+/// Since `select_ref` permits guards, it can be used to downcast type-erased tokens (such as those produced by a
+/// plugin system) by checking the type of the token in the guard. Error types like [`Rich`](error::Rich) require that
+/// tokens can be compared and displayed, which `Box<dyn Any>` cannot, so it is best to wrap such tokens in a type that
+/// compares them by [`TypeId`](core::any::TypeId) and displays the name of their type. Labelling a downcasting parser
+/// with the name of the type it expects (see `Parser::labelled_type`, which requires the `label` feature) then
+/// produces errors that name both the type expected and the type found.
+///
+/// ```
+/// # #[cfg(feature = "label")] {
+/// # use chumsky::prelude::*;
+/// use std::any::{type_name, Any};
+///
+/// #[derive(Debug)]
+/// struct Token {
+///     value: Box<dyn Any>,
+///     type_name: &'static str,
+/// }
+///
+/// impl Token {
+///     fn new<T: Any>(value: T) -> Self {
+///         Self { value: Box::new(value), type_name: type_name::<T>() }
+///     }
+/// }
+///
+/// impl PartialEq for Token {
+///     fn eq(&self, other: &Self) -> bool {
+///         // Note the dereference: otherwise, this would compare the `TypeId` of `Box<dyn Any>` itself
+///         (*self.value).type_id() == (*other.value).type_id()
+///     }
+/// }
+///
+/// impl std::fmt::Display for Token {
+///     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+///         write!(f, "{}", self.type_name)
+///     }
+/// }
+///
+/// fn downcast<'a, T: Any>() -> impl Parser<'a, &'a [Token], &'a T, extra::Err<Rich<'a, Token>>> + Clone {
+///     select_ref! { Token { value, .. } if value.is::<T>() => value.downcast_ref::<T>().unwrap() }
+///         .labelled_type::<T>()
+/// }
+///
+/// let valid = [Token::new(42i64), Token::new("hello".to_string())];
+/// let invalid = [Token::new(42i64), Token::new(7i64)];
+/// let pair = downcast::<i64>().then(downcast::<String>());
+///
+/// assert_eq!(pair.parse(&valid).into_result(), Ok((&42, &"hello".to_string())));
+///
+/// let errs = pair.parse(&invalid).into_errors();
+/// assert_eq!(errs[0].to_string(), "found 'i64' expected alloc::string::String");
+/// # }
+/// ```
+#[macro_export]
 macro_rules! select_ref {
     ($($p:pat $(= $span:ident)? $(if $guard:expr)? $(=> $out:expr)?),+ $(,)?) => ({
         $crate::primitive::select_ref(
-            move |x, span| match x {
+            move |x, #[allow(unused_variables)] span| match x {
                 $($p $(if $guard)? => ::core::option::Option::Some({ $(let $span = span;)? () $(;$out)? })),+,
                 _ => ::core::option::Option::None,
             }
@@ -2625,9 +3195,1601 @@ mod tests {
                 .parse("a+b+c");
         }
 
+        #[test]
+        #[should_panic]
+        #[cfg(debug_assertions)]
+        fn debug_assert_spanned_checked() {
+            let tokens = [('a', 0..1), ('b', 3..4), ('c', 2..3)];
+            any::<_, extra::Default>()
+                .repeated()
+                .parse(tokens.as_slice().spanned_checked(4..4));
+        }
+
         // TODO what about IterConfigure and TryIterConfigure?
     }
 
+    #[test]
+    fn isolated_errors() {
+        use self::prelude::*;
+
+        fn parser<'a>() -> impl Parser<'a, &'a str, Vec<char>, extra::Err<Rich<'a, char>>> {
+            let sub = just('a')
+                .recover_with(via_parser(any().to('?')))
+                .repeated()
+                .collect::<Vec<_>>();
+
+            custom(move |inp| {
+                inp.with_isolated_errors(|inp, errs| {
+                    let out = inp.parse(sub);
+                    errs.collect(inp);
+                    let count = errs.len();
+                    let pos = *errs[0].pos();
+                    errs.clear();
+                    errs.push(Located::at(
+                        pos,
+                        Rich::custom((0..4).into(), format!("{} errors", count)),
+                    ));
+                    out
+                })
+            })
+        }
+
+        let res = parser().parse("abba");
+        assert_eq!(res.output(), Some(&vec!['a', '?', '?', 'a']));
+        assert_eq!(
+            res.errors_located().map(|(at, _)| at).collect::<Vec<_>>(),
+            [2]
+        );
+        assert_eq!(res.errors().next().unwrap().to_string(), "2 errors");
+    }
+
+    #[test]
+    fn str_rope_edits() {
+        use self::input::StrRope;
+
+        let parser = any::<_, extra::Default>()
+            .repeated()
+            .collect::<String>()
+            .map_with_span(|s, span| (s, span));
+
+        let rope = StrRope::new("αβγ");
+        let rope = rope.with_edit(2..4, "xy");
+        assert_eq!(
+            parser.parse(rope.clone()).into_result(),
+            Ok(("αxyγ".to_string(), (0..6).into())),
+        );
+
+        let rope = rope.with_edit(0..0, "!").with_edit(3..7, "");
+        assert_eq!(
+            parser.parse(rope.clone()).into_result(),
+            Ok(("!α".to_string(), (0..3).into())),
+        );
+        assert!(rope.with_edit(0..3, "").is_empty());
+    }
+
+    #[test]
+    fn alt_expected_order_independent() {
+        use self::error::RichPattern;
+
+        fn expected<'a>(
+            parser: impl Parser<'a, &'a str, char, extra::Err<Rich<'a, char>>>,
+        ) -> Vec<RichPattern<'a, char>> {
+            let errs = parser.parse("").into_errors();
+            assert_eq!(errs.len(), 1);
+            let mut expected = errs[0].expected().cloned().collect::<Vec<_>>();
+            expected.sort_by_key(|pat| format!("{:?}", pat));
+            expected
+        }
+
+        let abc = expected(just('a').or(just('b')).or(just('c')));
+        let cba = expected(just('c').or(just('b').or(just('a'))));
+
+        assert_eq!(abc.len(), 3);
+        assert_eq!(abc, cba);
+    }
+
+    #[test]
+    fn errors_since() {
+        let item = just::<_, &str, extra::Err<Simple<char>>>('a').recover_with(via_parser(any()));
+        let parser = custom(move |inp| {
+            let before = inp.save();
+            inp.parse(item.repeated().collect::<String>())?;
+            Ok(inp.errors_since(before).len())
+        });
+
+        assert_eq!(parser.parse("aab").into_output(), Some(1));
+        assert_eq!(parser.parse("bbb").into_output(), Some(3));
+    }
+
+    #[test]
+    fn byte_set() {
+        const IDENT: ByteSet = ByteSet::range(b'a', b'z').union(ByteSet::new(b"_"));
+
+        assert_eq!(
+            IDENT.iter().collect::<Vec<_>>(),
+            b"_abcdefghijklmnopqrstuvwxyz".to_vec()
+        );
+
+        let ident = custom::<_, &[u8], _, extra::Default>(|inp| {
+            let mut len = 0;
+            while inp.next_if_in_set(&IDENT).is_some() {
+                len += 1;
+            }
+            Ok(len)
+        });
+
+        assert_eq!(
+            ident
+                .then_ignore(any().repeated())
+                .parse(b"foo_bar baz")
+                .into_result(),
+            Ok(7)
+        );
+
+        // Bytes consumed from a set use fuel like any other token
+        #[cfg(feature = "fuel")]
+        assert!(ident.parse_with_fuel(b"foo_bar", 3).ran_out_of_fuel());
+    }
+
+    #[test]
+    fn checkpoint_restore() {
+        let parser = custom::<_, &str, _, extra::Full<EmptyErr, i32, ()>>(|inp| {
+            let checkpoint = inp.checkpoint();
+            *inp.state() += 1;
+            let _ = inp.next();
+            let _ = inp.parse(just('!'));
+            inp.restore(checkpoint);
+            Ok(*inp.state())
+        })
+        .then(any().repeated().collect::<String>());
+
+        let mut state = 5;
+        assert_eq!(
+            parser.parse_with_state("ab", &mut state).into_result(),
+            Ok((5, "ab".to_string())),
+        );
+        assert_eq!(state, 5);
+    }
+
+    #[test]
+    fn state_scope_exit_on_panic() {
+        let parser = custom::<_, &str, _, extra::State<Vec<()>>>(|inp| {
+            inp.with_state_scope(
+                |scopes| scopes.push(()),
+                |scopes| {
+                    scopes.pop();
+                },
+                |_| -> Result<(), EmptyErr> { panic!("oh no") },
+            )
+        });
+
+        let mut scopes = Vec::new();
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            parser.parse_with_state("", &mut scopes)
+        }));
+        assert!(res.is_err());
+        assert!(scopes.is_empty());
+    }
+
+    #[test]
+    fn case_fold() {
+        let word = any::<_, extra::Err<Rich<char>>>()
+            .filter(|c: &char| !c.is_whitespace())
+            .repeated()
+            .at_least(1)
+            .collect::<String>()
+            .map_with_span(|folded, span| (folded, span));
+
+        assert_eq!(
+            word.parse("ΣİX".case_fold()).into_result(),
+            Ok(("σİx".to_string(), SimpleSpan::new(0, 5))),
+        );
+        assert_eq!(
+            any::<_, extra::Default>()
+                .repeated()
+                .slice()
+                .parse("ΣİX".case_fold())
+                .into_result(),
+            Ok("ΣİX"),
+        );
+    }
+
+    #[test]
+    fn recursion_limit() {
+        let nested = recursive::<_, _, extra::Err<Rich<char>>, _, _>(|nested| {
+            nested.delimited_by(just('('), just(')')).or(just('x'))
+        });
+
+        let errs = nested
+            .clone()
+            .recursion_limit(3)
+            .parse("((((x))))")
+            .into_errors();
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].span(), &SimpleSpan::new(3, 4));
+
+        // The most restrictive of nested limits applies
+        assert!(nested
+            .clone()
+            .recursion_limit(3)
+            .recursion_limit(10)
+            .parse("((((x))))")
+            .has_errors());
+        assert_eq!(
+            nested.recursion_limit(5).parse("((((x))))").into_result(),
+            Ok('x'),
+        );
+    }
+
+    #[test]
+    fn commit_cut() {
+        fn commit<'a>() -> impl Parser<'a, &'a str, (), extra::Err<EmptyErr>> + Clone {
+            custom(|inp| {
+                inp.commit();
+                Ok(())
+            })
+        }
+
+        let committed = just('a').then(commit()).then(just('b')).to(1);
+        let fallback = just('a').then(just('c')).to(2);
+
+        // A commit prevents later alternatives of the nearest choice from being tried
+        assert!(committed.clone().or(fallback).parse("ac").has_errors());
+        assert!(choice((committed.clone(), fallback))
+            .parse("ac")
+            .has_errors());
+        assert!(committed
+            .clone()
+            .or_not()
+            .then(just("ac"))
+            .parse("ac")
+            .has_errors());
+
+        // ...but only the nearest one
+        assert_eq!(
+            committed
+                .clone()
+                .or(fallback)
+                .or(fallback)
+                .parse("ac")
+                .into_result(),
+            Ok(2),
+        );
+
+        // A commit within a failed iteration of a repetition fails the repetition instead of ending it
+        let items = committed.clone().repeated().count().then_ignore(just("ac"));
+        assert!(items.parse("abac").has_errors());
+        let list = committed
+            .clone()
+            .separated_by(just(','))
+            .count()
+            .then_ignore(just(",ac"));
+        assert!(list.parse("ab,ac").has_errors());
+
+        // ...but not once the failed alternative has been dealt with
+        assert_eq!(
+            just('x').to(0).or(committed).parse("ab").into_result(),
+            Ok(1),
+        );
+    }
+
+    #[test]
+    fn tee_reports_committed_tokens() {
+        use core::cell::RefCell;
+
+        let commit = custom::<_, _, _, extra::Err<EmptyErr>>(|inp| {
+            inp.commit();
+            Ok(())
+        });
+        let seen = RefCell::new(String::new());
+        let input = || "abc".tee(|c: &char, _: SimpleSpan| seen.borrow_mut().push(*c));
+
+        // Tokens read by an abandoned alternative are not reported by a failed parse
+        let abandoned = just::<_, _, extra::Err<EmptyErr>>("abx")
+            .or(just("ab"))
+            .then(just('x'));
+        assert!(abandoned.parse(input()).has_errors());
+        assert_eq!(seen.take(), "");
+
+        // A commit reports the tokens consumed so far, even if parsing later fails
+        let committed = just::<_, _, extra::Err<EmptyErr>>('a')
+            .then(commit)
+            .then(just("bx"));
+        assert!(committed.parse(input()).has_errors());
+        assert_eq!(seen.take(), "a");
+
+        // A successful parse reports every token
+        let parser = just::<_, _, extra::Err<EmptyErr>>("abx")
+            .or(just("ab"))
+            .then(just('c'));
+        assert!(!parser.parse(input()).has_errors());
+        assert_eq!(seen.take(), "abc");
+    }
+
+    #[test]
+    fn tee_wrapped() {
+        use core::cell::RefCell;
+
+        fn commit<'a, I: Input<'a>>() -> impl Parser<'a, I, (), extra::Err<EmptyErr>> {
+            custom(|inp| {
+                inp.commit();
+                Ok(())
+            })
+        }
+
+        let seen = RefCell::new(String::new());
+        let input = |s: &'static str| s.tee(|c: &char, _: SimpleSpan| seen.borrow_mut().push(*c));
+
+        // Commits within either part of a chain are passed on to that part
+        let parser = just::<_, _, extra::Err<EmptyErr>>('a')
+            .then(commit())
+            .then(just("bc"))
+            .then(commit())
+            .then(just('x'));
+        assert!(parser.parse(input("ab").chain(input("cd"))).has_errors());
+        assert_eq!(seen.take(), "abc");
+
+        // A reversed input is only committed to once all of it has been
+        let parser = just::<_, _, extra::Err<EmptyErr>>('c')
+            .then(commit())
+            .then(just('x'));
+        assert!(parser.parse(input("abc").reversed()).has_errors());
+        assert_eq!(seen.take(), "");
+
+        let parser = just::<_, _, extra::Err<EmptyErr>>("cba");
+        assert!(!parser.parse(input("abc").reversed()).has_errors());
+        assert_eq!(seen.take(), "abc");
+    }
+
+    #[test]
+    fn spanned_multi_field_span() {
+        // A span carrying both line numbers and byte positions within its offsets
+        #[derive(Copy, Clone, Debug, PartialEq)]
+        struct Pos {
+            line: usize,
+            byte: usize,
+        }
+
+        #[derive(Clone, Debug, PartialEq)]
+        struct FileSpan {
+            file: u32,
+            start: Pos,
+            end: Pos,
+        }
+
+        impl Span for FileSpan {
+            type Context = u32;
+            type Offset = Pos;
+
+            fn new(file: u32, range: Range<Pos>) -> Self {
+                Self {
+                    file,
+                    start: range.start,
+                    end: range.end,
+                }
+            }
+            fn context(&self) -> u32 {
+                self.file
+            }
+            fn start(&self) -> Pos {
+                self.start
+            }
+            fn end(&self) -> Pos {
+                self.end
+            }
+        }
+
+        let pos = |line, byte| Pos { line, byte };
+        let span = |start, end| FileSpan::new(7, start..end);
+        let tokens = [
+            ('a', span(pos(1, 0), pos(1, 1))),
+            ('b', span(pos(2, 2), pos(2, 3))),
+            ('c', span(pos(3, 4), pos(3, 5))),
+        ];
+
+        let parser = any::<_, extra::Err<Simple<_, FileSpan>>>()
+            .repeated()
+            .exactly(2)
+            .map_with_span(|_, span| span)
+            .then(custom(|inp| {
+                let before = inp.offset();
+                inp.skip();
+                Ok(inp.span_since(before))
+            }));
+
+        assert_eq!(
+            parser
+                .parse(tokens.as_slice().spanned(span(pos(3, 5), pos(3, 5))))
+                .into_result(),
+            Ok((span(pos(1, 0), pos(2, 3)), span(pos(3, 4), pos(3, 5)))),
+        );
+    }
+
+    #[test]
+    fn is_boundary() {
+        fn boundaries<'a, I: ValueInput<'a>>(input: I) -> Vec<usize>
+        where
+            I::Offset: From<usize>,
+        {
+            custom::<_, I, _, extra::Default>(|inp| {
+                Ok((0..8)
+                    .filter(|i| inp.is_boundary((*i).into()))
+                    .collect::<Vec<_>>())
+            })
+            .lazy()
+            .parse(input)
+            .into_result()
+            .unwrap()
+        }
+
+        let rope = input::StrRope::new("aé").with_edit(3..3, "€");
+        assert_eq!(boundaries(rope), vec![0, 1, 3, 6]);
+        assert_eq!(boundaries(&[1, 2, 3][..]), vec![0, 1, 2, 3]);
+        assert_eq!(boundaries("€x".with_context(())), vec![0, 3, 4],);
+    }
+
+    #[test]
+    fn chain_slices_and_errors() {
+        use input::ChainPart;
+
+        let word = text::ident::<_, _, extra::Err<Rich<char, (ChainPart, SimpleSpan)>>>().padded();
+
+        assert_eq!(
+            word.repeated()
+                .collect::<Vec<_>>()
+                .parse("abc de ".chain("fg"))
+                .into_result(),
+            Ok(vec!["abc", "de", "fg"]),
+        );
+
+        // Errors in the second input are reported relative to its start
+        let errs = word
+            .repeated()
+            .collect::<Vec<_>>()
+            .then_ignore(end())
+            .parse("abc\n".chain("de !"))
+            .into_errors();
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].span(), &(ChainPart::Second, SimpleSpan::new(3, 4)));
+
+        // Slices may not cross the boundary between the inputs, which is reported as an error rather than a panic
+        let res = text::ident::<_, _, extra::Err<Simple<char, (ChainPart, SimpleSpan)>>>()
+            .parse("abc".chain("def"));
+        assert_eq!(res.output(), None);
+        assert_eq!(res.errors().len(), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "memoization")]
+    fn memo_get_put() {
+        let parser = custom::<_, &str, _, extra::Err<Simple<char>>>(|inp| {
+            let start = inp.save();
+            let before = inp.offset();
+            assert!(inp.memo_get::<char>(1).is_none());
+            let res = inp.parse(just('a'));
+            inp.memo_put(before, 1, res);
+
+            let after = inp.offset();
+            inp.rewind(start);
+            // A different key or output type finds nothing
+            assert!(inp.memo_get::<char>(2).is_none());
+            assert!(inp.memo_get::<u8>(1).is_none());
+            Ok((res, inp.memo_get::<char>(1), after == inp.offset()))
+        });
+
+        let (res, memo, same) = parser.parse("a").into_result().unwrap();
+        assert_eq!(memo, Some(res));
+        assert!(same);
+    }
+
+    #[test]
+    fn with_bidi_spans() {
+        use span::{Direction, DirectionalSpan};
+
+        let direction = |c: &char| match c {
+            '\u{0590}'..='\u{08FF}' => Some(Direction::Rtl),
+            c if c.is_alphabetic() => Some(Direction::Ltr),
+            _ => None,
+        };
+        let parser = any::<_, extra::Err<Rich<char, DirectionalSpan<SimpleSpan>>>>()
+            .repeated()
+            .map_with_span(|_, span: DirectionalSpan<SimpleSpan>| span);
+
+        // The first strongly-directional token decides the direction of the whole span
+        assert_eq!(
+            parser
+                .parse("1 שלום abc".with_bidi(direction))
+                .into_result(),
+            Ok(DirectionalSpan {
+                span: SimpleSpan::new(0, 14),
+                direction: Direction::Rtl,
+            }),
+        );
+        // Spans with no strongly-directional tokens default to left-to-right
+        let errs = just::<_, _, extra::Err<Rich<char, DirectionalSpan<SimpleSpan>>>>('a')
+            .parse("  ".with_bidi(direction))
+            .into_errors();
+        assert_eq!(errs[0].span().direction, Direction::Ltr);
+    }
+
+    #[test]
+    #[cfg(feature = "label")]
+    fn select_ref_downcast() {
+        use core::any::{type_name, Any};
+
+        #[derive(Debug)]
+        struct Token {
+            value: Box<dyn Any>,
+            type_name: &'static str,
+        }
+
+        impl Token {
+            fn new<T: Any>(value: T) -> Self {
+                Self {
+                    value: Box::new(value),
+                    type_name: type_name::<T>(),
+                }
+            }
+        }
+
+        impl fmt::Display for Token {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "{}", self.type_name)
+            }
+        }
+
+        impl PartialEq for Token {
+            fn eq(&self, other: &Self) -> bool {
+                (*self.value).type_id() == (*other.value).type_id()
+            }
+        }
+
+        fn downcast<'a, T: Any>(
+        ) -> impl Parser<'a, &'a [Token], &'a T, extra::Err<Rich<'a, Token>>> + Clone {
+            select_ref! { Token { value, .. } if value.is::<T>() => value.downcast_ref::<T>().unwrap() }
+                .labelled_type::<T>()
+        }
+
+        let valid = [Token::new(42i64), Token::new("hello".to_string())];
+        let invalid = [Token::new(42i64), Token::new(7i64)];
+
+        let pair = downcast::<i64>().then(downcast::<String>());
+
+        assert_eq!(
+            pair.parse(&valid).into_result(),
+            Ok((&42, &"hello".to_string()))
+        );
+
+        // The label gives the name of the expected type, and the token's display impl that of the type found
+        let errs = pair.parse(&invalid).into_errors();
+        assert_eq!(
+            errs[0].to_string(),
+            "found 'i64' expected alloc::string::String"
+        );
+    }
+
+    #[test]
+    fn eof_token() {
+        let digits = any::<_, extra::Err<Rich<char>>>()
+            .filter(char::is_ascii_digit)
+            .repeated()
+            .slice()
+            .then_ignore(just('\0'));
+
+        assert_eq!(
+            digits.parse("12".with_eof_token('\0')).into_result(),
+            Ok("12")
+        );
+
+        let errs = digits.parse("12x".with_eof_token('\0')).into_errors();
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].span(), &SimpleSpan::new(2, 3));
+
+        // Nothing may follow the synthetic token
+        let errs = digits
+            .then(just('\0'))
+            .parse("12".with_eof_token('\0'))
+            .into_errors();
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].found(), None);
+        assert_eq!(errs[0].span(), &SimpleSpan::new(2, 2));
+    }
+
+    #[test]
+    fn narrow_span_offsets() {
+        type Span = SimpleSpan<u32>;
+
+        let tokens = [
+            ('a', Span::new(0, 1)),
+            ('b', Span::new(2, 3)),
+            ('c', Span::new(4, 5)),
+        ];
+        let parser = just::<_, _, extra::Err<Rich<_, Span>>>('a')
+            .then(just('b'))
+            .map_with_span(|_, span: Span| span)
+            .then(any().repeated().map_with_span(|_, span: Span| span));
+
+        let input = tokens.as_slice().spanned(Span::new(5, 5));
+        assert_eq!(
+            parser.parse(input).into_result(),
+            Ok((Span::new(0, 3), Span::new(4, 5))),
+        );
+        assert_eq!(input.full_span(), Span::new(0, 5));
+
+        let spans = any::<_, extra::Err<Rich<_, Span>>>()
+            .map_with_span(|_, span: Span| span)
+            .repeated()
+            .collect::<Vec<_>>()
+            .parse(tokens.as_slice().spanned(Span::new(5, 5)).offset_by(10))
+            .into_result();
+        assert_eq!(
+            spans,
+            Ok(vec![
+                Span::new(10, 11),
+                Span::new(12, 13),
+                Span::new(14, 15)
+            ]),
+        );
+
+        let errs = just::<_, _, extra::Err<Rich<_, Span>>>('a')
+            .then(just('c'))
+            .parse(tokens.as_slice().spanned(Span::new(5, 5)))
+            .into_errors();
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].span(), &Span::new(2, 3));
+
+        let spans = any::<_, extra::Err<Rich<_, Span>>>()
+            .map_with_span(|_, span: Span| span)
+            .repeated()
+            .collect::<Vec<_>>()
+            .parse("aé".map_span(|span: SimpleSpan| Span::new(span.start as u32, span.end as u32)))
+            .into_result();
+        assert_eq!(spans, Ok(vec![Span::new(0, 1), Span::new(1, 3)]));
+    }
+
+    #[test]
+    fn parse_nested_partial() {
+        let digit = any::<_, extra::Err<Rich<char>>>()
+            .filter(|c: &char| c.is_ascii_digit())
+            .validate(|c, span, emitter| {
+                if c == '0' {
+                    emitter.emit(Rich::custom(span, "zero"));
+                }
+                c
+            });
+        let digits = digit.repeated().at_least(1).collect::<String>();
+
+        let parser = custom::<_, &str, _, extra::Err<Rich<char>>>(move |inp| {
+            let a = inp.parse_nested("10x", digits);
+            let b = inp.parse_nested("1x", just('1').then(just('2')));
+            Ok((a, b.offset, b.output.is_err()))
+        });
+
+        let (out, errs) = parser.parse("").into_output_errors();
+        let (a, b_offset, b_failed) = out.unwrap();
+        assert_eq!(a.output, Ok("10".to_string()));
+        assert_eq!(a.offset, 2);
+        assert_eq!(a.errors, vec![Rich::custom(SimpleSpan::new(1, 2), "zero")]);
+        assert_eq!((b_offset, b_failed), (2, true));
+        // Errors from the nested parse are not added to those of the outer parse
+        assert!(errs.is_empty());
+    }
+
+    #[test]
+    fn probe_leaves_errors_untouched() {
+        let parser = custom::<_, &str, _, extra::Err<Rich<char>>>(|inp| {
+            let before = inp.offset();
+            assert!(!inp.probe(just("abc")));
+            assert!(inp.probe(just("ab")));
+            assert!(inp.offset() == before);
+            inp.parse(just('z'))
+        });
+
+        let errs = parser.parse("abx").into_errors();
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].span(), &SimpleSpan::new(0, 1));
+    }
+
+    #[test]
+    fn errors_sorted_is_stable() {
+        let emit = |labels: &'static [&'static str]| {
+            any::<_, extra::Err<Rich<char>>>()
+                .then(any())
+                .validate(move |out, span, emitter| {
+                    for label in labels {
+                        emitter.emit(Rich::custom(span, label))
+                    }
+                    out
+                })
+        };
+        let item = just('a')
+            .then(just('b'))
+            .recover_with(via_parser(emit(&["a", "b"])));
+        let parser = item.then(emit(&["c"]));
+
+        let res = parser.parse("xbcd");
+        let labels =
+            |errs: Vec<&Rich<char>>| errs.into_iter().map(|e| e.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            labels(res.errors().collect()),
+            ["a", "b", "found 'x' expected 'a'", "c"]
+        );
+        // The recovered error is emitted at the offset where recovery finished, after those of the recovery parser
+        assert_eq!(
+            labels(res.errors_sorted().collect()),
+            ["a", "b", "found 'x' expected 'a'", "c"]
+        );
+    }
+
+    #[test]
+    fn word_boundaries() {
+        let boundaries = custom::<_, &str, _, extra::Default>(|inp| {
+            let mut found = Vec::new();
+            loop {
+                if inp.at_word_boundary() {
+                    found.push(inp.offset().offset);
+                }
+                if inp.next().is_none() {
+                    break Ok(found);
+                }
+            }
+        });
+
+        assert_eq!(boundaries.parse("").into_result(), Ok(vec![]));
+        assert_eq!(
+            boundaries.parse("a_1 é-b").into_result(),
+            Ok(vec![0, 3, 4, 6, 7, 8])
+        );
+    }
+
+    #[test]
+    fn parse_from_offset() {
+        let parser = any::<_, extra::Err<Simple<u8>>>()
+            .map_with_span(|tok, span: SimpleSpan| (tok, span.start))
+            .repeated()
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            parser.parse_from(&[1u8, 2, 3][..], 1).into_result(),
+            Ok(vec![(2, 1), (3, 2)])
+        );
+        assert_eq!(
+            parser.parse_from(&[1u8, 2, 3][..], 3).into_result(),
+            Ok(vec![])
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn parse_from_within_char() {
+        any::<_, extra::Default>().repeated().parse_from("é", 1);
+    }
+
+    #[test]
+    fn parse_from_stream() {
+        let parser = any::<_, extra::Err<Simple<u8>>>()
+            .repeated()
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            parser
+                .parse_from(input::Stream::from_iter([1u8, 2, 3]), 2)
+                .into_result(),
+            Ok(vec![3])
+        );
+        assert_eq!(
+            parser
+                .parse_from(input::Stream::from_iter([1u8, 2, 3]), 3)
+                .into_result(),
+            Ok(vec![])
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn parse_from_beyond_stream() {
+        any::<_, extra::Default>()
+            .repeated()
+            .parse_from(input::Stream::from_iter([1u8, 2, 3]), 4);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic = "without advancing its offset"]
+    fn non_advancing_input() {
+        struct Stuck;
+
+        impl Sealed for Stuck {}
+        impl<'a> Input<'a> for Stuck {
+            type Offset = usize;
+            type Token = char;
+            type Span = SimpleSpan;
+            type TokenMaybe = char;
+
+            fn start(&self) -> usize {
+                0
+            }
+
+            unsafe fn next_maybe(&self, offset: usize) -> (usize, Option<char>) {
+                (offset, Some('a'))
+            }
+
+            unsafe fn span(&self, range: Range<usize>) -> SimpleSpan {
+                range.into()
+            }
+
+            fn prev(offs: usize) -> usize {
+                offs.saturating_sub(1)
+            }
+
+            fn is_boundary(&self, offset: usize) -> bool {
+                offset == 0
+            }
+        }
+
+        impl<'a> ValueInput<'a> for Stuck {
+            unsafe fn next(&self, offset: usize) -> (usize, Option<char>) {
+                (offset, Some('a'))
+            }
+        }
+
+        any::<_, extra::Default>().repeated().parse(Stuck);
+    }
+
+    #[test]
+    fn end_at_sentinel() {
+        let parser = any::<_, extra::Default>()
+            .repeated()
+            .map_slice(|s: &[u8]| s);
+
+        assert_eq!(
+            parser
+                .parse((&b"ab\0cd"[..]).end_at_sentinel(0))
+                .into_result(),
+            Ok(&b"ab"[..])
+        );
+        assert_eq!(
+            parser
+                .parse((&b"abcd"[..]).end_at_sentinel(0))
+                .into_result(),
+            Ok(&b"abcd"[..])
+        );
+        assert_eq!(
+            parser.parse((&b"\0"[..]).end_at_sentinel(0)).into_result(),
+            Ok(&b""[..])
+        );
+    }
+
+    #[test]
+    fn token_distance() {
+        fn distances<'a, I: ValueInput<'a>>(input: I) -> (usize, usize) {
+            custom::<_, I, _, extra::Default>(|inp| {
+                let start = inp.offset();
+                inp.skip();
+                let mid = inp.offset();
+                inp.parse(any().repeated())?;
+                Ok((
+                    inp.token_distance(mid, inp.offset()),
+                    inp.token_distance(mid, start),
+                ))
+            })
+            .parse(input)
+            .into_output()
+            .unwrap()
+        }
+
+        assert_eq!(distances("aé🙂b"), (3, 0));
+        assert_eq!(distances(&[1, 2, 3, 4][..]), (3, 0));
+        assert_eq!(distances(stream::Stream::from_iter(0..4)), (3, 0));
+        assert_eq!(distances("aé🙂b".window(1, 7)), (1, 0));
+    }
+
+    #[test]
+    fn emitter_span() {
+        let parser = any::<_, extra::Err<Rich<char>>>().then(any()).validate(
+            |(a, b), span: SimpleSpan, emitter| {
+                assert_eq!(emitter.span(), &span);
+                emitter.emit_here("pair");
+                emitter.emit_at(SimpleSpan::new(span.end, span.end), "after pair");
+                (a, b)
+            },
+        );
+
+        let errs = parser.parse("ab").into_errors();
+        let errs = errs
+            .iter()
+            .map(|e| (e.to_string(), *e.span()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errs,
+            [
+                ("pair".to_string(), SimpleSpan::new(0, 2)),
+                ("after pair".to_string(), SimpleSpan::new(2, 2)),
+            ]
+        );
+
+        // Signatures that name the emitter without its span type still work
+        fn check(x: char, _: SimpleSpan, emitter: &mut input::Emitter<EmptyErr>) -> char {
+            if x == 'b' {
+                emitter.emit(EmptyErr::default());
+            }
+            x
+        }
+        let errs = any::<_, extra::Err<EmptyErr>>()
+            .validate(check)
+            .parse("b")
+            .into_errors();
+        assert_eq!(errs.len(), 1);
+    }
+
+    #[test]
+    fn endpoint_spans_str() {
+        let parser = custom::<_, &str, _, extra::Default>(|inp| {
+            let before = inp.offset();
+            while inp.next().is_some() {}
+            Ok(inp.endpoint_spans(before..inp.offset()))
+        });
+
+        // Multi-byte characters are never split
+        assert_eq!(
+            parser.parse("é").into_result(),
+            Ok((SimpleSpan::new(0, 2), SimpleSpan::new(0, 2))),
+        );
+        assert_eq!(
+            parser.parse("aé").into_result(),
+            Ok((SimpleSpan::new(0, 1), SimpleSpan::new(1, 3))),
+        );
+        assert_eq!(
+            parser.parse("€xß").into_result(),
+            Ok((SimpleSpan::new(0, 3), SimpleSpan::new(4, 6))),
+        );
+    }
+
+    #[test]
+    fn coalesce_str() {
+        let input = "a   b ".coalesce(|a: &char, b: &char| (*a == ' ' && *b == ' ').then_some(' '));
+        let parser = any::<_, extra::Default>()
+            .map_slice(|s: &str| s)
+            .repeated()
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            parser.parse(input).into_result(),
+            Ok(vec!["a", "   ", "b", " "])
+        );
+    }
+
+    #[test]
+    fn trivia_discarded_on_backtrack() {
+        let a = just::<_, _, extra::RecordTrivia<extra::Default>>('a').padded_by(just('-'));
+        let alts = a.then(just('x')).or(a.then(just('y')));
+        let parser = custom(move |inp| {
+            let before = inp.offset();
+            inp.parse(alts)?;
+            Ok(inp.trivia_since(before))
+        });
+
+        assert_eq!(
+            parser.parse("-a-y").into_result(),
+            Ok(vec![SimpleSpan::new(0, 1), SimpleSpan::new(2, 3)]),
+        );
+        assert_eq!(
+            custom::<_, &str, _, extra::Default>(|inp| {
+                let before = inp.offset();
+                inp.parse(just('a').padded())?;
+                Ok(inp.trivia_since(before))
+            })
+            .parse(" a ")
+            .into_result(),
+            Ok(vec![]),
+        );
+    }
+
+    #[test]
+    fn trivia_recorded_with_ctx() {
+        // The inner parser of `then_with_ctx` keeps the settings of the outer extra type
+        let digit = any::<_, extra::RecordTrivia<extra::Default>>().filter(char::is_ascii_digit);
+        let letters = just('a')
+            .padded()
+            .repeated()
+            .configure(|cfg, ctx: &char| cfg.exactly(ctx.to_digit(10).unwrap() as usize));
+        let parser = custom(move |inp| {
+            let before = inp.offset();
+            inp.parse(digit.then_with_ctx(letters))?;
+            Ok(inp.trivia_since(before))
+        });
+
+        assert_eq!(
+            parser.parse("2 a a").into_result(),
+            Ok(vec![SimpleSpan::new(1, 2), SimpleSpan::new(3, 4)]),
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "fuel")]
+    fn fuel_exhaustion() {
+        // Running out of fuel must not look like reaching the end of the input
+        let all = any::<_, extra::Err<Simple<char>>>().repeated();
+        let res = all.parse_with_fuel("abc", 2);
+        assert!(res.ran_out_of_fuel());
+        assert!(!res.has_output());
+        assert_eq!(res.fatal_error_offset(), Some(2));
+        assert!(!all.parse_with_fuel("abc", 100).ran_out_of_fuel());
+
+        // Backtracking consumes fuel too, bounding the work done by an exponential grammar
+        let deep = "(".repeat(30) + "x" + &")".repeat(30);
+        let expr = recursive::<_, _, extra::Err<Simple<char>>, _, _>(|expr| {
+            let atom = just('x')
+                .ignored()
+                .or(expr.delimited_by(just('('), just(')')));
+            atom.clone()
+                .then(just('+'))
+                .then(atom.clone())
+                .ignored()
+                .or(atom)
+        });
+        assert!(expr
+            .parse_with_fuel(deep.as_str(), 10_000)
+            .ran_out_of_fuel());
+
+        // Skipping tokens consumes fuel for each token skipped
+        let skip = custom::<_, &str, _, extra::Err<Simple<char>>>(|inp| {
+            inp.skip_until(&';');
+            Ok(())
+        })
+        .then(text::whitespace())
+        .then(any().repeated());
+        assert!(skip.parse_with_fuel("abcdef;", 3).ran_out_of_fuel());
+        assert!(skip.parse_with_fuel(";      x", 3).ran_out_of_fuel());
+        assert!(!skip.parse_with_fuel("abc;  x", 100).ran_out_of_fuel());
+    }
+
+    #[test]
+    fn require_prefix() {
+        use crate::input::PrefixError;
+
+        let parser = any::<_, extra::Default>()
+            .repeated()
+            .map_slice(|s: &[u8]| s);
+
+        let input = (&b"MAGICdata"[..]).require_prefix(b"MAGIC").unwrap();
+        assert_eq!(parser.parse(input).into_result(), Ok(&b"data"[..]));
+
+        let input = (&b"MAGIC"[..]).require_prefix(b"MAGIC").unwrap();
+        assert_eq!(parser.parse(input).into_result(), Ok(&b""[..]));
+
+        assert_eq!(
+            (&b"MAG"[..]).require_prefix(b"MAGIC").err(),
+            Some(PrefixError {
+                offset: 3,
+                index: 3
+            })
+        );
+        assert_eq!(
+            (&b"magic"[..]).require_prefix(b"MAGIC").err(),
+            Some(PrefixError {
+                offset: 0,
+                index: 0
+            })
+        );
+    }
+
+    #[test]
+    fn notes_discarded_on_backtrack() {
+        let noted = |c| {
+            custom::<_, &str, _, extra::Default>(move |inp| {
+                let before = inp.offset();
+                inp.parse(just(c))?;
+                inp.note_at(before, c);
+                Ok(())
+            })
+        };
+        let alts = noted('a')
+            .then(just('x'))
+            .or(noted('a').then(just('y')))
+            .then(noted('b'));
+        let parser = custom(move |inp| {
+            let before = inp.offset();
+            inp.parse(alts)?;
+            let notes = inp
+                .notes_in::<char>(before..)
+                .into_iter()
+                .copied()
+                .collect::<Vec<_>>();
+            // Notes of other types and outside of the range are not included
+            assert!(inp.notes_in::<u32>(..).is_empty());
+            assert_eq!(inp.notes_in::<char>(..=before), vec![&'a']);
+            Ok(notes)
+        });
+
+        assert_eq!(parser.parse("ayb").into_result(), Ok(vec!['a', 'b']));
+    }
+
+    #[test]
+    fn max_errors_discarded_on_backtrack() {
+        let xs = just::<_, _, extra::MaxErrors<extra::Err<Rich<char>>, 2>>('x')
+            .recover_with(via_parser(any().to('x')))
+            .repeated()
+            .exactly(3)
+            .then(just(';'))
+            .ignored();
+        let rest = any()
+            .repeated()
+            .validate(|_, span, emitter| emitter.emit(Rich::custom(span, "rest")));
+        let parser = xs.or(rest);
+
+        // The errors emitted by the first alternative are discarded with it, including the one that was dropped
+        let res = parser.parse("abc!");
+        assert_eq!(res.errors().count(), 1);
+        assert_eq!(res.dropped_errors(), 0);
+
+        let res = parser.parse("abc;");
+        assert_eq!(res.errors().count(), 2);
+        assert_eq!(res.dropped_errors(), 1);
+    }
+
+    #[test]
+    fn array_error_storage() {
+        type Storage<'a> = ArrayStorage<Located<usize, Rich<'a, char>>, 2>;
+
+        let xs = just::<_, _, extra::StoreErrors<extra::Err<Rich<char>>, Storage>>('x')
+            .recover_with(via_parser(any().to('x')))
+            .repeated()
+            .exactly(3)
+            .then(just(';'))
+            .ignored();
+        let rest = any()
+            .repeated()
+            .validate(|_, span, emitter| emitter.emit(Rich::custom(span, "rest")));
+        let parser = xs.or(rest);
+
+        let res = parser.parse("abc!");
+        assert_eq!(res.errors().count(), 1);
+        assert_eq!(res.dropped_errors(), 0);
+
+        let res = parser.parse("abc;");
+        assert_eq!(
+            res.errors().map(|e| e.span().start).collect::<Vec<_>>(),
+            [0, 1]
+        );
+        assert_eq!(res.dropped_errors(), 1);
+
+        // The storage is shared with the inner parser of `then_with_ctx`
+        let counted = any::<_, extra::StoreErrors<extra::Err<Rich<char>>, Storage>>()
+            .then_with_ctx(
+                just('x')
+                    .recover_with(via_parser(any().to('x')))
+                    .repeated()
+                    .configure(|cfg, ctx: &char| cfg.exactly(ctx.to_digit(10).unwrap() as usize)),
+            )
+            .ignored();
+        let res = counted.parse("4abcx");
+        assert_eq!(res.errors().count(), 2);
+        assert_eq!(res.dropped_errors(), 1);
+    }
+
+    #[test]
+    fn tab_width_columns() {
+        use span::LineCol;
+
+        let pos = |offset, line, column| LineCol {
+            offset,
+            line,
+            column,
+        };
+        let word = any::<_, extra::Err<Rich<char, SimpleSpan<LineCol>>>>()
+            .filter(|c: &char| !c.is_whitespace())
+            .repeated()
+            .at_least(1)
+            .map_with_span(|_, span: SimpleSpan<LineCol>| span)
+            .padded();
+
+        // Columns count characters rather than bytes, and tabs advance to the next tab stop
+        assert_eq!(
+            word.repeated()
+                .collect::<Vec<_>>()
+                .parse("é\tx\n \t\ty".with_tab_width(8))
+                .into_result(),
+            Ok(vec![
+                SimpleSpan::new(pos(0, 0, 0), pos(2, 0, 1)),
+                SimpleSpan::new(pos(3, 0, 8), pos(4, 0, 9)),
+                SimpleSpan::new(pos(8, 1, 16), pos(9, 1, 17)),
+            ]),
+        );
+
+        // Positions far into a long line are counted from the start of the line
+        let line = format!("{}\t{}\ty", "é".repeat(100), "x".repeat(200));
+        assert_eq!(
+            word.repeated()
+                .collect::<Vec<_>>()
+                .parse(line.as_str().with_tab_width(8))
+                .into_result(),
+            Ok(vec![
+                SimpleSpan::new(pos(0, 0, 0), pos(200, 0, 100)),
+                SimpleSpan::new(pos(201, 0, 104), pos(401, 0, 304)),
+                SimpleSpan::new(pos(402, 0, 312), pos(403, 0, 313)),
+            ]),
+        );
+
+        // The end of input is reported at the last line
+        let errs = word
+            .then(word)
+            .parse("\tab\n".with_tab_width(2))
+            .into_errors();
+        assert_eq!(errs[0].span(), &SimpleSpan::new(pos(4, 1, 0), pos(4, 1, 0)));
+    }
+
+    #[test]
+    fn reversed_input() {
+        // Multi-byte characters are yielded whole, and spans refer to the original input
+        let chars = any::<_, extra::Err<Rich<char>>>()
+            .map_with_span(|c, span: SimpleSpan| (c, span))
+            .repeated()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            chars.parse("aéb".reversed()).into_result(),
+            Ok(vec![
+                ('b', SimpleSpan::new(3, 4)),
+                ('é', SimpleSpan::new(1, 3)),
+                ('a', SimpleSpan::new(0, 1)),
+            ]),
+        );
+        assert_eq!(chars.parse("".reversed()).into_result(), Ok(vec![]));
+
+        // Errors point into the original input
+        let errs = just::<_, _, extra::Err<Rich<u8>>>(3)
+            .then(just(2))
+            .then(end())
+            .parse((&[1u8, 2, 3][..]).reversed())
+            .into_errors();
+        assert_eq!(errs[0].span(), &SimpleSpan::new(0, 1));
+    }
+
+    #[test]
+    fn skip_until_token() {
+        fn skipped<'a, I>(input: I, token: I::Token) -> usize
+        where
+            I: input::ValueInput<'a, Offset = usize>,
+            I::Token: PartialEq + Clone,
+        {
+            custom::<_, I, _, extra::Default>(move |inp| {
+                inp.skip_until(&token);
+                Ok(inp.offset().as_usize())
+            })
+            .lazy()
+            .parse(input)
+            .into_result()
+            .unwrap()
+        }
+
+        // Fast path for strings, including multi-byte characters
+        assert_eq!(skipped("abcé", 'é'), 3);
+        assert_eq!(skipped("abcé", 'z'), 5);
+        // Default path for other inputs
+        assert_eq!(skipped(&[1, 2, 3][..], 3), 2);
+        assert_eq!(skipped(&[1, 2, 3][..], 4), 3);
+        // The end of a window is respected
+        assert_eq!(skipped("abc\ndef".window(0, 2), '\n'), 2);
+    }
+
+    #[test]
+    fn rest_str_skip_bytes() {
+        let word = custom::<_, &str, _, extra::Default>(|inp| {
+            let rest = inp.rest_str();
+            let len = rest.find(' ').unwrap_or(rest.len());
+            inp.skip_bytes(len);
+            Ok(&rest[..len])
+        });
+        let parser = word.separated_by(just(' ')).collect::<Vec<_>>();
+
+        assert_eq!(
+            parser.parse("héllo wörld").into_result(),
+            Ok(vec!["héllo", "wörld"]),
+        );
+    }
+
+    #[test]
+    fn slice_clamped() {
+        let tokens = [1, 2, 3, 4, 5];
+        let preview = |max_len| {
+            any::<_, extra::Default>()
+                .ignore_then(custom(move |inp| {
+                    let before = inp.offset();
+                    inp.advance(3);
+                    Ok(inp.slice_clamped(before..inp.offset(), max_len))
+                }))
+                .then_ignore(any().repeated())
+        };
+
+        assert_eq!(preview(2).parse(&tokens[..]).into_result(), Ok(&[2, 3][..]));
+        assert_eq!(
+            preview(9).parse(&tokens[..]).into_result(),
+            Ok(&[2, 3, 4][..])
+        );
+        assert_eq!(preview(0).parse(&tokens[..]).into_result(), Ok(&[][..]));
+    }
+
+    #[test]
+    #[should_panic]
+    fn skip_bytes_within_char() {
+        let parser = custom::<_, &str, _, extra::Default>(|inp| {
+            inp.skip_bytes(1);
+            Ok(())
+        });
+        let _ = parser.parse("é");
+    }
+
+    #[test]
+    #[cfg(feature = "consumption-log")]
+    fn consumption_log_divergence() {
+        use core::cell::Cell;
+
+        // A parser that only tries its first alternative on the first run, so the second run never backtracks
+        let first_run = Cell::new(true);
+        let parser = custom::<_, &str, _, extra::Default>(|inp| {
+            let before = inp.save();
+            if first_run.replace(false) && inp.parse(just("ab")).is_ok() {
+                return Ok(());
+            }
+            inp.rewind(before);
+            inp.parse(just("a").then(just("c")).ignored())
+        });
+
+        let first = parser.parse("ac").consumption_log().clone();
+        let second = parser.parse("ac").consumption_log().clone();
+        assert_eq!(
+            first.transitions(),
+            &[(0, 1), (1, 2), (2, 0), (0, 1), (1, 2)]
+        );
+        assert_eq!(second.transitions(), &[(0, 1), (1, 2)]);
+        assert_eq!(first.first_divergence(&second), Some(2));
+        assert_eq!(first.first_divergence(&first), None);
+
+        // A log that is a prefix of another diverges where the shorter one ends
+        let prefix = just::<_, &str, extra::Default>('a')
+            .parse("a")
+            .consumption_log()
+            .clone();
+        assert_eq!(prefix.first_divergence(&second), Some(1));
+
+        let res = std::panic::catch_unwind(|| first.assert_replayed_by(&second));
+        assert!(res.is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "consumption-log")]
+    fn consumption_log_records_skips() {
+        let parser = custom::<_, &str, _, extra::Default>(|inp| {
+            inp.skip_until(&';');
+            inp.next();
+            Ok(())
+        })
+        .then(text::whitespace())
+        .then(just('x'));
+
+        let res = parser.parse("ab;  x");
+        assert_eq!(
+            res.consumption_log().transitions()[..4],
+            [(0, 2), (2, 3), (3, 4), (4, 5)]
+        );
+    }
+
+    #[test]
+    fn normalize_newlines() {
+        let tokens = any::<_, extra::Default>()
+            .repeated()
+            .collect::<String>()
+            .parse("a\r\nb\rc\n\r\r\n".normalize_newlines())
+            .into_result();
+        assert_eq!(tokens, Ok("a\nb\nc\n\n\n".to_string()));
+
+        // Spans and slices refer to the original text
+        let parser = just::<_, _, extra::Err<Rich<char>>>('a')
+            .ignore_then(just('\n').map_with_span(|_, span: SimpleSpan| span))
+            .then(any().repeated().slice());
+        assert_eq!(
+            parser
+                .parse("a\r\nb\r\n".normalize_newlines())
+                .into_result(),
+            Ok((SimpleSpan::new(1, 3), "b\r\n")),
+        );
+
+        // Looking behind a `\r\n` sees a single `\n`
+        let prev = custom::<_, _, _, extra::Default>(|inp| Ok(inp.peek_prev()));
+        let parser = just("a\n").ignore_then(prev).then_ignore(any().repeated());
+        assert_eq!(
+            parser.parse("a\r\nb".normalize_newlines()).into_result(),
+            Ok(Some('\n')),
+        );
+    }
+
+    #[test]
+    fn line_break_tokens() {
+        const BREAK: char = '\u{1E}';
+
+        let tokens = any::<_, extra::Default>()
+            .repeated()
+            .collect::<String>()
+            .parse("é\nb\n".with_line_breaks(BREAK))
+            .into_result();
+        assert_eq!(tokens, Ok("é\n\u{1E}b\n\u{1E}".to_string()));
+
+        // The synthetic token has a zero-width span after the newline, and is never part of a slice
+        let parser = just::<_, _, extra::Err<Rich<char>>>("é\n")
+            .ignore_then(just(BREAK).map_with_span(|_, span: SimpleSpan| span))
+            .then(any().repeated().slice());
+        assert_eq!(
+            parser.parse("é\nb\n".with_line_breaks(BREAK)).into_result(),
+            Ok((SimpleSpan::new(3, 3), "b\n")),
+        );
+
+        // Looking behind sees the synthetic token only once it has been consumed
+        let prev = custom::<_, _, _, extra::Default>(|inp| Ok(inp.peek_prev()));
+        let parser = just("é\n")
+            .ignore_then(prev)
+            .then_ignore(just(BREAK))
+            .then(prev)
+            .then_ignore(any().repeated());
+        assert_eq!(
+            parser.parse("é\nb".with_line_breaks(BREAK)).into_result(),
+            Ok((Some('\n'), Some(BREAK))),
+        );
+        let parser = just("é").ignore_then(prev).then_ignore(any().repeated());
+        assert_eq!(
+            parser.parse("é\nb".with_line_breaks(BREAK)).into_result(),
+            Ok(Some('é'))
+        );
+    }
+
+    #[test]
+    fn errors_located() {
+        #[derive(Debug, PartialEq)]
+        enum Kind {
+            Odd,
+            Other,
+        }
+
+        impl<'a> Error<'a, &'a str> for Kind {
+            fn expected_found<Iter: IntoIterator<Item = Option<MaybeRef<'a, char>>>>(
+                _: Iter,
+                _: Option<MaybeRef<'a, char>>,
+                _: SimpleSpan,
+            ) -> Self {
+                Kind::Other
+            }
+        }
+
+        let digit = any::<_, extra::Err<Kind>>()
+            .filter(char::is_ascii_digit)
+            .validate(|c, _, emitter| {
+                if c.to_digit(10).unwrap() % 2 == 1 {
+                    emitter.emit(Kind::Odd);
+                }
+                c
+            });
+        let parser = digit.repeated().collect::<String>();
+
+        let res = parser.parse("2345x");
+        assert_eq!(res.errors().count(), 3);
+        assert_eq!(
+            res.into_errors_located(),
+            [
+                Located::at(2, Kind::Odd),
+                Located::at(4, Kind::Odd),
+                Located::at(5, Kind::Other)
+            ],
+        );
+    }
+
+    #[test]
+    fn append_tokens() {
+        let tokens = [1, 2, 3];
+        let trailer = [8, 9];
+
+        // Appended tokens follow the input, with zero-width spans at its end
+        let parser = any::<_, extra::Err<Rich<i32>>>()
+            .map_with_span(|tok, span: SimpleSpan| (tok, span))
+            .repeated()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            parser
+                .parse(tokens.as_slice().append_tokens(&trailer))
+                .into_result(),
+            Ok(vec![
+                (1, SimpleSpan::new(0, 1)),
+                (2, SimpleSpan::new(1, 2)),
+                (3, SimpleSpan::new(2, 3)),
+                (8, SimpleSpan::new(3, 3)),
+                (9, SimpleSpan::new(3, 3)),
+            ]),
+        );
+
+        // Slices within either region come from that region
+        let parser = any::<_, extra::Default>()
+            .repeated()
+            .exactly(3)
+            .slice()
+            .then(any().repeated().slice());
+        assert_eq!(
+            parser
+                .parse(tokens.as_slice().append_tokens(&trailer))
+                .into_result(),
+            Ok((&[1, 2, 3][..], &[8, 9][..])),
+        );
+
+        // Slices that cross into the appended tokens are reported as an error rather than a panic
+        let parser = any::<_, extra::Default>().repeated().slice();
+        assert!(parser
+            .parse(tokens.as_slice().append_tokens(&trailer))
+            .has_errors());
+
+        // Parsing stops once the appended tokens run out
+        let parser = any::<_, extra::Err<Rich<i32>>>().repeated().exactly(6);
+        let errs = parser
+            .parse(tokens.as_slice().append_tokens(&trailer))
+            .into_errors();
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].found(), None);
+        assert_eq!(errs[0].span(), &SimpleSpan::new(3, 3));
+    }
+
+    #[test]
+    fn peek_kind() {
+        let tokens = [10u32, 21, 32];
+        let kinds = custom::<_, _, _, extra::Default>(|inp| {
+            let mut kinds = Vec::new();
+            loop {
+                let kind = inp.peek_kind();
+                kinds.push(kind);
+                if kind.is_none() {
+                    break Ok(kinds);
+                }
+                inp.next();
+            }
+        });
+
+        assert_eq!(
+            kinds
+                .parse(tokens.as_slice().with_kinds(|tok| (tok % 10) as u8))
+                .into_result(),
+            Ok(vec![Some(0), Some(1), Some(2), None]),
+        );
+    }
+
+    #[test]
+    fn spanned_empty_eoi() {
+        let tokens: [(char, SimpleSpan<usize>); 0] = [];
+        let eoi = SimpleSpan::new(0, 42);
+
+        let errs = just::<_, _, extra::Err<Rich<char>>>('a')
+            .parse(tokens.as_slice().spanned(eoi))
+            .into_errors();
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].span(), &eoi);
+
+        let span = empty::<_, extra::Default>()
+            .map_with_span(|_, span| span)
+            .parse(tokens.as_slice().spanned(eoi))
+            .into_result();
+        assert_eq!(span, Ok(eoi));
+    }
+
+    #[test]
+    fn spanned_checked() {
+        let tokens = [('a', 0..1), ('b', 2..4), ('c', 4..4)];
+        let parser = any::<_, extra::Default>()
+            .repeated()
+            .collect::<String>()
+            .map_with_span(|s, span| (s, span));
+
+        assert_eq!(
+            parser
+                .parse(tokens.as_slice().spanned_checked(4..4))
+                .into_result(),
+            Ok(("abc".to_string(), 0..4)),
+        );
+    }
+
     #[test]
     #[should_panic]
     fn recursive_define_twice() {
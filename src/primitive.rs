@@ -611,7 +611,7 @@ impl<'a, I, O, E, A, F, Ctx> ParserSealed<'a, I, O, E> for MapCtx<A, F>
 where
     I: Input<'a>,
     E: ParserExtra<'a, I>,
-    A: Parser<'a, I, O, extra::Full<E::Error, E::State, Ctx>>,
+    A: Parser<'a, I, O, E::WithContext<Ctx>>,
     F: Fn(&E::Context) -> Ctx,
     Ctx: 'a,
 {
@@ -800,16 +800,16 @@ macro_rules! impl_choice_for_tuple {
 
                 let Choice { parsers: ($Head, $($X,)*), .. } = self;
 
-                match $Head.go::<M>(inp) {
-                    Ok(out) => return Ok(out),
-                    Err(()) => inp.rewind(before),
+                if let Some(res) = inp.go_alt(|inp| $Head.go::<M>(inp)) {
+                    return res;
                 }
+                inp.rewind(before);
 
                 $(
-                    match $X.go::<M>(inp) {
-                        Ok(out) => return Ok(out),
-                        Err(()) => inp.rewind(before),
+                    if let Some(res) = inp.go_alt(|inp| $X.go::<M>(inp)) {
+                        return res;
                     }
+                    inp.rewind(before);
                 )*
 
                 Err(())
@@ -852,16 +852,13 @@ where
             Err(())
         } else {
             let before = inp.save();
-            match self.parsers.iter().find_map(|parser| {
-                inp.rewind(before);
-                match parser.go::<M>(inp) {
-                    Ok(out) => Some(out),
-                    Err(()) => None,
-                }
-            }) {
-                Some(out) => Ok(out),
-                None => Err(()),
-            }
+            self.parsers
+                .iter()
+                .find_map(|parser| {
+                    inp.rewind(before);
+                    inp.go_alt(|inp| parser.go::<M>(inp))
+                })
+                .unwrap_or(Err(()))
         }
     }
 
@@ -1,18 +1,5 @@
 use super::*;
 
-#[derive(Clone)]
-pub(crate) struct Located<T, E> {
-    pub(crate) pos: T,
-    pub(crate) err: E,
-}
-
-impl<T, E> Located<T, E> {
-    #[inline]
-    pub fn at(pos: T, err: E) -> Self {
-        Self { pos, err }
-    }
-}
-
 /// The result of calling [`Parser::go`]
 pub(crate) type PResult<M, O> = Result<<M as Mode>::Output<O>, ()>;
 /// The result of calling [`IterParser::next`]
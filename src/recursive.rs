@@ -187,6 +187,34 @@ fn recurse<R, F: FnOnce() -> R>(f: F) -> R {
     f()
 }
 
+/// Invoke a recursive parser, failing instead if doing so would exceed the recursion limit. See
+/// [`Parser::recursion_limit`].
+#[inline]
+fn go_limited<'a, I, O, E, M>(
+    inp: &mut InputRef<'a, '_, I, E>,
+    f: impl FnOnce(&mut InputRef<'a, '_, I, E>) -> PResult<M, O>,
+) -> PResult<M, O>
+where
+    I: Input<'a>,
+    E: ParserExtra<'a, I>,
+    M: Mode,
+{
+    if inp.depth >= inp.depth_limit {
+        // The limit is checked before anything is consumed, so the next token is only looked at to report it
+        let at = inp.offset;
+        // SAFETY: the offset was generated by the input
+        let (next, found) = unsafe { inp.input.next_maybe(at) };
+        // SAFETY: both offsets were generated by the input
+        let span = unsafe { inp.input.span(at..next) };
+        inp.add_alt(at, None, found.map(Into::into), span);
+        return Err(());
+    }
+    inp.depth += 1;
+    let res = recurse(|| f(inp));
+    inp.depth -= 1;
+    res
+}
+
 impl<'a, 'b, I, O, E> ParserSealed<'a, I, O, E> for Recursive<Indirect<'a, 'b, I, O, E>>
 where
     I: Input<'a>,
@@ -194,7 +222,7 @@ where
 {
     #[inline]
     fn go<M: Mode>(&self, inp: &mut InputRef<'a, '_, I, E>) -> PResult<M, O> {
-        recurse(move || {
+        go_limited::<_, _, _, M>(inp, move |inp| {
             M::invoke(
                 self.parser()
                     .inner
@@ -216,7 +244,7 @@ where
 {
     #[inline]
     fn go<M: Mode>(&self, inp: &mut InputRef<'a, '_, I, E>) -> PResult<M, O> {
-        recurse(move || M::invoke(&*self.parser(), inp))
+        go_limited::<_, _, _, M>(inp, move |inp| M::invoke(&*self.parser(), inp))
     }
 
     go_extra!(O);
@@ -25,13 +25,8 @@ where
     #[inline]
     fn go<M: Mode>(&self, inp: &mut InputRef<'a, '_, I, E>) -> PResult<M, &'a C::Str> {
         let before = inp.offset();
-        match C::match_regex(&self.regex, inp.slice_trailing_inner()) {
-            Some(len) => {
-                let before = inp.offset();
-                inp.skip_bytes(len);
-                let after = inp.offset();
-                Ok(M::bind(|| inp.slice_inner(before.offset..after.offset)))
-            }
+        match inp.try_match_regex(&self.regex) {
+            Some(slice) => Ok(M::bind(|| slice)),
             None => {
                 // TODO: Improve error
                 inp.add_alt(inp.offset().offset, None, None, inp.span_since(before));
@@ -11,6 +11,13 @@ use super::*;
 /// Spans typically consist of some context, such as the file they originated from, and a start/end offset. Spans are
 /// permitted to overlap one-another. The end offset must always be greater than or equal to the start offset.
 ///
+/// Chumsky will sometimes need to build new spans out of existing ones (when using [`Input::spanned`], for example,
+/// the span of a parser's output is created from the start of its first token's span and the end of its last token's
+/// span). It does this using only [`Span::context`], [`Span::start`], [`Span::end`], and [`Span::new`], so any
+/// information that should survive this process must be stored in either the context or the offsets. For example, if
+/// a span should carry line numbers as well as byte positions, both should be part of [`Span::Offset`] rather than
+/// being stored as separate fields of the span.
+///
 /// Span is automatically implemented for [`Range<T>`] and [`(C, Range<T>)`].
 pub trait Span {
     /// Extra context used in a span.
@@ -33,6 +40,9 @@ pub trait Span {
     type Offset;
 
     /// Create a new span given a context and an offset range.
+    ///
+    /// The context and offsets of the resulting span should be equal to those given, such that
+    /// `S::new(span.context(), span.start()..span.end())` produces a span equivalent to `span`.
     fn new(context: Self::Context, range: Range<Self::Offset>) -> Self;
 
     /// Return the span's context.
@@ -43,6 +53,144 @@ pub trait Span {
 
     /// Return the end offset of the span.
     fn end(&self) -> Self::Offset;
+
+    /// Determine whether the span contains the given offset: that is, whether the offset is at or after the start of
+    /// the span and before its end. Empty spans contain no offsets.
+    ///
+    /// The context of the span is not taken into account.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::{prelude::*, span::Span};
+    /// let span = SimpleSpan::new(2, 5);
+    ///
+    /// assert!(span.contains(&2));
+    /// assert!(span.contains(&4));
+    /// assert!(!span.contains(&5));
+    /// ```
+    fn contains(&self, offset: &Self::Offset) -> bool
+    where
+        Self::Offset: PartialOrd,
+    {
+        &self.start() <= offset && offset < &self.end()
+    }
+
+    /// Determine whether the span overlaps with another: that is, whether there is any offset that both spans contain.
+    /// Empty spans intersect nothing, and spans that merely touch (one ending where the other starts) do not
+    /// intersect.
+    ///
+    /// The contexts of the spans are not taken into account.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::{prelude::*, span::Span};
+    /// let span = SimpleSpan::new(2, 5);
+    ///
+    /// assert!(span.intersects(&SimpleSpan::new(4, 8)));
+    /// assert!(span.intersects(&SimpleSpan::new(0, 10)));
+    /// assert!(!span.intersects(&SimpleSpan::new(5, 8)));
+    /// ```
+    fn intersects(&self, other: &Self) -> bool
+    where
+        Self::Offset: PartialOrd,
+    {
+        self.start() < other.end() && other.start() < self.end()
+    }
+
+    /// Create an empty span positioned at the start of this span, such as to point at the position before a token.
+    ///
+    /// The context of the span is preserved.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::{prelude::*, span::Span};
+    /// assert_eq!(SimpleSpan::new(2, 5).at_start(), SimpleSpan::new(2, 2));
+    /// ```
+    fn at_start(&self) -> Self
+    where
+        Self: Sized,
+        Self::Offset: Clone,
+    {
+        let start = self.start();
+        Self::new(self.context(), start.clone()..start)
+    }
+
+    /// Create an empty span positioned at the end of this span. This is useful for pointing at the place where a
+    /// missing token should be inserted, such as when generating fix-its.
+    ///
+    /// The context of the span is preserved.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::{prelude::*, span::Span};
+    /// assert_eq!(SimpleSpan::new(2, 5).at_end(), SimpleSpan::new(5, 5));
+    /// assert_eq!(("main.rs", 2..5).at_end(), ("main.rs", 5..5));
+    /// ```
+    fn at_end(&self) -> Self
+    where
+        Self: Sized,
+        Self::Offset: Clone,
+    {
+        let end = self.end();
+        Self::new(self.context(), end.clone()..end)
+    }
+
+    /// Move this span into another coordinate space by adding `base` to both of its offsets and giving it a new
+    /// context, producing a span of any type with the same offset type.
+    ///
+    /// This is useful when combining the results of separate parses, such as when splicing a fragment that was parsed
+    /// on its own (a macro expansion, for example) into the AST of the file that it came from: the spans of the
+    /// fragment can be rebased onto the position at which the fragment begins within the file. Chumsky has no
+    /// knowledge of the structure of your AST, so you must apply this to each span within it yourself.
+    ///
+    /// Where the base is known before parsing, it is usually simpler to produce spans in the right coordinate space in
+    /// the first place using [`Input::map_span`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::{prelude::*, span::Span};
+    /// let in_fragment = SimpleSpan::new(2, 5);
+    ///
+    /// let in_file: (&str, SimpleSpan) = in_fragment.rebase(100, "main.rs");
+    /// assert_eq!(in_file, ("main.rs", SimpleSpan::new(102, 105)));
+    /// ```
+    fn rebase<S>(&self, base: Self::Offset, context: S::Context) -> S
+    where
+        S: Span<Offset = Self::Offset>,
+        Self::Offset: Clone + core::ops::Add<Output = Self::Offset>,
+    {
+        S::new(context, base.clone() + self.start()..base + self.end())
+    }
+
+    /// Convert this span into a plain [`Range<usize>`], for passing to libraries that describe locations in this way
+    /// (such as diagnostic renderers and language server protocol types).
+    ///
+    /// The context of the span is discarded by this conversion. If it is needed, such as to identify the file that the
+    /// span refers to, it should be retrieved with [`Span::context`] beforehand.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chumsky::{prelude::*, span::Span};
+    /// # use std::ops::Range;
+    /// assert_eq!(("main.rs", 2usize..5).into_range(), 2..5);
+    ///
+    /// let span = SimpleSpan::<usize, &str>::new("main.rs", 2..5);
+    /// assert_eq!(Span::into_range(span), 2..5);
+    /// assert_eq!(Range::from(span), 2..5);
+    /// ```
+    fn into_range(self) -> Range<usize>
+    where
+        Self: Sized,
+        Self::Offset: Into<usize>,
+    {
+        self.start().into()..self.end().into()
+    }
 }
 
 /// The most basic implementor of `Span` - akin to `Range`, but `Copy` since it's not also
@@ -82,8 +230,9 @@ impl<T> From<Range<T>> for SimpleSpan<T> {
     }
 }
 
-impl<T> From<SimpleSpan<T>> for Range<T> {
-    fn from(span: SimpleSpan<T>) -> Self {
+/// Note that the context of the span, if any, is discarded by this conversion.
+impl<T, C> From<SimpleSpan<T, C>> for Range<T> {
+    fn from(span: SimpleSpan<T, C>) -> Self {
         Range {
             start: span.start,
             end: span.end,
@@ -143,6 +292,62 @@ impl<T: Clone, C: Clone> Span for SimpleSpan<T, C> {
     }
 }
 
+/// A position within text that carries its line and column alongside its byte offset. See
+/// [`Input::with_tab_width`].
+///
+/// Because chumsky rebuilds spans from their offsets (see [`Span`]), the line and column are part of the offset type
+/// so that they survive spans being combined. Lines and columns both count from zero.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LineCol {
+    /// The byte offset of the position.
+    pub offset: usize,
+    /// The line on which the position lies.
+    pub line: usize,
+    /// The column of the position within its line, in characters, with tabs expanded to the next tab stop.
+    pub column: usize,
+}
+
+/// The direction in which the text covered by a [`DirectionalSpan`] flows.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// Left-to-right text, such as Latin or Cyrillic.
+    Ltr,
+    /// Right-to-left text, such as Hebrew or Arabic.
+    Rtl,
+}
+
+/// A span annotated with the direction of the text that it covers. See [`Input::with_bidi`].
+///
+/// The direction is stored as part of the span's context so that it survives spans being recombined (see [`Span`]).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DirectionalSpan<S> {
+    /// The underlying span.
+    pub span: S,
+    /// The direction of the text covered by the span.
+    pub direction: Direction,
+}
+
+impl<S: Span> Span for DirectionalSpan<S> {
+    type Context = (Direction, S::Context);
+    type Offset = S::Offset;
+
+    fn new((direction, context): Self::Context, range: Range<Self::Offset>) -> Self {
+        Self {
+            span: S::new(context, range),
+            direction,
+        }
+    }
+    fn context(&self) -> Self::Context {
+        (self.direction, self.span.context())
+    }
+    fn start(&self) -> Self::Offset {
+        self.span.start()
+    }
+    fn end(&self) -> Self::Offset {
+        self.span.end()
+    }
+}
+
 impl<C: Clone, S: Span<Context = ()>> Span for (C, S) {
     type Context = C;
     type Offset = S::Offset;
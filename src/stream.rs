@@ -8,6 +8,40 @@ use core::cell::Cell;
 /// required.
 pub struct Stream<I: Iterator> {
     tokens: Cell<(Vec<I::Item>, Option<I>)>,
+    window: BacktrackWindow,
+}
+
+// Bookkeeping for streams that limit how far back parsing may rewind. See `Stream::with_backtrack_limit`.
+#[derive(Clone, Default)]
+struct BacktrackWindow {
+    limit: Option<usize>,
+    // The number of tokens that have been discarded from the front of the buffer.
+    discarded: Cell<usize>,
+    // The furthest offset that has been read.
+    furthest: Cell<usize>,
+    // The first offset that was read after its token had been discarded.
+    exceeded_at: Cell<Option<usize>>,
+}
+
+/// Cloning a stream duplicates both the tokens it has already pulled from its iterator and the state of the iterator
+/// itself, allowing the same streamed input to be parsed several times without re-reading it from scratch.
+impl<I: Iterator + Clone> Clone for Stream<I>
+where
+    I::Item: Clone,
+{
+    fn clone(&self) -> Self {
+        let mut other = Cell::new((Vec::new(), None));
+        self.tokens.swap(&other);
+
+        let cloned = other.get_mut().clone();
+
+        self.tokens.swap(&other);
+
+        Self {
+            tokens: Cell::new(cloned),
+            window: self.window.clone(),
+        }
+    }
 }
 
 impl<I: Iterator> Stream<I> {
@@ -26,9 +60,70 @@ impl<I: Iterator> Stream<I> {
     pub fn from_iter<J: IntoIterator<IntoIter = I>>(iter: J) -> Self {
         Self {
             tokens: Cell::new((Vec::new(), Some(iter.into_iter()))),
+            window: BacktrackWindow::default(),
         }
     }
 
+    /// Limit how far back parsing may rewind within this stream, such that only the `limit` tokens before the furthest
+    /// token read need to be kept in memory.
+    ///
+    /// By default, a stream keeps every token that it pulls from its iterator in case the parser backtracks to it. With
+    /// a limit, memory use is bounded, but a parser that tries to read a token more than `limit` tokens behind the
+    /// furthest token that it has read fails with an error at that position, which can be detected with
+    /// [`ParseResult::exceeded_backtrack_limit`]. This happens consistently, regardless of which tokens happen to still
+    /// be buffered.
+    ///
+    /// Note that when this stream is given spans with [`Input::spanned`] or [`Stream::spanned_by`], creating the span
+    /// of an output requires its first token, so spans may not cover more than `limit` tokens either.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use chumsky::{prelude::*, input::Stream};
+    /// // Without lookahead, this grammar only needs to rewind by one token
+    /// let list = any::<_, extra::Err<Simple<char>>>()
+    ///     .filter(char::is_ascii_digit)
+    ///     .separated_by(just(','))
+    ///     .collect::<String>();
+    /// let res = list.parse(Stream::from_iter("1,2,3,4".chars()).with_backtrack_limit(1));
+    /// assert_eq!(res.into_result().as_deref(), Ok("1234"));
+    ///
+    /// // This grammar must rewind to the start of the list to try the second alternative
+    /// let ambiguous = list.then_ignore(just(';')).or(list.then_ignore(just('.')));
+    /// let res = ambiguous.parse(Stream::from_iter("1,2,3,4.".chars()).with_backtrack_limit(4));
+    /// assert!(res.exceeded_backtrack_limit());
+    /// assert_eq!(res.fatal_error_offset(), Some(0));
+    ///
+    /// let res = ambiguous.parse(Stream::from_iter("1,2,3,4.".chars()).with_backtrack_limit(8));
+    /// assert_eq!(res.into_result().as_deref(), Ok("1234"));
+    /// ```
+    pub fn with_backtrack_limit(mut self, limit: usize) -> Self {
+        self.window.limit = Some(limit);
+        self
+    }
+
+    /// Push a token onto the front of this stream, such that it is the first token yielded when the stream is parsed.
+    ///
+    /// This is useful when interleaving hand-written parsing with chumsky over the same stream: a token that was
+    /// pulled from the underlying source but not consumed can be put back. For streams of `(token, span)` pairs (such
+    /// as those passed to [`Input::spanned`]), the pushed token must come with its span, which should precede the spans
+    /// of the tokens already in the stream.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use chumsky::{prelude::*, input::Stream};
+    /// let mut stream = Stream::from_iter("bc".chars());
+    /// stream.push_front('a');
+    ///
+    /// let parser = any::<_, extra::Err<Simple<_>>>().repeated().collect::<String>();
+    ///
+    /// assert_eq!(parser.parse(stream).into_result().as_deref(), Ok("abc"));
+    /// ```
+    pub fn push_front(&mut self, token: I::Item) {
+        self.tokens.get_mut().0.insert(0, token);
+    }
+
     /// Box this stream, turning it into a [BoxedStream]. This can be useful in cases where your parser accepts input
     /// from several different sources and it needs to work with all of them.
     pub fn boxed<'a>(self) -> BoxedStream<'a, I::Item>
@@ -38,6 +133,7 @@ impl<I: Iterator> Stream<I> {
         let (vec, iter) = self.tokens.into_inner();
         Stream {
             tokens: Cell::new((vec, Some(Box::new(iter.expect("no iterator?!"))))),
+            window: self.window,
         }
     }
 
@@ -49,7 +145,464 @@ impl<I: Iterator> Stream<I> {
         let (vec, iter) = self.tokens.into_inner();
         Stream {
             tokens: Cell::new((vec, Some(Box::new(iter.expect("no iterator?!"))))),
+            window: self.window,
+        }
+    }
+
+    /// Attach spans to the tokens of this stream by accumulating their lengths, yielding a [`SpannedStream`].
+    ///
+    /// `len_of` is called on each token to determine how many offsets (usually bytes) of the original source it
+    /// occupied. Each token is then given a span starting where the previous token ended, with the first token
+    /// starting at `0`. Tokens with a length of zero are given zero-width spans. The end of input span is the
+    /// zero-width span just after the last token.
+    ///
+    /// This is useful for connecting a lexer that produces tokens without spans to a parser.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use chumsky::{prelude::*, input::Stream};
+    /// #[derive(Clone, Debug, PartialEq)]
+    /// enum Token {
+    ///     Word(&'static str),
+    ///     Space(usize),
+    /// }
+    ///
+    /// let tokens = vec![Token::Word("hello"), Token::Space(3), Token::Word("world")];
+    /// let stream = Stream::from_iter(tokens).spanned_by(|tok| match tok {
+    ///     Token::Word(w) => w.len(),
+    ///     Token::Space(n) => *n,
+    /// });
+    ///
+    /// let word = select! { Token::Word(w) => w }.map_with_span(|w, span| (w, span));
+    /// let parser = word.then_ignore(any::<_, extra::Err<Simple<_>>>()).then(word);
+    ///
+    /// assert_eq!(
+    ///     parser.parse(stream).into_result(),
+    ///     Ok((("hello", SimpleSpan::new(0, 5)), ("world", SimpleSpan::new(8, 13)))),
+    /// );
+    /// ```
+    pub fn spanned_by<F: FnMut(&I::Item) -> usize>(self, mut len_of: F) -> SpannedStream<I, F> {
+        let (vec, iter) = self.tokens.into_inner();
+        let mut offset = 0;
+        let vec = vec
+            .into_iter()
+            .map(|tok| {
+                let start = offset;
+                offset += len_of(&tok);
+                (tok, SimpleSpan::new(start, offset))
+            })
+            .collect();
+        SpannedStream {
+            stream: Stream {
+                tokens: Cell::new((
+                    vec,
+                    Some(SpanLengths {
+                        iter: iter.expect("no iterator?!"),
+                        len_of,
+                        offset,
+                    }),
+                )),
+                window: self.window,
+            },
+        }
+    }
+}
+
+impl<T, J: Iterator<Item = (T, usize)>> Stream<OffsetSpans<J>> {
+    /// Create a new stream from an [`Iterator`] of tokens paired with their offset in the original source.
+    ///
+    /// This is useful when tokens are produced by some process (such as decoding from a non-UTF-8 encoding) where
+    /// the position of a token in the original source does not correspond to its index in the stream. Each token is
+    /// given a span extending from its own offset up to the offset of the token that follows it, or up to `end` (which
+    /// should be the length of the original source) for the final token. `end` is also used as the end of input span.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use chumsky::{prelude::*, input::Stream};
+    /// // Each character of this source occupies two bytes in its original encoding
+    /// let chars = "abc1".chars().enumerate().map(|(i, c)| (c, i * 2));
+    ///
+    /// let word = any::<_, extra::Err<Simple<_>>>()
+    ///     .filter(|c: &char| c.is_alphabetic())
+    ///     .repeated()
+    ///     .at_least(1)
+    ///     .map_with_span(|_, span| span);
+    /// let number = text::digits(10).map_with_span(|_, span| span);
+    ///
+    /// assert_eq!(
+    ///     word.then(number).parse(Stream::from_offset_iter(chars, 8)).into_result(),
+    ///     Ok((SimpleSpan::new(0, 6), SimpleSpan::new(6, 8))),
+    /// );
+    /// ```
+    pub fn from_offset_iter<'a, K: IntoIterator<IntoIter = J>>(
+        iter: K,
+        end: usize,
+    ) -> input::SpannedInput<T, SimpleSpan<usize>, Self>
+    where
+        T: Clone + 'a,
+        J: 'a,
+    {
+        Stream::from_iter(OffsetSpans {
+            iter: iter.into_iter().peekable(),
+            end,
+        })
+        .spanned(SimpleSpan::new(end, end))
+    }
+}
+
+impl<T: Clone, J: Iterator<Item = (T, usize)>> Stream<SparseTokens<J, T>> {
+    /// Create a new stream from an [`Iterator`] of tokens paired with their positions, where some positions may have no
+    /// token. Each run of missing positions is filled by a single `gap` token.
+    ///
+    /// This is useful for parsing sparse data (such as a column of values keyed by row) in which gaps are meaningful.
+    /// Each token is given a span covering only its own position, and each gap token a span covering the run of
+    /// positions that it fills. Positions begin at zero, and any positions between the final token and `end` are also
+    /// filled by a gap token. `end` is used as the end of input span. Positions must be strictly increasing.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use chumsky::{prelude::*, input::Stream};
+    /// let cells = [(1, 'a'), (2, 'b'), (5, 'c')].into_iter().map(|(pos, c)| (Some(c), pos));
+    ///
+    /// let cell = any::<_, extra::Err<Simple<_>>>().map_with_span(|cell, span: SimpleSpan| (cell, span));
+    ///
+    /// assert_eq!(
+    ///     cell.repeated().collect::<Vec<_>>().parse(Stream::from_sparse_iter(cells, None, 7)).into_result(),
+    ///     Ok(vec![
+    ///         (None, SimpleSpan::new(0, 1)),
+    ///         (Some('a'), SimpleSpan::new(1, 2)),
+    ///         (Some('b'), SimpleSpan::new(2, 3)),
+    ///         (None, SimpleSpan::new(3, 5)),
+    ///         (Some('c'), SimpleSpan::new(5, 6)),
+    ///         (None, SimpleSpan::new(6, 7)),
+    ///     ]),
+    /// );
+    /// ```
+    pub fn from_sparse_iter<'a, K: IntoIterator<IntoIter = J>>(
+        iter: K,
+        gap: T,
+        end: usize,
+    ) -> input::SpannedInput<T, SimpleSpan<usize>, Self>
+    where
+        T: 'a,
+        J: 'a,
+    {
+        Stream::from_iter(SparseTokens {
+            iter: iter.into_iter().peekable(),
+            gap,
+            pos: 0,
+            end,
+        })
+        .spanned(SimpleSpan::new(end, end))
+    }
+}
+
+/// An error produced when the tokens and spans given to [`ZipSpanned::new`] do not have the same length.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ZipLengthError {
+    /// The number of tokens.
+    pub tokens: usize,
+    /// The number of spans.
+    pub spans: usize,
+}
+
+impl fmt::Display for ZipLengthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the number of tokens ({}) does not match the number of spans ({})",
+            self.tokens, self.spans,
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ZipLengthError {}
+
+/// An iterator that converts tokens paired with sparse positions into tokens paired with spans, filling any gaps. See
+/// [`Stream::from_sparse_iter`].
+pub struct SparseTokens<I: Iterator, T> {
+    iter: core::iter::Peekable<I>,
+    gap: T,
+    // The first position not yet covered by a token
+    pos: usize,
+    end: usize,
+}
+
+impl<T: Clone, I: Iterator<Item = (T, usize)>> Iterator for SparseTokens<I, T> {
+    type Item = (T, SimpleSpan<usize>);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.pos;
+        match self.iter.peek() {
+            Some((_, pos)) if *pos > start => {
+                self.pos = *pos;
+                Some((self.gap.clone(), SimpleSpan::new(start, self.pos)))
+            }
+            Some(_) => {
+                let (tok, pos) = self.iter.next()?;
+                self.pos = pos + 1;
+                Some((tok, SimpleSpan::new(pos, self.pos)))
+            }
+            None if start < self.end => {
+                self.pos = self.end;
+                Some((self.gap.clone(), SimpleSpan::new(start, self.end)))
+            }
+            None => None,
+        }
+    }
+}
+
+/// An iterator that converts tokens paired with offsets into tokens paired with spans. See
+/// [`Stream::from_offset_iter`].
+pub struct OffsetSpans<I: Iterator> {
+    iter: core::iter::Peekable<I>,
+    end: usize,
+}
+
+impl<T, I: Iterator<Item = (T, usize)>> Iterator for OffsetSpans<I> {
+    type Item = (T, SimpleSpan<usize>);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let (tok, start) = self.iter.next()?;
+        let end = self.iter.peek().map_or(self.end, |(_, next)| *next);
+        Some((tok, SimpleSpan::new(start, end)))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<T, I: ExactSizeIterator<Item = (T, usize)>> ExactSizeIterator for OffsetSpans<I> {}
+
+/// An iterator that pairs tokens with spans derived from their lengths. See [`Stream::spanned_by`].
+struct SpanLengths<I, F> {
+    iter: I,
+    len_of: F,
+    offset: usize,
+}
+
+impl<I: Iterator, F: FnMut(&I::Item) -> usize> Iterator for SpanLengths<I, F> {
+    type Item = (I::Item, SimpleSpan<usize>);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let tok = self.iter.next()?;
+        let start = self.offset;
+        self.offset += (self.len_of)(&tok);
+        Some((tok, SimpleSpan::new(start, self.offset)))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+/// A stream whose tokens are given spans by accumulating their lengths. See [`Stream::spanned_by`].
+pub struct SpannedStream<I: Iterator, F: FnMut(&I::Item) -> usize> {
+    stream: Stream<SpanLengths<I, F>>,
+}
+
+impl<I: Iterator, F: FnMut(&I::Item) -> usize> SpannedStream<I, F> {
+    /// Push a token, along with its span, onto the front of this stream, such that it is the first token yielded when
+    /// the stream is parsed. See [`Stream::push_front`].
+    ///
+    /// The span should precede the spans of the tokens already in the stream.
+    pub fn push_front(&mut self, token: I::Item, span: SimpleSpan<usize>) {
+        self.stream.push_front((token, span));
+    }
+}
+
+// Implemented by iterators of tokens paired with spans that know the offset at which the last of their tokens ended.
+trait SpanEnd {
+    // The offset just after the last token pulled from this iterator so far
+    fn end(&self) -> usize;
+}
+
+impl<I, F> SpanEnd for SpanLengths<I, F> {
+    #[inline(always)]
+    fn end(&self) -> usize {
+        self.offset
+    }
+}
+
+/// Get the span of the token at the given offset of a spanned stream, if there is one.
+fn token_span<T, J>(stream: &Stream<J>, offset: usize) -> Option<SimpleSpan<usize>>
+where
+    J: Iterator<Item = (T, SimpleSpan<usize>)> + SpanEnd,
+{
+    let mut other = Cell::new((Vec::new(), None));
+    stream.tokens.swap(&other);
+
+    let (vec, iter) = other.get_mut();
+
+    let window = &stream.window;
+    let span = match offset.checked_sub(window.discarded.get()) {
+        Some(index) => {
+            // Pull new items into the vector if we need them
+            if vec.len() <= index {
+                vec.extend(iter.as_mut().expect("no iterator?!").take(500));
+            }
+            vec.get(index).map(|(_, span)| *span)
+        }
+        // The token has been discarded, so its span is no longer known. This is treated as backtracking too far, which
+        // causes the parse to fail, so the placeholder span is never observed.
+        None => {
+            window
+                .exceeded_at
+                .set(window.exceeded_at.get().or(Some(offset)));
+            Some(SimpleSpan::new(offset, offset))
         }
+    };
+
+    stream.tokens.swap(&other);
+
+    span
+}
+
+/// Get the offset just after the last token of a spanned stream (i.e: the start of the end of input span), along with
+/// the number of tokens. This requires pulling all remaining tokens from the iterator.
+fn stream_eoi<T, J>(stream: &Stream<J>) -> (usize, usize)
+where
+    J: Iterator<Item = (T, SimpleSpan<usize>)> + SpanEnd,
+{
+    let mut other = Cell::new((Vec::new(), None));
+    stream.tokens.swap(&other);
+
+    let (vec, iter) = other.get_mut();
+    let iter = iter.as_mut().expect("no iterator?!");
+    vec.extend(iter.by_ref());
+    let eoi = (iter.end(), stream.window.discarded.get() + vec.len());
+
+    stream.tokens.swap(&other);
+
+    eoi
+}
+
+/// Whether the given offset lies within a stream, including at its end. This requires pulling tokens from the iterator
+/// up to the offset.
+fn stream_is_boundary<J: Iterator>(stream: &Stream<J>, offset: usize) -> bool {
+    // Offsets before the discarded tokens were once within the stream
+    let index = match offset.checked_sub(stream.window.discarded.get()) {
+        Some(index) => index,
+        None => return true,
+    };
+
+    let mut other = Cell::new((Vec::new(), None));
+    stream.tokens.swap(&other);
+
+    let (vec, iter) = other.get_mut();
+    if vec.len() < index {
+        let missing = index - vec.len();
+        vec.extend(iter.as_mut().expect("no iterator?!").take(missing));
+    }
+    let within = index <= vec.len();
+
+    stream.tokens.swap(&other);
+
+    within
+}
+
+/// Get the span that covers the tokens of a spanned stream in the given range of offsets.
+fn tokens_span<T, J>(stream: &Stream<J>, range: Range<usize>) -> SimpleSpan<usize>
+where
+    J: Iterator<Item = (T, SimpleSpan<usize>)> + SpanEnd,
+{
+    let start = match token_span(stream, range.start) {
+        Some(span) => span.start,
+        None => stream_eoi(stream).0,
+    };
+    let end = match range
+        .end
+        .checked_sub(1)
+        .and_then(|last| token_span(stream, last))
+    {
+        Some(span) => span.end.max(start),
+        None => start,
+    };
+    SimpleSpan::new(start, end)
+}
+
+impl<I: Iterator, F: FnMut(&I::Item) -> usize> Sealed for SpannedStream<I, F> {}
+impl<'a, I, F> Input<'a> for SpannedStream<I, F>
+where
+    I: Iterator + 'a,
+    I::Item: Clone,
+    F: FnMut(&I::Item) -> usize + 'a,
+{
+    type Offset = usize;
+    type Token = I::Item;
+    type Span = SimpleSpan<usize>;
+
+    #[inline(always)]
+    fn start(&self) -> Self::Offset {
+        0
+    }
+
+    type TokenMaybe = I::Item;
+
+    #[inline(always)]
+    unsafe fn next_maybe(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::TokenMaybe>) {
+        self.next(offset)
+    }
+
+    #[inline]
+    unsafe fn span(&self, range: Range<Self::Offset>) -> Self::Span {
+        tokens_span(&self.stream, range)
+    }
+
+    #[inline]
+    fn prev(offs: Self::Offset) -> Self::Offset {
+        offs.saturating_sub(1)
+    }
+
+    #[inline]
+    fn is_boundary(&self, offset: Self::Offset) -> bool {
+        stream_is_boundary(&self.stream, offset)
+    }
+
+    #[inline(always)]
+    unsafe fn token_distance(&self, from: Self::Offset, to: Self::Offset) -> usize {
+        to.saturating_sub(from)
+    }
+
+    #[inline(always)]
+    fn backtrack_exceeded_at(&self) -> Option<Self::Offset> {
+        self.stream.backtrack_exceeded_at()
+    }
+}
+
+impl<'a, I, F> ExactSizeInput<'a> for SpannedStream<I, F>
+where
+    I: Iterator + 'a,
+    I::Item: Clone,
+    F: FnMut(&I::Item) -> usize + 'a,
+{
+    #[inline]
+    unsafe fn span_from(&self, range: RangeFrom<Self::Offset>) -> Self::Span {
+        let len = stream_eoi(&self.stream).1;
+        tokens_span(&self.stream, range.start..len)
+    }
+}
+
+impl<'a, I, F> ValueInput<'a> for SpannedStream<I, F>
+where
+    I: Iterator + 'a,
+    I::Item: Clone,
+    F: FnMut(&I::Item) -> usize + 'a,
+{
+    #[inline]
+    unsafe fn next(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::Token>) {
+        let (offset, tok) = self.stream.next(offset);
+        (offset, tok.map(|(tok, _)| tok))
     }
 }
 
@@ -89,6 +642,21 @@ where
     fn prev(offs: Self::Offset) -> Self::Offset {
         offs.saturating_sub(1)
     }
+
+    #[inline]
+    fn is_boundary(&self, offset: Self::Offset) -> bool {
+        stream_is_boundary(self, offset)
+    }
+
+    #[inline(always)]
+    unsafe fn token_distance(&self, from: Self::Offset, to: Self::Offset) -> usize {
+        to.saturating_sub(from)
+    }
+
+    #[inline(always)]
+    fn backtrack_exceeded_at(&self) -> Option<Self::Offset> {
+        self.window.exceeded_at.get()
+    }
 }
 
 impl<'a, I: ExactSizeIterator + 'a> ExactSizeInput<'a> for Stream<I>
@@ -99,7 +667,9 @@ where
     unsafe fn span_from(&self, range: RangeFrom<Self::Offset>) -> Self::Span {
         let mut other = Cell::new((Vec::new(), None));
         self.tokens.swap(&other);
-        let len = other.get_mut().1.as_ref().expect("no iterator?!").len();
+        let (vec, iter) = other.get_mut();
+        let len =
+            self.window.discarded.get() + vec.len() + iter.as_ref().expect("no iterator?!").len();
         self.tokens.swap(&other);
         (range.start..len).into()
     }
@@ -111,18 +681,40 @@ where
 {
     #[inline]
     unsafe fn next(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::Token>) {
+        let window = &self.window;
+        if let Some(limit) = window.limit {
+            window.furthest.set(window.furthest.get().max(offset));
+            // The token may already have been discarded, so fail consistently whether or not it has been
+            if offset.saturating_add(limit) < window.furthest.get() {
+                window
+                    .exceeded_at
+                    .set(window.exceeded_at.get().or(Some(offset)));
+                return (offset, None);
+            }
+        }
+
         let mut other = Cell::new((Vec::new(), None));
         self.tokens.swap(&other);
 
         let (vec, iter) = other.get_mut();
+        let mut index = offset - window.discarded.get();
 
         // Pull new items into the vector if we need them
-        if vec.len() <= offset {
+        if vec.len() <= index {
+            // Discard the tokens that can no longer be backtracked to first
+            if let Some(limit) = window.limit {
+                let discard = (window.furthest.get().saturating_sub(limit)
+                    - window.discarded.get())
+                .min(vec.len());
+                vec.drain(..discard);
+                window.discarded.set(window.discarded.get() + discard);
+                index -= discard;
+            }
             vec.extend(iter.as_mut().expect("no iterator?!").take(500));
         }
 
         // Get the token at the given offset
-        let tok = vec.get(offset).map(I::Item::clone);
+        let tok = vec.get(index).map(I::Item::clone);
 
         self.tokens.swap(&other);
 
@@ -130,18 +722,869 @@ where
     }
 }
 
-#[test]
-fn spanned() {
-    fn parser<'a>() -> impl Parser<
-        'a,
-        input::SpannedInput<char, Range<usize>, BoxedStream<'static, (char, Range<usize>)>>,
-        char,
-    > {
-        just('h')
+/// A buffer of tokens that may continue to grow while it is being parsed, such as bytes that are still arriving from a
+/// network connection.
+///
+/// Tokens are added to the buffer with [`GrowableBuffer::extend`], and [`GrowableBuffer::finish`] signals that no more
+/// will arrive. Each [`GrowableBuffer::input`] re-reads the length of the buffer whenever it needs a token, so tokens
+/// added during parsing become visible to it.
+///
+/// Unlike [`Stream`], which pulls tokens from an iterator on demand, this is a cooperative model in which the producer
+/// pushes tokens into the buffer and the parser is run again once more have arrived. If a parser reaches the end of
+/// the buffer before it has been finished, it cannot know whether the input really ends there, so the parse fails and
+/// [`ParseResult::needs_more_input`] reports that it should be retried once more tokens have been added.
+///
+/// The buffer is not thread-safe: it is intended for producers and parsers that share a thread, such as tasks on a
+/// single-threaded executor.
+///
+/// # Example
+///
+/// ```
+/// # use chumsky::{prelude::*, input::GrowableBuffer};
+/// let num = any::<_, extra::Err<Simple<u8>>>()
+///     .filter(u8::is_ascii_digit)
+///     .repeated()
+///     .at_least(1)
+///     .collect::<Vec<_>>()
+///     .map(|digits| digits.iter().fold(0, |n, d| n * 10 + (d - b'0') as u32));
+/// let sum = num
+///     .separated_by(just(b'+'))
+///     .collect::<Vec<_>>()
+///     .map(|nums| nums.into_iter().sum::<u32>());
+///
+/// let buffer = GrowableBuffer::new();
+/// buffer.extend(*b"1+2");
+/// // More digits may yet arrive, so the result isn't known
+/// assert!(sum.parse(buffer.input()).needs_more_input());
+///
+/// buffer.extend(*b"0+3");
+/// buffer.finish();
+/// assert_eq!(sum.parse(buffer.input()).into_result(), Ok(24));
+/// ```
+pub struct GrowableBuffer<T> {
+    tokens: core::cell::RefCell<Vec<T>>,
+    finished: Cell<bool>,
+}
+
+impl<T> GrowableBuffer<T> {
+    /// Create a new, empty buffer.
+    pub fn new() -> Self {
+        Self {
+            tokens: core::cell::RefCell::new(Vec::new()),
+            finished: Cell::new(false),
+        }
     }
 
-    let stream = Stream::from_iter(core::iter::once(('h', 0..1))).boxed();
-    let stream = stream.spanned(0..10);
+    /// Add tokens to the end of the buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`GrowableBuffer::finish`] has already been called.
+    pub fn extend(&self, tokens: impl IntoIterator<Item = T>) {
+        assert!(
+            !self.finished.get(),
+            "tokens cannot be added to a buffer that has been finished"
+        );
+        self.tokens.borrow_mut().extend(tokens);
+    }
 
-    assert_eq!(parser().parse(stream).into_result(), Ok('h'));
+    /// Signal that no more tokens will be added to the buffer, such that its current end is the end of the input.
+    pub fn finish(&self) {
+        self.finished.set(true);
+    }
+
+    /// Whether [`GrowableBuffer::finish`] has been called.
+    pub fn is_finished(&self) -> bool {
+        self.finished.get()
+    }
+
+    /// The number of tokens currently in the buffer.
+    pub fn len(&self) -> usize {
+        self.tokens.borrow().len()
+    }
+
+    /// Whether the buffer currently contains no tokens.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Create an input that parses the tokens of this buffer, including any added during parsing.
+    pub fn input(&self) -> GrowableInput<'_, T> {
+        GrowableInput {
+            buffer: self,
+            starved_at: Cell::new(None),
+        }
+    }
+}
+
+impl<T> Default for GrowableBuffer<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An input over the tokens of a [`GrowableBuffer`]. See [`GrowableBuffer::input`].
+pub struct GrowableInput<'a, T> {
+    buffer: &'a GrowableBuffer<T>,
+    // The first offset at which the parser reached the end of the buffer before it was finished.
+    starved_at: Cell<Option<usize>>,
+}
+
+impl<'a, T> Sealed for GrowableInput<'a, T> {}
+impl<'a, T: Clone + 'a> Input<'a> for GrowableInput<'a, T> {
+    type Offset = usize;
+    type Token = T;
+    type Span = SimpleSpan<usize>;
+
+    #[inline(always)]
+    fn start(&self) -> Self::Offset {
+        0
+    }
+
+    type TokenMaybe = T;
+
+    #[inline(always)]
+    unsafe fn next_maybe(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::TokenMaybe>) {
+        self.next(offset)
+    }
+
+    #[inline(always)]
+    unsafe fn span(&self, range: Range<Self::Offset>) -> Self::Span {
+        range.into()
+    }
+
+    #[inline]
+    fn prev(offs: Self::Offset) -> Self::Offset {
+        offs.saturating_sub(1)
+    }
+
+    // Tokens that have not yet arrived cannot be known to exist
+    #[inline]
+    fn is_boundary(&self, offset: Self::Offset) -> bool {
+        offset <= self.buffer.len()
+    }
+
+    #[inline(always)]
+    unsafe fn token_distance(&self, from: Self::Offset, to: Self::Offset) -> usize {
+        to.saturating_sub(from)
+    }
+
+    #[inline(always)]
+    fn starved_at(&self) -> Option<Self::Offset> {
+        self.starved_at.get()
+    }
+}
+
+impl<'a, T: Clone + 'a> ExactSizeInput<'a> for GrowableInput<'a, T> {
+    // The end of the input is the end of the tokens that have arrived so far
+    #[inline(always)]
+    unsafe fn span_from(&self, range: RangeFrom<Self::Offset>) -> Self::Span {
+        (range.start..self.buffer.len()).into()
+    }
+}
+
+impl<'a, T: Clone + 'a> ValueInput<'a> for GrowableInput<'a, T> {
+    #[inline]
+    unsafe fn next(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::Token>) {
+        match self.buffer.tokens.borrow().get(offset) {
+            Some(tok) => (offset + 1, Some(tok.clone())),
+            None => {
+                if !self.buffer.finished.get() {
+                    self.starved_at.set(self.starved_at.get().or(Some(offset)));
+                }
+                (offset, None)
+            }
+        }
+    }
+}
+
+/// An input over tokens and their spans that are stored in separate slices, such that the `n`th token has the `n`th
+/// span.
+///
+/// This behaves like [`Input::spanned`], but is useful when a lexer stores its tokens and spans in separate arrays
+/// since each is indexed directly, without first combining them into a single array of pairs.
+#[derive(Debug)]
+pub struct ZipSpanned<'a, T, S> {
+    tokens: &'a [T],
+    spans: &'a [S],
+    eoi: S,
+}
+
+impl<'a, T, S> ZipSpanned<'a, T, S> {
+    /// Create a new input from a slice of tokens and a slice of their spans. `eoi` is used as the end of input span,
+    /// as with [`Input::spanned`].
+    ///
+    /// An error is returned if the two slices do not have the same length.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use chumsky::{prelude::*, input::ZipSpanned};
+    /// let tokens = ['a', '+', 'b'];
+    /// let spans = [SimpleSpan::new(0, 1), SimpleSpan::new(2, 3), SimpleSpan::new(4, 5)];
+    ///
+    /// let ident = any::<_, extra::Err<Simple<_>>>().filter(char::is_ascii_alphabetic);
+    /// let sum = ident.then_ignore(just('+')).then(ident).map_with_span(|_, span| span);
+    ///
+    /// let input = ZipSpanned::new(&tokens, &spans, SimpleSpan::new(5, 5)).unwrap();
+    /// assert_eq!(sum.parse(input).into_result(), Ok(SimpleSpan::new(0, 5)));
+    ///
+    /// assert!(ZipSpanned::new(&tokens, &spans[..2], SimpleSpan::new(5, 5)).is_err());
+    /// ```
+    pub fn new(tokens: &'a [T], spans: &'a [S], eoi: S) -> Result<Self, ZipLengthError> {
+        if tokens.len() != spans.len() {
+            return Err(ZipLengthError {
+                tokens: tokens.len(),
+                spans: spans.len(),
+            });
+        }
+        Ok(Self { tokens, spans, eoi })
+    }
+}
+
+impl<'a, T, S: Clone> Clone for ZipSpanned<'a, T, S> {
+    fn clone(&self) -> Self {
+        Self {
+            tokens: self.tokens,
+            spans: self.spans,
+            eoi: self.eoi.clone(),
+        }
+    }
+}
+
+impl<'a, T, S: Copy> Copy for ZipSpanned<'a, T, S> {}
+
+impl<'a, T, S> Sealed for ZipSpanned<'a, T, S> {}
+impl<'a, T: 'a, S: Span + Clone + 'a> Input<'a> for ZipSpanned<'a, T, S> {
+    type Offset = usize;
+    type Token = T;
+    type Span = S;
+
+    #[inline(always)]
+    fn start(&self) -> Self::Offset {
+        0
+    }
+
+    type TokenMaybe = &'a T;
+
+    #[inline(always)]
+    unsafe fn next_maybe(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::TokenMaybe>) {
+        self.next_ref(offset)
+    }
+
+    #[inline(always)]
+    unsafe fn token_at(&self, offset: Self::Offset) -> Option<Self::TokenMaybe> {
+        self.tokens.get(offset)
+    }
+
+    #[inline(always)]
+    unsafe fn span(&self, range: Range<Self::Offset>) -> Self::Span {
+        let start = match self.spans.get(range.start) {
+            Some(span) => span.start(),
+            // The input is empty, so the EoI span is the only sensible span
+            None if range.start == 0 => return self.eoi.clone(),
+            None => self.eoi.start(),
+        };
+        let end = self
+            .spans
+            .get(Self::prev(range.end))
+            .map_or(self.eoi.start(), |span| span.end());
+        S::new(self.eoi.context(), start..end)
+    }
+
+    #[inline(always)]
+    fn prev(offs: Self::Offset) -> Self::Offset {
+        offs.saturating_sub(1)
+    }
+
+    #[inline(always)]
+    fn is_boundary(&self, offset: Self::Offset) -> bool {
+        offset <= self.tokens.len()
+    }
+
+    #[inline(always)]
+    unsafe fn token_distance(&self, from: Self::Offset, to: Self::Offset) -> usize {
+        to.saturating_sub(from)
+    }
+}
+
+impl<'a, T: 'a, S: Span + Clone + 'a> ExactSizeInput<'a> for ZipSpanned<'a, T, S> {
+    #[inline(always)]
+    unsafe fn span_from(&self, range: RangeFrom<Self::Offset>) -> Self::Span {
+        let start = match self.spans.get(range.start) {
+            Some(span) => span.start(),
+            // The input is empty, so the EoI span is the only sensible span
+            None if range.start == 0 => return self.eoi.clone(),
+            None => self.eoi.start(),
+        };
+        S::new(self.eoi.context(), start..self.eoi.start())
+    }
+}
+
+impl<'a, T: Clone + 'a, S: Span + Clone + 'a> ValueInput<'a> for ZipSpanned<'a, T, S> {
+    #[inline(always)]
+    unsafe fn next(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::Token>) {
+        let (offset, tok) = self.next_ref(offset);
+        (offset, tok.cloned())
+    }
+}
+
+impl<'a, T: 'a, S: Span + Clone + 'a> BorrowInput<'a> for ZipSpanned<'a, T, S> {
+    #[inline(always)]
+    unsafe fn next_ref(&self, offset: Self::Offset) -> (Self::Offset, Option<&'a Self::Token>) {
+        match self.tokens.get(offset) {
+            Some(tok) => (offset + 1, Some(tok)),
+            None => (offset, None),
+        }
+    }
+}
+
+impl<'a, T: 'a, S: Span + Clone + 'a> SliceInput<'a> for ZipSpanned<'a, T, S> {
+    type Slice = &'a [T];
+
+    #[inline(always)]
+    fn slice(&self, range: Range<Self::Offset>) -> Self::Slice {
+        &self.tokens[range]
+    }
+
+    #[inline(always)]
+    fn slice_from(&self, from: RangeFrom<Self::Offset>) -> Self::Slice {
+        &self.tokens[from]
+    }
+}
+
+/// An iterator that deserializes the lines of a reader of newline-delimited JSON, pairing each record with its span.
+/// See [`JsonRecords::new`].
+#[cfg(feature = "serde")]
+struct JsonLines<R, T> {
+    // Taken after the first I/O error, so that no further lines are read
+    reader: Option<R>,
+    // The offset of the next line to be read
+    offset: usize,
+    line: String,
+    // Chosen when the records are created, so that neither this type nor `JsonRecords` need to be bounded on the
+    // reader and record types
+    read_line: fn(&mut R, &mut String) -> std::io::Result<usize>,
+    deserialize: fn(&str) -> Result<T, JsonRecordError>,
+}
+
+#[cfg(feature = "serde")]
+impl<R, T> Iterator for JsonLines<R, T> {
+    type Item = (Result<T, JsonRecordError>, SimpleSpan<usize>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let start = self.offset;
+            self.line.clear();
+            match (self.read_line)(self.reader.as_mut()?, &mut self.line) {
+                Ok(0) => return None,
+                Ok(len) => self.offset += len,
+                Err(err) => {
+                    self.reader = None;
+                    let err = JsonRecordError(err.to_string());
+                    return Some((Err(err), SimpleSpan::new(start, start)));
+                }
+            }
+
+            let record = self.line.trim();
+            if !record.is_empty() {
+                let lo = start + (self.line.len() - self.line.trim_start().len());
+                let value = (self.deserialize)(record);
+                return Some((value, SimpleSpan::new(lo, lo + record.len())));
+            }
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<R, T> SpanEnd for JsonLines<R, T> {
+    #[inline(always)]
+    fn end(&self) -> usize {
+        self.offset
+    }
+}
+
+/// An error produced when a record given to [`JsonRecords::new`] cannot be read or deserialized.
+#[cfg(feature = "serde")]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct JsonRecordError(String);
+
+#[cfg(feature = "serde")]
+impl fmt::Display for JsonRecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid record: {}", self.0)
+    }
+}
+
+/// A stream of records deserialized from newline-delimited JSON. See [`JsonRecords::new`].
+#[cfg(feature = "serde")]
+pub struct JsonRecords<R, T> {
+    stream: Stream<JsonLines<R, T>>,
+}
+
+#[cfg(feature = "serde")]
+impl<R: std::io::BufRead, T: serde::de::DeserializeOwned> JsonRecords<R, T> {
+    /// Create a new stream of records that are lazily deserialized from a reader of newline-delimited JSON, in which
+    /// each non-blank line holds a single JSON value.
+    ///
+    /// This is useful for enforcing a grammar over a sequence of structured records, such as the output of an earlier
+    /// stage in a pipeline. Each token is the result of deserializing a record, and is given a span covering the bytes
+    /// of its line, excluding surrounding whitespace. A record that cannot be deserialized (or read) produces a
+    /// [`JsonRecordError`] token instead, which the parser can turn into an error at that record's span with
+    /// [`Parser::try_map`]. Reading stops after the first I/O error.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use chumsky::{prelude::*, input::JsonRecords};
+    /// type Event = (String, i64);
+    ///
+    /// let event = |kind: &'static str| {
+    ///     any::<_, extra::Err<Rich<_>>>().try_map(move |event: Result<Event, _>, span| match event {
+    ///         Ok((k, n)) if k == kind => Ok(n),
+    ///         Ok((k, _)) => Err(Rich::custom(span, format!("expected {kind}, found {k}"))),
+    ///         Err(e) => Err(Rich::custom(span, e)),
+    ///     })
+    /// };
+    /// let group = event("begin")
+    ///     .ignore_then(event("item").repeated().collect::<Vec<_>>())
+    ///     .then_ignore(event("end"));
+    ///
+    /// let source = "[\"begin\", 0]\n[\"item\", 5]\n\n[\"item\", 7]\n[\"end\", 0]\n";
+    /// let records = JsonRecords::new(source.as_bytes());
+    /// assert_eq!(group.parse(records).into_result(), Ok(vec![5, 7]));
+    ///
+    /// // Records that cannot be deserialized produce errors at their position
+    /// let source = "[\"begin\", 0]\n[\"item\", oops]\n[\"end\", 0]\n";
+    /// let records = JsonRecords::new(source.as_bytes());
+    /// let errs = group.parse(records).into_errors();
+    /// assert_eq!(errs[0].span(), &SimpleSpan::new(13, 27));
+    /// ```
+    pub fn new(reader: R) -> Self {
+        JsonRecords {
+            stream: Stream::from_iter(JsonLines {
+                reader: Some(reader),
+                offset: 0,
+                line: String::new(),
+                read_line: R::read_line,
+                deserialize: |record| {
+                    serde_json::from_str(record).map_err(|e| JsonRecordError(e.to_string()))
+                },
+            }),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<R, T> Sealed for JsonRecords<R, T> {}
+#[cfg(feature = "serde")]
+impl<'a, R, T> Input<'a> for JsonRecords<R, T>
+where
+    R: 'a,
+    T: Clone + 'a,
+{
+    type Offset = usize;
+    type Token = Result<T, JsonRecordError>;
+    type Span = SimpleSpan<usize>;
+
+    #[inline(always)]
+    fn start(&self) -> Self::Offset {
+        0
+    }
+
+    type TokenMaybe = Self::Token;
+
+    #[inline(always)]
+    unsafe fn next_maybe(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::TokenMaybe>) {
+        self.next(offset)
+    }
+
+    #[inline]
+    unsafe fn span(&self, range: Range<Self::Offset>) -> Self::Span {
+        tokens_span(&self.stream, range)
+    }
+
+    #[inline]
+    fn prev(offs: Self::Offset) -> Self::Offset {
+        offs.saturating_sub(1)
+    }
+
+    #[inline]
+    fn is_boundary(&self, offset: Self::Offset) -> bool {
+        stream_is_boundary(&self.stream, offset)
+    }
+
+    #[inline(always)]
+    unsafe fn token_distance(&self, from: Self::Offset, to: Self::Offset) -> usize {
+        to.saturating_sub(from)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'a, R, T> ExactSizeInput<'a> for JsonRecords<R, T>
+where
+    R: 'a,
+    T: Clone + 'a,
+{
+    #[inline]
+    unsafe fn span_from(&self, range: RangeFrom<Self::Offset>) -> Self::Span {
+        let len = stream_eoi(&self.stream).1;
+        tokens_span(&self.stream, range.start..len)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'a, R, T> ValueInput<'a> for JsonRecords<R, T>
+where
+    R: 'a,
+    T: Clone + 'a,
+{
+    #[inline]
+    unsafe fn next(&self, offset: Self::Offset) -> (Self::Offset, Option<Self::Token>) {
+        let (offset, tok) = self.stream.next(offset);
+        (offset, tok.map(|(tok, _)| tok))
+    }
+}
+
+#[test]
+fn spanned() {
+    fn parser<'a>() -> impl Parser<
+        'a,
+        input::SpannedInput<char, Range<usize>, BoxedStream<'static, (char, Range<usize>)>>,
+        char,
+    > {
+        just('h')
+    }
+
+    let stream = Stream::from_iter(core::iter::once(('h', 0..1))).boxed();
+    let stream = stream.spanned(0..10);
+
+    assert_eq!(parser().parse(stream).into_result(), Ok('h'));
+}
+
+#[test]
+fn offset_iter_error_spans() {
+    let chars = "ab!".chars().enumerate().map(|(i, c)| (c, i * 3));
+    let parser = any::<_, extra::Err<Rich<_>>>()
+        .filter(|c: &char| c.is_alphabetic())
+        .repeated()
+        .then(end());
+
+    let errs = parser
+        .parse(Stream::from_offset_iter(chars, 9))
+        .into_errors();
+    assert_eq!(errs.len(), 1);
+    assert_eq!(errs[0].span(), &SimpleSpan::new(6, 9));
+}
+
+#[test]
+fn spanned_by_zero_width_and_eoi() {
+    let stream = || Stream::from_iter(vec![2, 0, 3]).spanned_by(|len| *len);
+
+    let spans = any::<_, extra::Err<Rich<_>>>()
+        .map_with_span(|_, span| span)
+        .repeated()
+        .collect::<Vec<_>>()
+        .parse(stream())
+        .into_result();
+    assert_eq!(
+        spans,
+        Ok(vec![
+            SimpleSpan::new(0, 2),
+            SimpleSpan::new(2, 2),
+            SimpleSpan::new(2, 5)
+        ]),
+    );
+
+    let errs = any::<_, extra::Err<Rich<_>>>()
+        .repeated()
+        .then(just(1))
+        .parse(stream())
+        .into_errors();
+    assert_eq!(errs.len(), 1);
+    assert_eq!(errs[0].span(), &SimpleSpan::new(5, 5));
+}
+
+#[test]
+fn clone_buffered() {
+    let stream = Stream::from_iter("abcd".chars());
+    let parser = any::<_, extra::Err<Simple<_>>>()
+        .repeated()
+        .collect::<String>();
+
+    // Pull tokens into the buffer before cloning
+    // SAFETY: `0` is the start offset of the stream
+    assert_eq!(unsafe { stream.next(0) }, (1, Some('a')));
+
+    assert_eq!(
+        parser.parse(stream.clone()).into_result().as_deref(),
+        Ok("abcd")
+    );
+    assert_eq!(parser.parse(stream).into_result().as_deref(), Ok("abcd"));
+}
+
+#[test]
+fn push_front_spanned() {
+    let mut stream = Stream::from_iter(vec![2, 3]).spanned_by(|len| *len);
+    stream.push_front(1, SimpleSpan::new(0, 0));
+
+    let spans = any::<_, extra::Err<Rich<_>>>()
+        .map_with_span(|tok, span| (tok, span))
+        .repeated()
+        .collect::<Vec<_>>()
+        .parse(stream)
+        .into_result();
+    assert_eq!(
+        spans,
+        Ok(vec![
+            (1, SimpleSpan::new(0, 0)),
+            (2, SimpleSpan::new(0, 2)),
+            (3, SimpleSpan::new(2, 5))
+        ]),
+    );
+
+    // The length of an exact-size stream accounts for pushed tokens
+    let mut stream = Stream::from_iter(vec!['b', 'c']);
+    stream.push_front('a');
+    // SAFETY: `0` is the start offset of the stream
+    assert_eq!(unsafe { stream.span_from(0..) }, SimpleSpan::new(0, 3));
+}
+
+#[test]
+fn sparse_without_gaps() {
+    let parser = any::<_, extra::Err<Rich<_>>>()
+        .map_with_span(|tok, span: SimpleSpan| (tok, span.start))
+        .repeated()
+        .collect::<Vec<_>>();
+
+    let dense = Stream::from_sparse_iter(vec![('a', 0), ('b', 1)], '_', 2);
+    assert_eq!(
+        parser.parse(dense).into_result(),
+        Ok(vec![('a', 0), ('b', 1)])
+    );
+
+    let empty = Stream::from_sparse_iter(Vec::new(), '_', 0);
+    assert_eq!(parser.parse(empty).into_result(), Ok(vec![]));
+
+    let only_gap = Stream::from_sparse_iter(Vec::new(), '_', 3);
+    assert_eq!(parser.parse(only_gap).into_result(), Ok(vec![('_', 0)]));
+}
+
+#[test]
+fn backtrack_limit_discards_tokens() {
+    let stream = || {
+        Stream::from_iter((0..2000).map(|i| (i % 10, SimpleSpan::new(i, i + 1))))
+            .with_backtrack_limit(3)
+            .spanned(SimpleSpan::new(2000, 2000))
+    };
+
+    // Tokens are read correctly after earlier ones have been discarded
+    let ints = any::<_, extra::Err<Rich<_>>>()
+        .repeated()
+        .collect::<Vec<_>>();
+    let res = ints.parse(stream());
+    assert!(!res.exceeded_backtrack_limit());
+    assert_eq!(res.into_result().map(|ints| ints.len()), Ok(2000));
+
+    // Rewinding within the limit succeeds, even after many tokens have been discarded
+    let pair = any::<_, extra::Err<Rich<_>>>()
+        .then(just(9))
+        .or(any().then(any()))
+        .map_with_span(|_, span: SimpleSpan| span.start);
+    let res = pair.repeated().collect::<Vec<_>>().parse(stream());
+    assert_eq!(res.into_result().map(|spans| spans[999]), Ok(1998));
+
+    // Rewinding beyond the limit fails at the position that could not be reached
+    let long = any::<_, extra::Err<Rich<_>>>()
+        .repeated()
+        .exactly(1500)
+        .then(just(1))
+        .or(any().repeated().exactly(1600).ignored().then(just(0)));
+    let res = long.then(any().repeated()).parse(stream());
+    assert!(res.exceeded_backtrack_limit());
+    assert_eq!(res.fatal_error_offset(), Some(0));
+}
+
+#[test]
+fn spanned_by_backtrack_limit() {
+    let stream = || {
+        Stream::from_iter(core::iter::repeat(2).take(2000))
+            .with_backtrack_limit(3)
+            .spanned_by(|len| *len)
+    };
+
+    // The limit is kept, so tokens are discarded and spans remain correct afterwards
+    let res = any::<_, extra::Err<Rich<_>>>()
+        .map_with_span(|_, span: SimpleSpan| span)
+        .repeated()
+        .collect::<Vec<_>>()
+        .parse(stream());
+    assert!(!res.exceeded_backtrack_limit());
+    assert_eq!(
+        res.into_result().map(|spans| spans[1999]),
+        Ok(SimpleSpan::new(3998, 4000))
+    );
+
+    // Spanning discarded tokens fails as if the parser had backtracked to them
+    let res = any::<_, extra::Err<Rich<_>>>()
+        .repeated()
+        .map_with_span(|_, span: SimpleSpan| span)
+        .parse(stream());
+    assert!(res.exceeded_backtrack_limit());
+    assert_eq!(res.fatal_error_offset(), Some(0));
+}
+
+#[test]
+fn backtrack_limit_wrapped() {
+    let stream = || Stream::from_iter([3u8, 4, 5]).with_backtrack_limit(1);
+    let parser = just::<_, _, extra::Err<EmptyErr>>([1, 2, 3, 4, 9]).or(just([1, 2, 3, 4, 5]));
+
+    // Rewinding into the second input is limited by it, and reported at the chained offset
+    let res = parser.parse((&[1u8, 2][..]).chain(stream()));
+    assert!(res.exceeded_backtrack_limit());
+    assert_eq!(res.fatal_error_offset(), Some(2));
+
+    // Rewinding into the first input is limited by it too
+    let parser = just::<_, _, extra::Err<EmptyErr>>([3, 4, 9]).or(just([3, 4, 5]));
+    let res = parser.then(just(1)).parse(stream().chain(&[1u8][..]));
+    assert!(res.exceeded_backtrack_limit());
+    assert_eq!(res.fatal_error_offset(), Some(0));
+
+    // Reversing reads the whole stream first, so only the last tokens can be reached afterwards
+    let res = any::<_, extra::Err<EmptyErr>>()
+        .repeated()
+        .collect::<Vec<_>>()
+        .parse(Stream::from_iter(0..10).with_backtrack_limit(3).reversed());
+    assert!(res.exceeded_backtrack_limit());
+    assert_eq!(res.fatal_error_offset(), Some(3));
+}
+
+#[test]
+fn require_prefix_lazy() {
+    let pulled = Cell::new(0);
+    let tokens = b"MAGIC"
+        .iter()
+        .copied()
+        .chain(core::iter::repeat(b'x').take(5000));
+    let stream = Stream::from_iter(tokens.inspect(|_| pulled.set(pulled.get() + 1)));
+
+    // The stream pulls tokens in batches, but the rest of the input is not read up front
+    let input = stream.require_prefix(b"MAGIC").unwrap();
+    assert!(pulled.get() < 5005);
+
+    let res = any::<_, extra::Err<EmptyErr>>()
+        .repeated()
+        .count()
+        .parse(input);
+    assert_eq!(res.into_result(), Ok(5000));
+    assert_eq!(pulled.get(), 5005);
+}
+
+#[test]
+fn growable_buffer_resumes() {
+    let parser = any::<_, extra::Err<Rich<_>>>()
+        .repeated()
+        .collect::<Vec<_>>()
+        .then_ignore(end());
+
+    let buffer = GrowableBuffer::new();
+    let res = parser.parse(buffer.input());
+    assert!(res.needs_more_input());
+    assert_eq!(res.fatal_error_offset(), Some(0));
+
+    // Tokens added while parsing are visible to the parser
+    buffer.extend([1, 2]);
+    let parser = any::<_, extra::Err<Rich<_>>>()
+        .map(|tok| {
+            if tok == 1 && buffer.len() == 2 {
+                buffer.extend([3]);
+            }
+            tok
+        })
+        .repeated()
+        .collect::<Vec<_>>();
+    let res = parser.parse(buffer.input());
+    assert!(res.needs_more_input());
+    assert_eq!(res.fatal_error_offset(), Some(3));
+    assert_eq!(
+        res.errors().next().map(|e| e.span()),
+        Some(&SimpleSpan::new(3, 3))
+    );
+
+    buffer.finish();
+    let res = parser.parse(buffer.input());
+    assert!(!res.needs_more_input());
+    assert_eq!(res.into_result(), Ok(vec![1, 2, 3]));
+}
+
+#[test]
+fn growable_buffer_wrapped() {
+    fn parse<'a, I: ValueInput<'a, Token = u8>>(input: I) -> ParseResult<Vec<u8>, EmptyErr> {
+        any::<_, extra::Err<EmptyErr>>()
+            .repeated()
+            .collect()
+            .parse(input)
+    }
+
+    let buffer = GrowableBuffer::new();
+    buffer.extend(*b"cd");
+
+    // The end of the buffer is not the end of the input, so neither wrapper can succeed yet
+    let res = parse((&b"ab"[..]).chain(buffer.input()));
+    assert!(res.needs_more_input());
+    assert_eq!(res.fatal_error_offset(), Some(4));
+    assert_eq!(res.into_result().ok(), None);
+
+    let res = parse(buffer.input().reversed());
+    assert!(res.needs_more_input());
+    assert_eq!(res.fatal_error_offset(), Some(0));
+    assert_eq!(res.into_result().ok(), None);
+
+    // An unfinished first input leaves the position of the second unknown
+    let res = parse(buffer.input().chain(&b"ab"[..]));
+    assert!(res.needs_more_input());
+    assert_eq!(res.fatal_error_offset(), Some(2));
+
+    buffer.finish();
+    assert_eq!(
+        parse((&b"ab"[..]).chain(buffer.input())).into_result(),
+        Ok(b"abcd".to_vec())
+    );
+    assert_eq!(
+        parse(buffer.input().reversed()).into_result(),
+        Ok(b"dc".to_vec())
+    );
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn json_records_spans() {
+    let source = "  1\r\n\n[2, 3]  \n";
+    let records = JsonRecords::<_, serde_json::Value>::new(source.as_bytes());
+    let parser = any::<_, extra::Err<Rich<_>>>()
+        .map_with_span(|rec: Result<_, _>, span: SimpleSpan| (rec.is_ok(), span))
+        .repeated()
+        .collect::<Vec<_>>()
+        .then(empty().map_with_span(|_, span: SimpleSpan| span));
+    assert_eq!(
+        parser.parse(records).into_result(),
+        Ok((
+            vec![
+                (true, SimpleSpan::new(2, 3)),
+                (true, SimpleSpan::new(6, 12))
+            ],
+            SimpleSpan::new(15, 15),
+        )),
+    );
+
+    // Invalid records are produced as errors, and the end of input span follows the last line
+    let records = JsonRecords::<_, u32>::new("1\nx".as_bytes());
+    let parser = any::<_, extra::Err<Rich<_>>>()
+        .repeated()
+        .collect::<Vec<_>>()
+        .then(empty().map_with_span(|_, span: SimpleSpan| span));
+    let (recs, eoi) = parser.parse(records).into_result().unwrap();
+    assert_eq!(recs[0], Ok(1));
+    assert!(recs[1].is_err());
+    assert_eq!(eoi, SimpleSpan::new(3, 3));
 }
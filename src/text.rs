@@ -144,9 +144,13 @@ where
     A: Parser<'a, I, O, E>,
 {
     fn go<M: Mode>(&self, inp: &mut InputRef<'a, '_, I, E>) -> PResult<M, O> {
+        let before = inp.offset();
         inp.skip_while(|c| c.is_whitespace());
+        inp.record_trivia(before);
         let out = self.parser.go::<M>(inp)?;
+        let before = inp.offset();
         inp.skip_while(|c| c.is_whitespace());
+        inp.record_trivia(before);
         Ok(out)
     }
 
@@ -348,14 +352,84 @@ pub fn int<'a, I: ValueInput<'a> + StrInput<'a, C>, C: Char, E: ParserExtra<'a,
 ///
 /// An identifier is defined as an ASCII alphabetic character or an underscore followed by any number of alphanumeric
 /// characters or underscores. The regex pattern for it is `[a-zA-Z_][a-zA-Z0-9_]*`.
+///
+/// To use a different set of identifier characters, see [`ident_by`].
 #[must_use]
 pub fn ident<'a, I: ValueInput<'a> + StrInput<'a, C>, C: Char, E: ParserExtra<'a, I>>(
 ) -> impl Parser<'a, I, &'a C::Str, E> + Copy + Clone {
+    ident_by(AsciiIdent)
+}
+
+/// A classification of characters that determines which may start and continue an identifier. See [`ident_by`].
+///
+/// This is implemented by [`AsciiIdent`], the classification used by [`ident`] and [`keyword`], and by pairs of
+/// predicates of the form `(is_start, is_continue)`.
+pub trait IdentClass<C: Char> {
+    /// Returns true if the character may begin an identifier.
+    fn is_ident_start(&self, c: &C) -> bool;
+
+    /// Returns true if the character may appear in an identifier after the first character.
+    fn is_ident_continue(&self, c: &C) -> bool;
+}
+
+/// The C-style identifier classification used by [`ident`] and [`keyword`]: identifiers start with an ASCII alphabetic
+/// character or an underscore, and continue with ASCII alphanumeric characters or underscores.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct AsciiIdent;
+
+impl<C: Char> IdentClass<C> for AsciiIdent {
+    #[inline]
+    fn is_ident_start(&self, c: &C) -> bool {
+        c.to_char().is_ascii_alphabetic() || c.to_char() == '_'
+    }
+
+    #[inline]
+    fn is_ident_continue(&self, c: &C) -> bool {
+        c.to_char().is_ascii_alphanumeric() || c.to_char() == '_'
+    }
+}
+
+impl<C: Char, S: Fn(&C) -> bool, R: Fn(&C) -> bool> IdentClass<C> for (S, R) {
+    #[inline]
+    fn is_ident_start(&self, c: &C) -> bool {
+        (self.0)(c)
+    }
+
+    #[inline]
+    fn is_ident_continue(&self, c: &C) -> bool {
+        (self.1)(c)
+    }
+}
+
+/// Like [`ident`], but with a custom classification of the characters that may start and continue an identifier.
+///
+/// This is useful for languages with unusual identifier alphabets, such as those that allow `$` in identifiers.
+///
+/// # Examples
+///
+/// ```
+/// # use chumsky::prelude::*;
+/// let is_start = |c: &char| c.is_ascii_alphabetic() || *c == '_' || *c == '$';
+/// let is_continue = |c: &char| c.is_ascii_alphanumeric() || *c == '_' || *c == '$';
+/// let ident = text::ident_by::<_, _, _, extra::Err<Simple<char>>>((is_start, is_continue));
+///
+/// assert_eq!(ident.parse("$foo").into_result(), Ok("$foo"));
+/// assert_eq!(ident.parse("a$b_1").into_result(), Ok("a$b_1"));
+/// assert!(ident.parse("1$").has_errors());
+/// ```
+#[must_use]
+pub fn ident_by<'a, I, C, K, E>(class: K) -> impl Parser<'a, I, &'a C::Str, E> + Copy
+where
+    I: ValueInput<'a> + StrInput<'a, C>,
+    C: Char,
+    K: IdentClass<C> + Copy,
+    E: ParserExtra<'a, I>,
+{
     any()
-        .filter(|c: &C| c.to_char().is_ascii_alphabetic() || c.to_char() == '_')
+        .filter(move |c: &C| class.is_ident_start(c))
         .then(
             any()
-                .filter(|c: &C| c.to_char().is_ascii_alphanumeric() || c.to_char() == '_')
+                .filter(move |c: &C| class.is_ident_continue(c))
                 .repeated(),
         )
         .slice()
@@ -391,11 +465,29 @@ pub fn keyword<
 >(
     keyword: Str,
 ) -> impl Parser<'a, I, &'a C::Str, E> + Clone + 'a
+where
+    C::Str: PartialEq,
+{
+    keyword_by(AsciiIdent, keyword)
+}
+
+/// Like [`keyword`], but with a custom classification of identifier characters. See [`ident_by`].
+pub fn keyword_by<
+    'a,
+    I: ValueInput<'a> + StrInput<'a, C>,
+    C: Char + 'a,
+    K: IdentClass<C> + Copy + 'a,
+    Str: AsRef<C::Str> + 'a + Clone,
+    E: ParserExtra<'a, I> + 'a,
+>(
+    class: K,
+    keyword: Str,
+) -> impl Parser<'a, I, &'a C::Str, E> + Clone + 'a
 where
     C::Str: PartialEq,
 {
     // TODO: use .filter(...), improve error messages
-    ident()
+    ident_by(class)
         .try_map(move |s: &C::Str, span| {
             if s == keyword.as_ref() {
                 Ok(())